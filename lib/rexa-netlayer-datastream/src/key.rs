@@ -0,0 +1,22 @@
+//! Encoding of ed25519 public keys in locator hints.
+
+use ed25519_dalek::{VerifyingKey, PUBLIC_KEY_LENGTH};
+
+/// Encode `key` as lowercase hex.
+pub(crate) fn encode_key(key: &VerifyingKey) -> String {
+    key.as_bytes().iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Decode a key encoded by [`encode_key`].
+pub(crate) fn decode_key(hint: &str) -> Option<VerifyingKey> {
+    if hint.len() != PUBLIC_KEY_LENGTH * 2 || !hint.is_ascii() {
+        return None;
+    }
+    let mut bytes = [0; PUBLIC_KEY_LENGTH];
+    for (byte, pair) in bytes.iter_mut().zip(hint.as_bytes().chunks(2)) {
+        // `pair` is ascii, so this can't fail
+        let pair = std::str::from_utf8(pair).unwrap();
+        *byte = u8::from_str_radix(pair, 16).ok()?;
+    }
+    VerifyingKey::from_bytes(&bytes).ok()
+}
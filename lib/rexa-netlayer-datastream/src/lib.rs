@@ -1,16 +1,45 @@
+//! Netlayers over reliable, ordered byte streams.
+//!
+//! Listener and stream implementations are provided for tokio's types with the `tokio` feature, and
+//! for [`async_io::Async`] (used by smol and async-std) with the `async-io` feature. The I/O traits
+//! expected of each stream half follow `rexa`'s own `tokio` feature, so `async-io` streams are only
+//! usable as netlayers when `rexa` is built without it, and the two features can't be enabled
+//! together. `tokio` is a default feature, so `async-io` requires `default-features = false`.
+
+#[cfg(all(feature = "tokio", feature = "async-io"))]
+compile_error!(
+    "the `tokio` and `async-io` features are mutually exclusive; `tokio` is enabled by default, so \
+     `async-io` requires `default-features = false`"
+);
+
+use ed25519_dalek::{SigningKey, VerifyingKey};
 use rexa::{
+    async_compat::AsyncWrite,
     captp::{CapTpReadExt, CapTpSession, CapTpSessionManager, SessionInitError},
     locator::NodeLocator,
     netlayer::Netlayer,
 };
 
-use tokio::sync::RwLock;
+#[cfg(any(feature = "tls", feature = "noise"))]
+mod key;
+#[cfg(any(feature = "tls", feature = "noise"))]
+mod pending;
 
 #[cfg(feature = "tcp")]
 mod tcp;
 #[cfg(feature = "tcp")]
 pub use tcp::*;
 
+#[cfg(feature = "tls")]
+mod tls;
+#[cfg(feature = "tls")]
+pub use tls::*;
+
+#[cfg(feature = "noise")]
+mod noise;
+#[cfg(feature = "noise")]
+pub use noise::*;
+
 #[cfg(all(feature = "unix", target_family = "unix"))]
 mod unix;
 #[cfg(all(feature = "unix", target_family = "unix"))]
@@ -32,6 +61,10 @@ pub trait AsyncStreamListener: Sized {
            + Unpin;
     fn local_addr(&self) -> Result<Self::AddressOutput, Self::Error>;
     fn locator(&self) -> Result<NodeLocator<'_>, Self::Error>;
+    /// The key the listener authenticates the node with, if any, which sessions must then use too.
+    fn signing_key(&self) -> Option<&SigningKey> {
+        None
+    }
 }
 
 pub trait AsyncDataStream: Sized {
@@ -42,6 +75,14 @@ pub trait AsyncDataStream: Sized {
         addr: &NodeLocator<'loc>,
     ) -> impl std::future::Future<Output = Result<Self, Self::Error>> + std::marker::Send;
     fn split(self) -> (Self::ReadHalf, Self::WriteHalf);
+    /// Information about the remote end, made available by [`CapTpSession::peer_info`].
+    fn peer_info(&self) -> Option<Box<dyn std::any::Any + Send + Sync>> {
+        None
+    }
+    /// The key pinned by `addr`, which a connection to it would authenticate, if any.
+    fn pinned_key(_addr: &NodeLocator<'_>) -> Option<VerifyingKey> {
+        None
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -62,7 +103,7 @@ type DataStreamSessionManager<Listener> = CapTpSessionManager<
 #[derive(Debug)]
 pub struct DataStreamNetlayer<Listener: AsyncStreamListener> {
     listeners: Vec<Listener>,
-    manager: RwLock<DataStreamSessionManager<Listener>>,
+    manager: DataStreamSessionManager<Listener>,
 }
 
 impl<Listener: AsyncStreamListener> Netlayer for DataStreamNetlayer<Listener>
@@ -70,7 +111,7 @@ where
     Listener::Stream: AsyncDataStream,
     Listener::Error: std::error::Error,
     <Listener::Stream as AsyncDataStream>::ReadHalf: CapTpReadExt + Unpin + Send,
-    <Listener::Stream as AsyncDataStream>::WriteHalf: tokio::io::AsyncWrite + Unpin + Send,
+    <Listener::Stream as AsyncDataStream>::WriteHalf: AsyncWrite + Unpin + Send + 'static,
     <Listener::Stream as AsyncDataStream>::Error: std::error::Error,
     Self: Sync,
 {
@@ -82,8 +123,12 @@ where
         &self,
         locator: &NodeLocator<'loc>,
     ) -> Result<CapTpSession<Self::Reader, Self::Writer>, Self::Error> {
-        if let Some(session) = self.manager.read().await.get(&locator.designator) {
-            return Ok(session.clone());
+        // a node bound with its own key is recognized however it's reached
+        let existing = <Listener::Stream as AsyncDataStream>::pinned_key(locator)
+            .and_then(|key| self.manager.get_by_key(&key))
+            .or_else(|| self.manager.get(locator));
+        if let Some(session) = existing {
+            return Ok(session);
         }
 
         tracing::debug!(
@@ -92,18 +137,21 @@ where
             "starting connection"
         );
 
-        let (reader, writer) = <Listener::Stream as AsyncDataStream>::connect(locator)
+        let stream = <Listener::Stream as AsyncDataStream>::connect(locator)
             .await
-            .map_err(Error::Stream)?
-            .split();
+            .map_err(Error::Stream)?;
+        let peer_info = stream.peer_info();
+        let (reader, writer) = stream.split();
 
-        self.manager
-            .write()
-            .await
+        let session = self
+            .manager
             .init_session(reader, writer)
+            .with_peer_info(peer_info)
             .and_connect(self.locators().pop().unwrap())
-            .await
-            .map_err(From::from)
+            .await?;
+        // the remote node may know itself by a different address than the one dialed
+        self.manager.alias(locator, *session.remote_vkey());
+        Ok(session)
     }
 
     async fn accept(&self) -> Result<CapTpSession<Self::Reader, Self::Writer>, Self::Error> {
@@ -112,18 +160,18 @@ where
             "accepting connection"
         );
 
-        let (reader, writer) =
+        let stream =
             futures::future::select_all(self.listeners.iter().map(|listener| listener.accept()))
                 .await
                 .0
                 .map_err(Error::Listener)?
-                .0
-                .split();
+                .0;
+        let peer_info = stream.peer_info();
+        let (reader, writer) = stream.split();
 
         self.manager
-            .write()
-            .await
             .init_session(reader, writer)
+            .with_peer_info(peer_info)
             .and_accept(self.locators().pop().unwrap())
             .await
             .map_err(From::from)
@@ -138,10 +186,24 @@ where
 }
 
 impl<Listener: AsyncStreamListener> DataStreamNetlayer<Listener> {
+    /// Construct a netlayer whose sessions use the key of the first listener which authenticates the
+    /// node, or a newly generated signing key if none do.
     pub fn new(listeners: Vec<Listener>) -> Self {
+        match listeners.iter().find_map(Listener::signing_key).cloned() {
+            Some(signing_key) => Self::with_signing_key(listeners, signing_key),
+            None => Self {
+                listeners,
+                manager: CapTpSessionManager::new(),
+            },
+        }
+    }
+
+    /// Construct a netlayer whose sessions use `signing_key`. Listeners which authenticate the
+    /// node, such as those for TLS and Noise, should be bound with the same key.
+    pub fn with_signing_key(listeners: Vec<Listener>, signing_key: SigningKey) -> Self {
         Self {
             listeners,
-            manager: RwLock::new(CapTpSessionManager::new()),
+            manager: CapTpSessionManager::with_signing_key(signing_key),
         }
     }
 
@@ -154,7 +216,17 @@ impl<Listener: AsyncStreamListener> DataStreamNetlayer<Listener> {
         &mut self,
         addr: Listener::AddressInput<'_>,
     ) -> Result<(), Listener::Error> {
-        self.listeners.push(Listener::bind(addr).await?);
+        let listener = Listener::bind(addr).await?;
+        if listener
+            .signing_key()
+            .is_some_and(|key| key != self.manager.signing_key())
+        {
+            tracing::warn!(
+                local = ?self.locators(),
+                "listener authenticates with a different key than the netlayer's sessions"
+            );
+        }
+        self.listeners.push(listener);
         Ok(())
     }
 }
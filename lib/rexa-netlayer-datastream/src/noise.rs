@@ -0,0 +1,439 @@
+//! An encrypting wrapper for any datastream, using the Noise protocol.
+//!
+//! Listeners run the responder side of a `Noise_XX` handshake, with a static key derived from the
+//! node's ed25519 key, and advertise the ed25519 key in the `noise-key` hint of their locators.
+//! Connecting streams use a fresh static key, and reject responders whose static key doesn't match
+//! the locator they were given.
+
+use std::{
+    borrow::Cow,
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+};
+
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use futures::FutureExt;
+use parking_lot::Mutex;
+use rexa::locator::NodeLocator;
+use snow::{HandshakeState, TransportState};
+use syrup::symbol;
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+use super::{
+    key::{decode_key, encode_key},
+    pending::PendingHandshakes,
+    AsyncDataStream, AsyncStreamListener,
+};
+
+pub type NoiseNetlayer<Listener> = super::DataStreamNetlayer<NoiseListener<Listener>>;
+
+impl<Listener> NoiseNetlayer<Listener>
+where
+    Listener: AsyncStreamListener + Send + Sync + 'static,
+    Listener::Stream: Send,
+    <Listener::Stream as AsyncDataStream>::ReadHalf: AsyncRead + Unpin + Send,
+    <Listener::Stream as AsyncDataStream>::WriteHalf: AsyncWrite + Unpin + Send,
+    Listener::AddressOutput: std::fmt::Debug + Send,
+    Listener::Error: std::fmt::Debug + Send,
+{
+    /// Bind to `addr`, using `key` both for sessions and for the listener's static key, so that
+    /// the `noise-key` hint of its locator is the node's own key.
+    pub async fn bind_with_key(
+        addr: Listener::AddressInput<'_>,
+        key: SigningKey,
+    ) -> Result<Self, NoiseError<Listener::Error>> {
+        Self::bind((addr, &key)).await
+    }
+}
+
+const PARAMS: &str = "Noise_XX_25519_ChaChaPoly_BLAKE2s";
+/// Every frame is prefixed with its length, as a big-endian u16.
+const LEN_PREFIX: usize = 2;
+const MAX_MESSAGE_LEN: usize = u16::MAX as usize;
+const TAG_LEN: usize = 16;
+const MAX_PAYLOAD_LEN: usize = MAX_MESSAGE_LEN - TAG_LEN;
+
+#[derive(Debug, thiserror::Error)]
+pub enum HandshakeError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Noise(#[from] snow::Error),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum NoiseError<Inner> {
+    #[error(transparent)]
+    Inner(Inner),
+    #[error(transparent)]
+    Handshake(#[from] HandshakeError),
+    #[error("expected connect address to specify noise-key")]
+    MissingKey,
+    #[error("invalid noise-key hint")]
+    InvalidKey,
+    #[error("remote static key does not match noise-key hint")]
+    KeyMismatch,
+}
+
+/// Build a handshake state, generating a static key if `private_key` is `None`.
+fn build(private_key: Option<&[u8]>, initiator: bool) -> Result<HandshakeState, HandshakeError> {
+    let generated;
+    // PARAMS is a valid pattern
+    let builder = snow::Builder::new(PARAMS.parse().unwrap());
+    let private_key = match private_key {
+        Some(key) => key,
+        None => {
+            generated = builder.generate_keypair()?;
+            &generated.private
+        }
+    };
+    let builder = builder.local_private_key(private_key);
+    if initiator {
+        builder.build_initiator().map_err(From::from)
+    } else {
+        builder.build_responder().map_err(From::from)
+    }
+}
+
+/// Run a handshake to completion, returning the transport state and the remote's static key.
+async fn handshake<Reader, Writer>(
+    mut state: HandshakeState,
+    reader: &mut Reader,
+    writer: &mut Writer,
+) -> Result<(TransportState, Vec<u8>), HandshakeError>
+where
+    Reader: AsyncRead + Unpin,
+    Writer: AsyncWrite + Unpin,
+{
+    let mut buf = vec![0; MAX_MESSAGE_LEN];
+    while !state.is_handshake_finished() {
+        if state.is_my_turn() {
+            let len = state.write_message(&[], &mut buf)?;
+            // handshake messages are always shorter than MAX_MESSAGE_LEN
+            writer.write_u16(len as u16).await?;
+            writer.write_all(&buf[..len]).await?;
+            writer.flush().await?;
+        } else {
+            let mut msg = vec![0; reader.read_u16().await? as usize];
+            reader.read_exact(&mut msg).await?;
+            state.read_message(&msg, &mut buf)?;
+        }
+    }
+    let remote = state
+        .get_remote_static()
+        .map(<[u8]>::to_vec)
+        .unwrap_or_default();
+    Ok((state.into_transport_mode()?, remote))
+}
+
+fn noise_io(error: snow::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+/// Accepts connections from the inner listener in the background, running each handshake in a
+/// task of its own.
+pub struct NoiseListener<Listener: AsyncStreamListener> {
+    inner: Arc<Listener>,
+    pending: PendingHandshakes<
+        NoiseStream<Listener::Stream>,
+        Listener::AddressOutput,
+        NoiseError<Listener::Error>,
+    >,
+    key: SigningKey,
+}
+
+impl<Listener> std::fmt::Debug for NoiseListener<Listener>
+where
+    Listener: AsyncStreamListener + std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NoiseListener")
+            .field("inner", &self.inner)
+            .field("key", &encode_key(&self.key.verifying_key()))
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Listener> AsyncStreamListener for NoiseListener<Listener>
+where
+    Listener: AsyncStreamListener + Send + Sync + 'static,
+    Listener::Stream: Send,
+    <Listener::Stream as AsyncDataStream>::ReadHalf: AsyncRead + Unpin + Send,
+    <Listener::Stream as AsyncDataStream>::WriteHalf: AsyncWrite + Unpin + Send,
+    Listener::AddressOutput: std::fmt::Debug + Send,
+    Listener::Error: std::fmt::Debug + Send,
+{
+    /// The inner transport; the `noise-key` hint marks a locator as encrypted.
+    const TRANSPORT: &'static str = Listener::TRANSPORT;
+    type AddressInput<'addr> = (Listener::AddressInput<'addr>, &'addr SigningKey);
+    type AddressOutput = Listener::AddressOutput;
+    type Error = NoiseError<Listener::Error>;
+    type Stream = NoiseStream<Listener::Stream>;
+
+    async fn bind((addr, key): Self::AddressInput<'_>) -> Result<Self, Self::Error> {
+        let inner = Arc::new(Listener::bind(addr).await.map_err(NoiseError::Inner)?);
+        let private_key = key.to_scalar_bytes();
+        let pending = PendingHandshakes::spawn(
+            {
+                let inner = inner.clone();
+                move || {
+                    let inner = inner.clone();
+                    async move { inner.accept().await.map_err(NoiseError::Inner) }
+                }
+            },
+            move |stream: Listener::Stream| async move {
+                let (mut reader, mut writer) = stream.split();
+                let state = build(Some(&private_key), false)?;
+                let (transport, _) = handshake(state, &mut reader, &mut writer).await?;
+                Ok::<_, Self::Error>(NoiseStream::new(reader, writer, transport))
+            },
+        );
+        Ok(Self {
+            inner,
+            pending,
+            key: key.clone(),
+        })
+    }
+
+    fn accept(
+        &self,
+    ) -> impl std::future::Future<Output = Result<(Self::Stream, Self::AddressOutput), Self::Error>>
+           + std::marker::Send
+           + Unpin {
+        self.pending.accept().boxed()
+    }
+
+    fn local_addr(&self) -> Result<Self::AddressOutput, Self::Error> {
+        self.inner.local_addr().map_err(NoiseError::Inner)
+    }
+
+    fn locator(&self) -> Result<NodeLocator<'_>, Self::Error> {
+        let mut locator = self.inner.locator().map_err(NoiseError::Inner)?;
+        locator.hints.insert(
+            symbol!["noise-key"],
+            Cow::Owned(encode_key(&self.key.verifying_key())),
+        );
+        Ok(locator)
+    }
+
+    fn signing_key(&self) -> Option<&SigningKey> {
+        Some(&self.key)
+    }
+}
+
+/// A datastream encrypted with an established Noise session.
+pub struct NoiseStream<Stream: AsyncDataStream> {
+    reader: Stream::ReadHalf,
+    writer: Stream::WriteHalf,
+    transport: TransportState,
+}
+
+impl<Stream: AsyncDataStream> NoiseStream<Stream> {
+    fn new(reader: Stream::ReadHalf, writer: Stream::WriteHalf, transport: TransportState) -> Self {
+        Self {
+            reader,
+            writer,
+            transport,
+        }
+    }
+}
+
+impl<Stream> AsyncDataStream for NoiseStream<Stream>
+where
+    Stream: AsyncDataStream + Send,
+    Stream::ReadHalf: AsyncRead + Unpin + Send,
+    Stream::WriteHalf: AsyncWrite + Unpin + Send,
+{
+    type ReadHalf = NoiseReadHalf<Stream::ReadHalf>;
+    type WriteHalf = NoiseWriteHalf<Stream::WriteHalf>;
+    type Error = NoiseError<Stream::Error>;
+
+    async fn connect<'loc>(addr: &NodeLocator<'loc>) -> Result<Self, Self::Error> {
+        let key = addr.hint("noise-key").ok_or(NoiseError::MissingKey)?;
+        let key = decode_key(key).ok_or(NoiseError::InvalidKey)?;
+        let (mut reader, mut writer) = Stream::connect(addr)
+            .await
+            .map_err(NoiseError::Inner)?
+            .split();
+        let state = build(None, true)?;
+        let (transport, remote) = handshake(state, &mut reader, &mut writer).await?;
+        if remote != key.to_montgomery().to_bytes() {
+            return Err(NoiseError::KeyMismatch);
+        }
+        Ok(Self::new(reader, writer, transport))
+    }
+
+    fn split(self) -> (Self::ReadHalf, Self::WriteHalf) {
+        let transport = Arc::new(Mutex::new(self.transport));
+        (
+            NoiseReadHalf {
+                inner: self.reader,
+                transport: transport.clone(),
+                frame: vec![0; LEN_PREFIX + MAX_MESSAGE_LEN],
+                filled: 0,
+                plain: Vec::new(),
+                pos: 0,
+            },
+            NoiseWriteHalf {
+                inner: self.writer,
+                transport,
+                frame: Vec::new(),
+                written: 0,
+                accepted: 0,
+            },
+        )
+    }
+
+    fn pinned_key(addr: &NodeLocator<'_>) -> Option<VerifyingKey> {
+        decode_key(addr.hint("noise-key")?)
+    }
+}
+
+/// Decrypts frames read from the inner stream.
+pub struct NoiseReadHalf<Reader> {
+    inner: Reader,
+    transport: Arc<Mutex<TransportState>>,
+    /// The frame being read, including its length prefix.
+    frame: Vec<u8>,
+    filled: usize,
+    /// The decrypted contents of the last frame.
+    plain: Vec<u8>,
+    pos: usize,
+}
+
+impl<Reader: AsyncRead + Unpin> AsyncBufRead for NoiseReadHalf<Reader> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+        while this.pos == this.plain.len() {
+            let needed = match this.filled {
+                0 | 1 => LEN_PREFIX,
+                _ => LEN_PREFIX + u16::from_be_bytes([this.frame[0], this.frame[1]]) as usize,
+            };
+            if this.filled == needed {
+                if needed == LEN_PREFIX {
+                    return Poll::Ready(Err(io::ErrorKind::InvalidData.into()));
+                }
+                this.plain.resize(needed - LEN_PREFIX, 0);
+                let len = this
+                    .transport
+                    .lock()
+                    .read_message(&this.frame[LEN_PREFIX..needed], &mut this.plain)
+                    .map_err(noise_io)?;
+                this.plain.truncate(len);
+                this.pos = 0;
+                this.filled = 0;
+                continue;
+            }
+            let mut buf = ReadBuf::new(&mut this.frame[this.filled..needed]);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut buf))?;
+            match buf.filled().len() {
+                0 if this.filled == 0 => return Poll::Ready(Ok(&[])),
+                0 => return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into())),
+                read => this.filled += read,
+            }
+        }
+        Poll::Ready(Ok(&this.plain[this.pos..]))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let this = self.get_mut();
+        this.pos = (this.pos + amt).min(this.plain.len());
+    }
+}
+
+impl<Reader: AsyncRead + Unpin> AsyncRead for NoiseReadHalf<Reader> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let available = ready!(Pin::new(&mut *this).poll_fill_buf(cx))?;
+        let amt = available.len().min(buf.remaining());
+        buf.put_slice(&available[..amt]);
+        Pin::new(this).consume(amt);
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Encrypts writes into frames for the inner stream.
+///
+/// Each write is encrypted as soon as it's made, so a write which returns [`Poll::Pending`] must be
+/// retried before anything else is written, as [`AsyncWriteExt::write_all`] does.
+pub struct NoiseWriteHalf<Writer> {
+    inner: Writer,
+    transport: Arc<Mutex<TransportState>>,
+    /// The encrypted frame being written, including its length prefix.
+    frame: Vec<u8>,
+    written: usize,
+    /// The length of the write which produced `frame`, if it returned [`Poll::Pending`].
+    accepted: usize,
+}
+
+impl<Writer: AsyncWrite + Unpin> NoiseWriteHalf<Writer> {
+    fn poll_write_frame(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.written < self.frame.len() {
+            let written =
+                ready!(Pin::new(&mut self.inner).poll_write(cx, &self.frame[self.written..]))?;
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.written += written;
+        }
+        self.frame.clear();
+        self.written = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<Writer: AsyncWrite + Unpin> AsyncWrite for NoiseWriteHalf<Writer> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.accepted > 0 {
+            // retrying a write which has already been encrypted
+            ready!(this.poll_write_frame(cx))?;
+            return Poll::Ready(Ok(std::mem::take(&mut this.accepted)));
+        }
+        ready!(this.poll_write_frame(cx))?;
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        let amt = buf.len().min(MAX_PAYLOAD_LEN);
+        this.frame.resize(LEN_PREFIX + MAX_MESSAGE_LEN, 0);
+        let len = this
+            .transport
+            .lock()
+            .write_message(&buf[..amt], &mut this.frame[LEN_PREFIX..])
+            .map_err(noise_io)?;
+        // len <= MAX_MESSAGE_LEN
+        this.frame[..LEN_PREFIX].copy_from_slice(&(len as u16).to_be_bytes());
+        this.frame.truncate(LEN_PREFIX + len);
+
+        match this.poll_write_frame(cx) {
+            Poll::Ready(res) => Poll::Ready(res.map(|_| amt)),
+            Poll::Pending => {
+                this.accepted = amt;
+                Poll::Pending
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_frame(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_frame(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
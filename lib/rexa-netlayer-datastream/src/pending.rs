@@ -0,0 +1,86 @@
+//! Handshakes run outside of `accept`, so that a slow or stalled peer doesn't hold up the
+//! connections behind it.
+
+use std::{fmt::Debug, future::Future, time::Duration};
+
+use tokio::{
+    sync::{mpsc, Mutex},
+    task::JoinHandle,
+};
+
+/// How long an accepted connection has to complete its handshake before it's dropped.
+pub(crate) const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// How many connections may be handshaking or waiting for `accept` at once.
+const BACKLOG: usize = 32;
+
+/// Connections accepted and handshaken by a background task.
+pub(crate) struct PendingHandshakes<Stream, Addr, Error> {
+    accepted: Mutex<mpsc::Receiver<Result<(Stream, Addr), Error>>>,
+    task: JoinHandle<()>,
+}
+
+impl<Stream, Addr, Error> PendingHandshakes<Stream, Addr, Error>
+where
+    Stream: Send + 'static,
+    Addr: Debug + Send + 'static,
+    Error: Debug + Send + 'static,
+{
+    /// Spawn a task which accepts raw connections with `accept`, and runs `handshake` on each in a
+    /// task of its own. Failed handshakes are logged and dropped; errors from `accept` are passed
+    /// on.
+    pub(crate) fn spawn<Raw, Accept, AcceptFut, Handshake, HandshakeFut>(
+        mut accept: Accept,
+        handshake: Handshake,
+    ) -> Self
+    where
+        Accept: FnMut() -> AcceptFut + Send + 'static,
+        AcceptFut: Future<Output = Result<(Raw, Addr), Error>> + Send,
+        Handshake: Fn(Raw) -> HandshakeFut + Send + 'static,
+        HandshakeFut: Future<Output = Result<Stream, Error>> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel(BACKLOG);
+        let task = tokio::spawn(async move {
+            // reserve a slot before accepting, so that at most BACKLOG connections are held
+            while let Ok(permit) = sender.clone().reserve_owned().await {
+                let (raw, addr) = match accept().await {
+                    Ok(accepted) => accepted,
+                    Err(error) => {
+                        permit.send(Err(error));
+                        continue;
+                    }
+                };
+                let handshake = tokio::time::timeout(HANDSHAKE_TIMEOUT, handshake(raw));
+                tokio::spawn(async move {
+                    match handshake.await {
+                        Ok(Ok(stream)) => {
+                            permit.send(Ok((stream, addr)));
+                        }
+                        Ok(Err(error)) => tracing::debug!(?addr, ?error, "handshake failed"),
+                        Err(_) => tracing::debug!(?addr, "handshake timed out"),
+                    }
+                });
+            }
+        });
+        Self {
+            accepted: Mutex::new(receiver),
+            task,
+        }
+    }
+
+    /// Wait for the next handshaken connection.
+    pub(crate) async fn accept(&self) -> Result<(Stream, Addr), Error> {
+        self.accepted
+            .lock()
+            .await
+            .recv()
+            .await
+            // the task holds a sender until it's aborted on drop
+            .expect("accept task should run until the listener is dropped")
+    }
+}
+
+impl<Stream, Addr, Error> Drop for PendingHandshakes<Stream, Addr, Error> {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -6,9 +6,18 @@ use syrup::symbol;
 
 use super::{AsyncDataStream, AsyncStreamListener};
 
+const TRANSPORT: &str = "tcpip";
+
+#[cfg(feature = "tokio")]
 pub type TcpIpNetlayer = super::DataStreamNetlayer<tokio::net::TcpListener>;
+#[cfg(feature = "async-io")]
+pub type AsyncIoTcpNetlayer = super::DataStreamNetlayer<async_io::Async<std::net::TcpListener>>;
 
-impl TcpIpNetlayer {
+impl<Listener> super::DataStreamNetlayer<Listener>
+where
+    Listener: AsyncStreamListener<AddressOutput = SocketAddr>,
+    Listener::Error: std::fmt::Debug,
+{
     pub fn addresses(&self) -> impl Iterator<Item = SocketAddr> + '_ {
         self.listeners
             .iter()
@@ -16,8 +25,17 @@ impl TcpIpNetlayer {
     }
 }
 
+fn locator(addr: SocketAddr) -> NodeLocator<'static> {
+    NodeLocator {
+        designator: Cow::Owned(addr.ip().to_string()),
+        transport: Cow::Borrowed(TRANSPORT),
+        hints: HashMap::from_iter([(symbol!["port"], Cow::Owned(addr.port().to_string()))]),
+    }
+}
+
+#[cfg(feature = "tokio")]
 impl AsyncStreamListener for tokio::net::TcpListener {
-    const TRANSPORT: &'static str = "tcpip";
+    const TRANSPORT: &'static str = TRANSPORT;
     /// FIX :: [permit impl trait in type alias](https://github.com/rust-lang/rust/issues/63063)
     type AddressInput<'addr> = &'addr SocketAddr;
     type AddressOutput = std::net::SocketAddr;
@@ -40,12 +58,35 @@ impl AsyncStreamListener for tokio::net::TcpListener {
     }
 
     fn locator(&self) -> Result<NodeLocator<'static>, Self::Error> {
-        let addr = self.local_addr()?;
-        Ok(NodeLocator {
-            designator: Cow::Owned(addr.ip().to_string()),
-            transport: Cow::Borrowed(Self::TRANSPORT),
-            hints: HashMap::from_iter([(symbol!["port"], Cow::Owned(addr.port().to_string()))]),
-        })
+        Ok(locator(self.local_addr()?))
+    }
+}
+
+#[cfg(feature = "async-io")]
+impl AsyncStreamListener for async_io::Async<std::net::TcpListener> {
+    const TRANSPORT: &'static str = TRANSPORT;
+    type AddressInput<'addr> = &'addr SocketAddr;
+    type AddressOutput = std::net::SocketAddr;
+    type Error = std::io::Error;
+    type Stream = async_io::Async<std::net::TcpStream>;
+
+    async fn bind(addr: Self::AddressInput<'_>) -> Result<Self, Self::Error> {
+        async_io::Async::<std::net::TcpListener>::bind(*addr)
+    }
+
+    fn accept(
+        &self,
+    ) -> impl std::future::Future<Output = Result<(Self::Stream, SocketAddr), Self::Error>> + Send + Unpin
+    {
+        async_io::Async::<std::net::TcpListener>::accept(self).boxed()
+    }
+
+    fn local_addr(&self) -> Result<Self::AddressOutput, Self::Error> {
+        self.get_ref().local_addr()
+    }
+
+    fn locator(&self) -> Result<NodeLocator<'static>, Self::Error> {
+        Ok(locator(self.local_addr()?))
     }
 }
 
@@ -57,8 +98,11 @@ pub enum TcpConnectError {
     MissingPort,
     #[error(transparent)]
     ParsePort(#[from] std::num::ParseIntError),
+    #[error(transparent)]
+    ParseAddr(#[from] std::net::AddrParseError),
 }
 
+#[cfg(feature = "tokio")]
 impl AsyncDataStream for tokio::net::TcpStream {
     type ReadHalf = tokio::net::tcp::OwnedReadHalf;
     type WriteHalf = tokio::net::tcp::OwnedWriteHalf;
@@ -77,3 +121,24 @@ impl AsyncDataStream for tokio::net::TcpStream {
         tokio::net::TcpStream::into_split(self)
     }
 }
+
+#[cfg(feature = "async-io")]
+impl AsyncDataStream for async_io::Async<std::net::TcpStream> {
+    type ReadHalf = futures::io::BufReader<futures::io::ReadHalf<Self>>;
+    type WriteHalf = futures::io::WriteHalf<Self>;
+    type Error = TcpConnectError;
+
+    async fn connect<'loc>(addr: &NodeLocator<'loc>) -> Result<Self, Self::Error> {
+        // async-io doesn't resolve hostnames, but tcpip designators are always ip addresses
+        let ip = addr.designator.parse::<std::net::IpAddr>()?;
+        let port = addr.hint_into("port").ok_or(<Self::Error>::MissingPort)??;
+        async_io::Async::<std::net::TcpStream>::connect(SocketAddr::new(ip, port))
+            .await
+            .map_err(From::from)
+    }
+
+    fn split(self) -> (Self::ReadHalf, Self::WriteHalf) {
+        let (reader, writer) = futures::AsyncReadExt::split(self);
+        (futures::io::BufReader::new(reader), writer)
+    }
+}
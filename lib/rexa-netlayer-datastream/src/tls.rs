@@ -0,0 +1,256 @@
+//! TCP with TLS, using certificates derived from the node's ed25519 key.
+//!
+//! Listeners present a self-signed certificate for their key, and advertise the key in the
+//! `tls-key` hint of their locators. Connecting streams accept only a certificate for the key in
+//! the locator they were given, so no certificate authority is involved.
+
+use std::{borrow::Cow, collections::HashMap, net::SocketAddr, sync::Arc};
+
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use futures::FutureExt;
+use rexa::locator::{AsSocketAddrError, NodeLocator};
+use syrup::symbol;
+use tokio::net::TcpStream;
+use tokio_rustls::{
+    rustls::{
+        self,
+        client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        crypto::{CryptoProvider, WebPkiSupportedAlgorithms},
+        pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName, UnixTime},
+        DigitallySignedStruct, SignatureScheme,
+    },
+    TlsAcceptor, TlsConnector, TlsStream,
+};
+
+use super::{
+    key::{decode_key, encode_key},
+    pending::PendingHandshakes,
+    AsyncDataStream, AsyncStreamListener,
+};
+
+pub type TlsTcpNetlayer = super::DataStreamNetlayer<TlsListener>;
+
+impl TlsTcpNetlayer {
+    /// Bind to `addr`, using `key` both for sessions and for the listener's certificate, so that
+    /// the `tls-key` hint of its locator is the node's own key.
+    pub async fn bind_with_key(addr: &SocketAddr, key: SigningKey) -> Result<Self, TlsError> {
+        Self::bind((addr, &key)).await
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TlsError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Tls(#[from] rustls::Error),
+    #[error(transparent)]
+    Certificate(#[from] rcgen::Error),
+    #[error(transparent)]
+    Address(#[from] AsSocketAddrError),
+    #[error("expected connect address to specify tls-key")]
+    MissingKey,
+    #[error("invalid tls-key hint")]
+    InvalidKey,
+}
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+/// DER encoding of the PKCS#8 v1 structure for an ed25519 key, minus the key itself.
+const PKCS8_PREFIX: [u8; 16] = [
+    0x30, 0x2e, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x22, 0x04, 0x20,
+];
+
+/// DER encoding of the SubjectPublicKeyInfo for an ed25519 key, minus the key itself.
+const SPKI_PREFIX: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+
+/// Build a self-signed certificate for `key`, valid for `addr`.
+fn server_config(key: &SigningKey, addr: &SocketAddr) -> Result<rustls::ServerConfig, TlsError> {
+    let mut pkcs8 = PKCS8_PREFIX.to_vec();
+    pkcs8.extend_from_slice(key.as_bytes());
+    let pkcs8 = PrivatePkcs8KeyDer::from(pkcs8);
+
+    let key_pair = rcgen::KeyPair::from_pkcs8_der_and_sign_algo(&pkcs8, &rcgen::PKCS_ED25519)?;
+    let cert = rcgen::CertificateParams::new(vec![addr.ip().to_string()])?.self_signed(&key_pair)?;
+
+    rustls::ServerConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(vec![cert.der().clone()], PrivateKeyDer::Pkcs8(pkcs8))
+        .map_err(From::from)
+}
+
+/// Accepts only certificates for a specific ed25519 key.
+#[derive(Debug)]
+struct PinnedKey {
+    spki: Vec<u8>,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl PinnedKey {
+    fn new(key: &VerifyingKey) -> Self {
+        let mut spki = SPKI_PREFIX.to_vec();
+        spki.extend_from_slice(key.as_bytes());
+        Self {
+            spki,
+            algorithms: provider().signature_verification_algorithms,
+        }
+    }
+}
+
+impl ServerCertVerifier for PinnedKey {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let (_, cert) = x509_parser::parse_x509_certificate(end_entity).map_err(|_| {
+            rustls::Error::InvalidCertificate(rustls::CertificateError::BadEncoding)
+        })?;
+        if cert.public_key().raw == self.spki {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::InvalidCertificate(
+                rustls::CertificateError::ApplicationVerificationFailure,
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
+
+/// Accepts TCP connections in the background, running each TLS handshake in a task of its own.
+pub struct TlsListener {
+    pending: PendingHandshakes<TlsStream<TcpStream>, SocketAddr, TlsError>,
+    local_addr: SocketAddr,
+    key: SigningKey,
+}
+
+impl std::fmt::Debug for TlsListener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TlsListener")
+            .field("local_addr", &self.local_addr)
+            .field("key", &encode_key(&self.key.verifying_key()))
+            .finish_non_exhaustive()
+    }
+}
+
+impl AsyncStreamListener for TlsListener {
+    const TRANSPORT: &'static str = "tcp+tls";
+    type AddressInput<'addr> = (&'addr SocketAddr, &'addr SigningKey);
+    type AddressOutput = SocketAddr;
+    type Error = TlsError;
+    type Stream = TlsStream<TcpStream>;
+
+    async fn bind((addr, key): Self::AddressInput<'_>) -> Result<Self, Self::Error> {
+        let listener = Arc::new(tokio::net::TcpListener::bind(addr).await?);
+        // bind first, so that the certificate names the address actually bound
+        let local_addr = listener.local_addr()?;
+        let acceptor = TlsAcceptor::from(Arc::new(server_config(key, &local_addr)?));
+        let pending = PendingHandshakes::spawn(
+            move || {
+                let listener = listener.clone();
+                async move { listener.accept().await.map_err(TlsError::from) }
+            },
+            move |stream: TcpStream| {
+                let acceptor = acceptor.clone();
+                async move { Ok::<_, TlsError>(TlsStream::from(acceptor.accept(stream).await?)) }
+            },
+        );
+        Ok(Self {
+            pending,
+            local_addr,
+            key: key.clone(),
+        })
+    }
+
+    fn accept(
+        &self,
+    ) -> impl std::future::Future<Output = Result<(Self::Stream, SocketAddr), Self::Error>> + Send + Unpin
+    {
+        self.pending.accept().boxed()
+    }
+
+    fn local_addr(&self) -> Result<Self::AddressOutput, Self::Error> {
+        Ok(self.local_addr)
+    }
+
+    fn locator(&self) -> Result<NodeLocator<'static>, Self::Error> {
+        let addr = self.local_addr()?;
+        Ok(NodeLocator {
+            designator: Cow::Owned(addr.ip().to_string()),
+            transport: Cow::Borrowed(Self::TRANSPORT),
+            hints: HashMap::from_iter([
+                (symbol!["port"], Cow::Owned(addr.port().to_string())),
+                (
+                    symbol!["tls-key"],
+                    Cow::Owned(encode_key(&self.key.verifying_key())),
+                ),
+            ]),
+        })
+    }
+
+    fn signing_key(&self) -> Option<&SigningKey> {
+        Some(&self.key)
+    }
+}
+
+impl AsyncDataStream for TlsStream<TcpStream> {
+    type ReadHalf = tokio::io::BufReader<tokio::io::ReadHalf<Self>>;
+    type WriteHalf = tokio::io::WriteHalf<Self>;
+    type Error = TlsError;
+
+    async fn connect<'loc>(addr: &NodeLocator<'loc>) -> Result<Self, Self::Error> {
+        let key = decode_key(addr.hint("tls-key").ok_or(TlsError::MissingKey)?)
+            .ok_or(TlsError::InvalidKey)?;
+        let socket_addr = addr.as_socket_addr()?;
+
+        let config = rustls::ClientConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(PinnedKey::new(&key)))
+            .with_no_client_auth();
+
+        let stream = TcpStream::connect(socket_addr).await?;
+        let stream = TlsConnector::from(Arc::new(config))
+            .connect(ServerName::IpAddress(socket_addr.ip().into()), stream)
+            .await?;
+        Ok(stream.into())
+    }
+
+    fn split(self) -> (Self::ReadHalf, Self::WriteHalf) {
+        let (reader, writer) = tokio::io::split(self);
+        (tokio::io::BufReader::new(reader), writer)
+    }
+
+    fn pinned_key(addr: &NodeLocator<'_>) -> Option<VerifyingKey> {
+        decode_key(addr.hint("tls-key")?)
+    }
+}
@@ -1,12 +1,91 @@
-use std::borrow::Cow;
+//! Unix domain sockets, named either by a filesystem path or, on Linux, by a name in the abstract
+//! namespace.
+//!
+//! Locators for abstract sockets use the name as their designator, with a `namespace` hint of
+//! `abstract`.
+
+use std::{borrow::Cow, io};
 
 use super::{AsyncDataStream, AsyncStreamListener};
 use rexa::locator::NodeLocator;
 
+const TRANSPORT: &str = "unix";
+
+#[cfg(feature = "tokio")]
 pub type UnixNetlayer = super::DataStreamNetlayer<tokio::net::UnixListener>;
+#[cfg(feature = "tokio")]
+pub type PeerCredUnixNetlayer = super::DataStreamNetlayer<PeerCredListener>;
+#[cfg(feature = "async-io")]
+pub type AsyncIoUnixNetlayer =
+    super::DataStreamNetlayer<async_io::Async<std::os::unix::net::UnixListener>>;
+
+fn locator(addr: &std::os::unix::net::SocketAddr) -> io::Result<NodeLocator<'static>> {
+    if let Some(path) = addr.as_pathname() {
+        let path = path.to_str().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "unix socket path is not utf-8")
+        })?;
+        return Ok(NodeLocator::new(
+            Cow::Owned(path.to_owned()),
+            Cow::Borrowed(TRANSPORT),
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    if let Some(name) = std::os::linux::net::SocketAddrExt::as_abstract_name(addr) {
+        let name = std::str::from_utf8(name).map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidData, "unix socket name is not utf-8")
+        })?;
+        return Ok(NodeLocator {
+            designator: Cow::Owned(name.to_owned()),
+            transport: Cow::Borrowed(TRANSPORT),
+            hints: std::collections::HashMap::from_iter([(
+                syrup::symbol!["namespace"],
+                Cow::Borrowed("abstract"),
+            )]),
+        });
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "unnamed unix sockets have no locator",
+    ))
+}
+
+/// Get the path of the socket referred to by a `unix` locator.
+///
+/// Abstract names are given as a path starting with a NUL byte, which both tokio and async-io
+/// connect to in the abstract namespace.
+fn socket_path(locator: &NodeLocator<'_>) -> io::Result<std::path::PathBuf> {
+    match locator.hint("namespace").map(|ns| &**ns) {
+        None => Ok(std::path::PathBuf::from(&*locator.designator)),
+        #[cfg(target_os = "linux")]
+        Some("abstract") => {
+            use std::os::unix::ffi::OsStringExt;
+            let mut name = vec![0];
+            name.extend_from_slice(locator.designator.as_bytes());
+            Ok(std::ffi::OsString::from_vec(name).into())
+        }
+        Some(namespace) => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("unsupported unix socket namespace: {namespace}"),
+        )),
+    }
+}
 
+/// Get the address of a tokio listener, as a std address so that abstract names are accessible.
+#[cfg(feature = "tokio")]
+fn std_local_addr(
+    listener: &tokio::net::UnixListener,
+) -> io::Result<std::os::unix::net::SocketAddr> {
+    use std::os::fd::AsFd;
+    // tokio's SocketAddr doesn't expose abstract names, so ask a duplicate of the socket instead
+    let fd = listener.as_fd().try_clone_to_owned()?;
+    std::os::unix::net::UnixListener::from(fd).local_addr()
+}
+
+#[cfg(feature = "tokio")]
 impl AsyncStreamListener for tokio::net::UnixListener {
-    const TRANSPORT: &'static str = "unix";
+    const TRANSPORT: &'static str = TRANSPORT;
     type AddressInput<'addr> = &'addr std::os::unix::net::SocketAddr;
     type AddressOutput = tokio::net::unix::SocketAddr;
     type Error = std::io::Error;
@@ -34,34 +113,160 @@ impl AsyncStreamListener for tokio::net::UnixListener {
     }
 
     fn locator(&self) -> Result<NodeLocator<'_>, Self::Error> {
-        // FIX :: tokio unix socketaddr does not suport as_abstract_namespace
-        match self
-            .local_addr()?
-            .as_pathname()
-            .and_then(std::path::Path::to_str)
-        {
-            Some(p) => Ok(NodeLocator::new(
-                Cow::Owned(p.to_owned()),
-                Cow::Borrowed(Self::TRANSPORT),
-            )),
-            None => todo!("handle unnamed unix streams"),
+        locator(&std_local_addr(self)?)
+    }
+}
+
+/// The users and groups whose processes may connect to a [`PeerCredListener`].
+///
+/// A peer is allowed if either its user or its group is.
+#[cfg(feature = "tokio")]
+#[derive(Debug, Clone, Default)]
+pub struct PeerFilter {
+    pub uids: std::collections::HashSet<u32>,
+    pub gids: std::collections::HashSet<u32>,
+}
+
+#[cfg(feature = "tokio")]
+impl PeerFilter {
+    pub fn allow_uid(mut self, uid: u32) -> Self {
+        self.uids.insert(uid);
+        self
+    }
+
+    pub fn allow_gid(mut self, gid: u32) -> Self {
+        self.gids.insert(gid);
+        self
+    }
+
+    pub fn allows(&self, cred: &tokio::net::unix::UCred) -> bool {
+        self.uids.contains(&cred.uid()) || self.gids.contains(&cred.gid())
+    }
+}
+
+/// A unix listener which drops connections from peers not allowed by a [`PeerFilter`], as
+/// reported by `SO_PEERCRED` or its equivalent.
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub struct PeerCredListener {
+    listener: tokio::net::UnixListener,
+    filter: PeerFilter,
+}
+
+#[cfg(feature = "tokio")]
+impl AsyncStreamListener for PeerCredListener {
+    const TRANSPORT: &'static str = TRANSPORT;
+    type AddressInput<'addr> = (&'addr std::os::unix::net::SocketAddr, PeerFilter);
+    type AddressOutput = tokio::net::unix::SocketAddr;
+    type Error = std::io::Error;
+    type Stream = tokio::net::UnixStream;
+
+    async fn bind((addr, filter): Self::AddressInput<'_>) -> Result<Self, Self::Error> {
+        Ok(Self {
+            listener: <tokio::net::UnixListener as AsyncStreamListener>::bind(addr).await?,
+            filter,
+        })
+    }
+
+    fn accept(
+        &self,
+    ) -> impl std::future::Future<Output = Result<(Self::Stream, Self::AddressOutput), Self::Error>>
+           + std::marker::Send
+           + Unpin {
+        use futures::FutureExt;
+        async move {
+            loop {
+                let (stream, addr) = self.listener.accept().await?;
+                match stream.peer_cred() {
+                    Ok(cred) if self.filter.allows(&cred) => return Ok((stream, addr)),
+                    Ok(cred) => tracing::warn!(?cred, "rejecting connection from unix peer"),
+                    Err(error) => {
+                        tracing::warn!(%error, "rejecting connection from unknown unix peer")
+                    }
+                }
+            }
         }
+        .boxed()
+    }
+
+    fn local_addr(&self) -> Result<Self::AddressOutput, Self::Error> {
+        self.listener.local_addr()
+    }
+
+    fn locator(&self) -> Result<NodeLocator<'_>, Self::Error> {
+        self.listener.locator()
     }
 }
 
-#[cfg(target_family = "unix")]
+#[cfg(feature = "async-io")]
+impl AsyncStreamListener for async_io::Async<std::os::unix::net::UnixListener> {
+    const TRANSPORT: &'static str = TRANSPORT;
+    type AddressInput<'addr> = &'addr std::os::unix::net::SocketAddr;
+    type AddressOutput = std::os::unix::net::SocketAddr;
+    type Error = std::io::Error;
+    type Stream = async_io::Async<std::os::unix::net::UnixStream>;
+
+    async fn bind(addr: Self::AddressInput<'_>) -> Result<Self, Self::Error> {
+        // `Async::new` makes the listener non-blocking
+        async_io::Async::new(std::os::unix::net::UnixListener::bind_addr(addr)?)
+    }
+
+    fn accept(
+        &self,
+    ) -> impl std::future::Future<Output = Result<(Self::Stream, Self::AddressOutput), Self::Error>>
+           + std::marker::Send
+           + Unpin {
+        use futures::FutureExt;
+        async_io::Async::<std::os::unix::net::UnixListener>::accept(self).boxed()
+    }
+
+    fn local_addr(&self) -> Result<Self::AddressOutput, Self::Error> {
+        self.get_ref().local_addr()
+    }
+
+    fn locator(&self) -> Result<NodeLocator<'_>, Self::Error> {
+        locator(&self.local_addr()?)
+    }
+}
+
+#[cfg(feature = "tokio")]
 impl AsyncDataStream for tokio::net::UnixStream {
     type ReadHalf = tokio::net::unix::OwnedReadHalf;
     type WriteHalf = tokio::net::unix::OwnedWriteHalf;
     type Error = std::io::Error;
 
-    fn connect<'loc>(
-        addr: &NodeLocator<'loc>,
-    ) -> impl std::future::Future<Output = Result<Self, Self::Error>> + std::marker::Send {
-        tokio::net::UnixStream::connect(&*addr.designator)
+    async fn connect<'loc>(addr: &NodeLocator<'loc>) -> Result<Self, Self::Error> {
+        tokio::net::UnixStream::connect(socket_path(addr)?).await
     }
 
     fn split(self) -> (Self::ReadHalf, Self::WriteHalf) {
         tokio::net::UnixStream::into_split(self)
     }
+
+    /// The [`UCred`](tokio::net::unix::UCred) of the process on the other end.
+    fn peer_info(&self) -> Option<Box<dyn std::any::Any + Send + Sync>> {
+        match self.peer_cred() {
+            Ok(cred) => Some(Box::new(cred)),
+            Err(error) => {
+                tracing::debug!(%error, "couldn't get unix peer credentials");
+                None
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async-io")]
+impl AsyncDataStream for async_io::Async<std::os::unix::net::UnixStream> {
+    type ReadHalf = futures::io::BufReader<futures::io::ReadHalf<Self>>;
+    type WriteHalf = futures::io::WriteHalf<Self>;
+    type Error = std::io::Error;
+
+    async fn connect<'loc>(addr: &NodeLocator<'loc>) -> Result<Self, Self::Error> {
+        async_io::Async::<std::os::unix::net::UnixStream>::connect(socket_path(addr)?).await
+    }
+
+    fn split(self) -> (Self::ReadHalf, Self::WriteHalf) {
+        let (reader, writer) = futures::AsyncReadExt::split(self);
+        (futures::io::BufReader::new(reader), writer)
+    }
 }
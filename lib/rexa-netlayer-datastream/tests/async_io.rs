@@ -0,0 +1,54 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use rexa::{
+    captp::{
+        object::{DeliverError, FetchError},
+        BootstrapEvent, Event,
+    },
+    netlayer::Netlayer,
+};
+use rexa_netlayer_datastream::AsyncIoTcpNetlayer;
+use syrup::Encode;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+const LOOPBACK: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+
+/// Sessions over async-io streams run without tokio.
+#[test]
+fn loopback() -> Result<(), BoxError> {
+    smol::block_on(async {
+        let node_a = AsyncIoTcpNetlayer::bind(&LOOPBACK).await?;
+        let node_b = AsyncIoTcpNetlayer::bind(&LOOPBACK).await?;
+        let locator_b = node_b.locators().pop().unwrap();
+
+        let (session_ab, session_ba) = futures::join!(node_a.connect(&locator_b), node_b.accept());
+        let (session_ab, session_ba) = (session_ab?, session_ba?);
+        assert_eq!(
+            session_ab.signing_key().verifying_key(),
+            *session_ba.remote_vkey()
+        );
+
+        let events_ab = session_ab.clone();
+        smol::spawn(async move { while events_ab.recv_event().await.is_ok() {} }).detach();
+        smol::spawn(async move {
+            while let Ok(event) = session_ba.recv_event().await {
+                if let Event::Bootstrap(BootstrapEvent::Fetch { resolver, .. }) = event {
+                    resolver
+                        .break_promise("nothing here".to_tokens())
+                        .await
+                        .expect("fetch should be broken");
+                }
+            }
+        })
+        .detach();
+
+        // the fetch is only answered if messages make it both ways
+        let res = session_ab.get_remote_bootstrap().fetch(b"missing").await;
+        assert!(
+            matches!(res, Err(FetchError::Deliver(DeliverError::Broken(_)))),
+            "{res:?}"
+        );
+        Ok(())
+    })
+}
@@ -0,0 +1,86 @@
+use std::{
+    borrow::Cow,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+};
+
+use ed25519_dalek::SigningKey;
+use rexa::netlayer::Netlayer;
+use rexa_netlayer_datastream::{
+    AsyncDataStream, AsyncStreamListener, NoiseListener, NoiseNetlayer, NoiseStream,
+};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+type Listener = NoiseListener<tokio::net::TcpListener>;
+type Stream = NoiseStream<tokio::net::TcpStream>;
+
+const LOOPBACK: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+
+#[tokio::test]
+async fn loopback() -> Result<(), BoxError> {
+    let key = SigningKey::from_bytes(&[7; 32]);
+    let listener = Listener::bind((&LOOPBACK, &key)).await?;
+    let locator = listener.locator()?;
+
+    let (accepted, connected) = tokio::join!(listener.accept(), Stream::connect(&locator));
+    let (_, mut server) = accepted?.0.split();
+    let (mut client, _) = connected?.split();
+
+    // larger than a single frame
+    let msg = (0..100_000).map(|i| i as u8).collect::<Vec<u8>>();
+    let (written, read) = tokio::join!(
+        async {
+            server.write_all(&msg).await?;
+            server.flush().await
+        },
+        async {
+            let mut buf = vec![0; msg.len()];
+            client.read_exact(&mut buf).await.map(|_| buf)
+        }
+    );
+    written?;
+    assert_eq!(read?, msg);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn rejects_unpinned_key() -> Result<(), BoxError> {
+    let key = SigningKey::from_bytes(&[7; 32]);
+    let listener = Listener::bind((&LOOPBACK, &key)).await?;
+
+    // point the locator at the listener, but pin a different key
+    let mut locator = listener.locator()?;
+    let other = SigningKey::from_bytes(&[8; 32]).verifying_key();
+    locator.hints.insert(
+        syrup::symbol!["noise-key"],
+        Cow::Owned(other.as_bytes().iter().map(|b| format!("{b:02x}")).collect()),
+    );
+
+    let (_, connected) = tokio::join!(listener.accept(), Stream::connect(&locator));
+    assert!(matches!(
+        connected,
+        Err(rexa_netlayer_datastream::NoiseError::KeyMismatch)
+    ));
+
+    Ok(())
+}
+
+/// A netlayer bound with a Noise listener uses the listener's key for its sessions too.
+#[tokio::test]
+async fn bind_uses_listener_key() -> Result<(), BoxError> {
+    let key_a = SigningKey::from_bytes(&[1; 32]);
+    let key_b = SigningKey::from_bytes(&[2; 32]);
+    let node_a = NoiseNetlayer::<tokio::net::TcpListener>::bind((&LOOPBACK, &key_a)).await?;
+    let node_b = NoiseNetlayer::new(vec![Listener::bind((&LOOPBACK, &key_b)).await?]);
+    let locator_b = node_b.locators().pop().unwrap();
+
+    let (session_ab, session_ba) = tokio::join!(node_a.connect(&locator_b), node_b.accept());
+    let (session_ab, session_ba) = (session_ab?, session_ba?);
+    assert_eq!(session_ab.signing_key(), &key_a);
+    assert_eq!(*session_ab.remote_vkey(), key_b.verifying_key());
+    assert_eq!(*session_ba.remote_vkey(), key_a.verifying_key());
+
+    session_ab.abort("done").await?;
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    time::Duration,
+};
+
+use rexa::netlayer::Netlayer;
+use rexa_netlayer_datastream::TcpIpNetlayer;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+const LOOPBACK: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+
+#[tokio::test]
+async fn crossed_hellos() -> Result<(), BoxError> {
+    let node_a = TcpIpNetlayer::bind(&LOOPBACK).await?;
+    let node_b = TcpIpNetlayer::bind(&LOOPBACK).await?;
+    let locator_a = node_a.locators().pop().unwrap();
+    let locator_b = node_b.locators().pop().unwrap();
+
+    let (ab, ba, a_acc, b_acc) = tokio::join!(
+        node_a.connect(&locator_b),
+        node_b.connect(&locator_a),
+        node_a.accept(),
+        node_b.accept()
+    );
+    for res in [ab, ba, a_acc, b_acc] {
+        res?;
+    }
+
+    // both nodes should have kept the same one of the two sessions
+    let session_ab = node_a.connect(&locator_b).await?;
+    let session_ba = node_b.connect(&locator_a).await?;
+    assert!(!session_ab.is_aborted());
+    assert!(!session_ba.is_aborted());
+    assert_eq!(
+        session_ab.signing_key().verifying_key(),
+        *session_ba.remote_vkey()
+    );
+    assert_eq!(
+        session_ba.signing_key().verifying_key(),
+        *session_ab.remote_vkey()
+    );
+    Ok(())
+}
+
+/// A node reached at a second address is recognized by its key, and both nodes keep the session
+/// started at the first.
+#[tokio::test]
+async fn two_addresses() -> Result<(), BoxError> {
+    let node_a = TcpIpNetlayer::bind(&LOOPBACK).await?;
+    let mut node_b = TcpIpNetlayer::bind(&LOOPBACK).await?;
+    node_b.push_bind(&LOOPBACK).await?;
+    let locators_b = node_b.locators();
+
+    let (session_ab, session_ba) = tokio::join!(node_a.connect(&locators_b[0]), node_b.accept());
+    let (session_ab, session_ba) = (session_ab?, session_ba?);
+    let (second_ab, second_ba) = tokio::join!(node_a.connect(&locators_b[1]), node_b.accept());
+    assert!(second_ab? == session_ab);
+    assert!(second_ba? == session_ba);
+    assert!(!session_ab.is_aborted());
+    assert!(!session_ba.is_aborted());
+
+    // the second address is now known, so it isn't dialed again
+    let again =
+        tokio::time::timeout(Duration::from_secs(5), node_a.connect(&locators_b[1])).await?;
+    assert!(again? == session_ab);
+    Ok(())
+}
@@ -0,0 +1,199 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::Arc,
+};
+
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use rexa::{locator::NodeLocator, netlayer::Netlayer};
+use rexa_netlayer_datastream::{AsyncDataStream, AsyncStreamListener, TlsListener, TlsTcpNetlayer};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_rustls::{
+    rustls::{
+        self,
+        pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer},
+    },
+    TlsAcceptor, TlsStream,
+};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+const LOOPBACK: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+
+fn hex(key: &VerifyingKey) -> String {
+    key.as_bytes().iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[tokio::test]
+async fn loopback() -> Result<(), BoxError> {
+    let key = SigningKey::from_bytes(&[7; 32]);
+    let listener = TlsListener::bind((&LOOPBACK, &key)).await?;
+    let locator = listener.locator()?;
+
+    let (accepted, connected) = tokio::join!(
+        listener.accept(),
+        TlsStream::<tokio::net::TcpStream>::connect(&locator)
+    );
+    let (mut server, _) = accepted?;
+    let mut client = connected?;
+
+    client.write_all(b"ping").await?;
+    client.flush().await?;
+    let mut buf = [0; 4];
+    server.read_exact(&mut buf).await?;
+    assert_eq!(&buf, b"ping");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn rejects_unpinned_key() -> Result<(), BoxError> {
+    let key = SigningKey::from_bytes(&[7; 32]);
+    let listener = TlsListener::bind((&LOOPBACK, &key)).await?;
+
+    // point the locator at the listener, but pin a different key
+    let mut locator = listener.locator()?;
+    let other = SigningKey::from_bytes(&[8; 32]).verifying_key();
+    locator
+        .hints
+        .insert(syrup::symbol!["tls-key"], Cow::Owned(hex(&other)));
+    assert!(TlsStream::<tokio::net::TcpStream>::connect(&locator)
+        .await
+        .is_err());
+
+    // the failed handshake isn't returned by accept
+    let (accepted, connected) = tokio::join!(
+        listener.accept(),
+        TlsStream::<tokio::net::TcpStream>::connect(&listener.locator()?)
+    );
+    accepted?;
+    connected?;
+
+    Ok(())
+}
+
+/// A connection which never starts its handshake doesn't hold up the ones behind it.
+#[tokio::test]
+async fn stalled_handshake() -> Result<(), BoxError> {
+    let key = SigningKey::from_bytes(&[7; 32]);
+    let listener = TlsListener::bind((&LOOPBACK, &key)).await?;
+    let locator = listener.locator()?;
+
+    let _stalled = tokio::net::TcpStream::connect(listener.local_addr()?).await?;
+    let (accepted, connected) = tokio::join!(
+        listener.accept(),
+        TlsStream::<tokio::net::TcpStream>::connect(&locator)
+    );
+    let (mut server, _) = accepted?;
+    let mut client = connected?;
+
+    client.write_all(b"ping").await?;
+    client.flush().await?;
+    let mut buf = [0; 4];
+    server.read_exact(&mut buf).await?;
+    assert_eq!(&buf, b"ping");
+
+    Ok(())
+}
+
+/// A certificate which mentions the pinned key anywhere but its own SubjectPublicKeyInfo is
+/// rejected.
+#[tokio::test]
+async fn rejects_key_in_extension() -> Result<(), BoxError> {
+    let pinned = SigningKey::from_bytes(&[7; 32]).verifying_key();
+    // DER encoding of the SubjectPublicKeyInfo for an ed25519 key
+    let mut pinned_spki = vec![
+        0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+    ];
+    pinned_spki.extend_from_slice(pinned.as_bytes());
+
+    let listener = tokio::net::TcpListener::bind(LOOPBACK).await?;
+    let addr = listener.local_addr()?;
+    let key_pair = rcgen::KeyPair::generate_for(&rcgen::PKCS_ED25519)?;
+    let mut params = rcgen::CertificateParams::new(vec![addr.ip().to_string()])?;
+    params
+        .custom_extensions
+        .push(rcgen::CustomExtension::from_oid_content(
+            &[1, 3, 6, 1, 4, 1, 55555, 1],
+            pinned_spki,
+        ));
+    let cert = params.self_signed(&key_pair)?;
+    let config = rustls::ServerConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()?
+    .with_no_client_auth()
+    .with_single_cert(
+        vec![cert.der().clone()],
+        PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key_pair.serialize_der())),
+    )?;
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+
+    let locator = NodeLocator {
+        designator: Cow::Owned(addr.ip().to_string()),
+        transport: Cow::Borrowed("tcp+tls"),
+        hints: HashMap::from_iter([
+            (syrup::symbol!["port"], Cow::Owned(addr.port().to_string())),
+            (syrup::symbol!["tls-key"], Cow::Owned(hex(&pinned))),
+        ]),
+    };
+    let (_, connected) = tokio::join!(
+        async {
+            let (stream, _) = listener.accept().await?;
+            acceptor.accept(stream).await
+        },
+        TlsStream::<tokio::net::TcpStream>::connect(&locator)
+    );
+    assert!(connected.is_err());
+
+    Ok(())
+}
+
+/// Sessions over TLS use the key pinned by the locator as the node's identity.
+#[tokio::test]
+async fn session() -> Result<(), BoxError> {
+    let key_a = SigningKey::from_bytes(&[1; 32]);
+    let key_b = SigningKey::from_bytes(&[2; 32]);
+    let node_a = TlsTcpNetlayer::bind_with_key(&LOOPBACK, key_a.clone()).await?;
+    let node_b = TlsTcpNetlayer::bind_with_key(&LOOPBACK, key_b.clone()).await?;
+    let locator_b = node_b.locators().pop().unwrap();
+    assert_eq!(
+        locator_b.hint("tls-key").map(|key| &**key),
+        Some(&*hex(&key_b.verifying_key()))
+    );
+
+    let (session_ab, session_ba) = tokio::join!(node_a.connect(&locator_b), node_b.accept());
+    let (session_ab, session_ba) = (session_ab?, session_ba?);
+    assert_eq!(*session_ab.remote_vkey(), key_b.verifying_key());
+    assert_eq!(*session_ba.remote_vkey(), key_a.verifying_key());
+
+    // any locator pinning the same key finds the session, without dialing
+    let mut elsewhere = locator_b.clone();
+    elsewhere
+        .hints
+        .insert(syrup::symbol!["port"], Cow::Borrowed("1"));
+    assert!(node_a.connect(&elsewhere).await? == session_ab);
+
+    session_ab.abort("done").await?;
+    Ok(())
+}
+
+/// A netlayer bound with a TLS listener uses the listener's key for its sessions too.
+#[tokio::test]
+async fn bind_uses_listener_key() -> Result<(), BoxError> {
+    let key_a = SigningKey::from_bytes(&[1; 32]);
+    let key_b = SigningKey::from_bytes(&[2; 32]);
+    let node_a = TlsTcpNetlayer::bind((&LOOPBACK, &key_a)).await?;
+    let node_b = TlsTcpNetlayer::new(vec![TlsListener::bind((&LOOPBACK, &key_b)).await?]);
+    let locator_b = node_b.locators().pop().unwrap();
+
+    let (session_ab, session_ba) = tokio::join!(node_a.connect(&locator_b), node_b.accept());
+    let (session_ab, session_ba) = (session_ab?, session_ba?);
+    assert_eq!(session_ab.signing_key(), &key_a);
+    assert_eq!(*session_ab.remote_vkey(), key_b.verifying_key());
+    assert_eq!(*session_ba.remote_vkey(), key_a.verifying_key());
+
+    session_ab.abort("done").await?;
+    Ok(())
+}
@@ -0,0 +1,71 @@
+#![cfg(target_os = "linux")]
+
+use std::{os::linux::net::SocketAddrExt, os::unix::net::SocketAddr, time::Duration};
+
+use rexa::netlayer::Netlayer;
+use rexa_netlayer_datastream::{
+    AsyncDataStream, AsyncStreamListener, PeerCredListener, PeerFilter, UnixNetlayer,
+};
+use tokio::net::unix::UCred;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+fn abstract_addr(name: &str) -> std::io::Result<SocketAddr> {
+    SocketAddr::from_abstract_name(format!("rexa-{name}-{}", std::process::id()))
+}
+
+fn own_cred() -> std::io::Result<UCred> {
+    tokio::net::UnixStream::pair()?.0.peer_cred()
+}
+
+#[tokio::test]
+async fn abstract_loopback() -> Result<(), BoxError> {
+    let node_a = UnixNetlayer::bind(&abstract_addr("abstract-a")?).await?;
+    let node_b = UnixNetlayer::bind(&abstract_addr("abstract-b")?).await?;
+
+    let locator_b = node_b.locators().pop().unwrap();
+    assert_eq!(locator_b.hint("namespace").map(|ns| &**ns), Some("abstract"));
+
+    let (session_ab, session_ba) = tokio::join!(node_a.connect(&locator_b), node_b.accept());
+    let (session_ab, session_ba) = (session_ab?, session_ba?);
+
+    let uid = own_cred()?.uid();
+    assert_eq!(session_ab.peer_info::<UCred>().map(UCred::uid), Some(uid));
+    assert_eq!(session_ba.peer_info::<UCred>().map(UCred::uid), Some(uid));
+
+    session_ab.abort("done").await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn peer_filter() -> Result<(), BoxError> {
+    let uid = own_cred()?.uid();
+
+    let denied = PeerCredListener::bind((
+        &abstract_addr("filter-denied")?,
+        PeerFilter::default().allow_uid(uid.wrapping_add(1)),
+    ))
+    .await?;
+    let locator = denied.locator()?;
+    let (accepted, connected) = tokio::join!(
+        tokio::time::timeout(Duration::from_millis(100), denied.accept()),
+        <tokio::net::UnixStream as AsyncDataStream>::connect(&locator)
+    );
+    connected?;
+    assert!(accepted.is_err(), "accepted a peer not allowed by the filter");
+
+    let allowed = PeerCredListener::bind((
+        &abstract_addr("filter-allowed")?,
+        PeerFilter::default().allow_uid(uid),
+    ))
+    .await?;
+    let locator = allowed.locator()?;
+    let (accepted, connected) = tokio::join!(
+        allowed.accept(),
+        <tokio::net::UnixStream as AsyncDataStream>::connect(&locator)
+    );
+    connected?;
+    accepted?;
+
+    Ok(())
+}
@@ -0,0 +1,196 @@
+//! CapTP between nodes in the same process.
+//!
+//! Messages are passed over channels as the token trees queued by the sending session, so there is
+//! no byte stream to buffer or frame, and the receiving session never lexes them.
+
+use std::{borrow::Cow, collections::HashMap, io, sync::Arc};
+
+use parking_lot::RwLock;
+use rexa::{
+    captp::{
+        CapTpMessageRead, CapTpMessageWrite, CapTpSession, CapTpSessionManager, ReadSyrupError,
+        SessionInitError,
+    },
+    locator::NodeLocator,
+    netlayer::Netlayer,
+};
+use syrup::TokenTree;
+use tokio::sync::{mpsc, Mutex as AsyncMutex};
+
+const TRANSPORT: &str = "local";
+
+type Connection = (LocalReader, LocalWriter);
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("name already in use")]
+    NameInUse,
+    #[error("address not found")]
+    NotFound,
+    #[error("netlayer closed")]
+    Closed,
+    #[error(transparent)]
+    Init(#[from] SessionInitError),
+}
+
+/// Receives messages sent by a [`LocalWriter`].
+#[derive(Debug)]
+pub struct LocalReader {
+    recv: mpsc::UnboundedReceiver<TokenTree<'static>>,
+}
+
+impl CapTpMessageRead for LocalReader {
+    async fn read_message(&mut self) -> Result<TokenTree<'static>, ReadSyrupError> {
+        self.recv
+            .recv()
+            .await
+            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof).into())
+    }
+}
+
+/// Sends each message to a [`LocalReader`].
+#[derive(Debug)]
+pub struct LocalWriter {
+    send: mpsc::UnboundedSender<TokenTree<'static>>,
+}
+
+impl CapTpMessageWrite for LocalWriter {
+    type Message = TokenTree<'static>;
+
+    fn prepare_message(msg: &TokenTree<'_>) -> Self::Message {
+        msg.clone().into_owned()
+    }
+
+    async fn write_message(&mut self, msg: Self::Message) -> io::Result<()> {
+        self.send
+            .send(msg)
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+    }
+
+    async fn flush_messages(&mut self) -> io::Result<()> {
+        // messages are delivered as soon as they're written
+        Ok(())
+    }
+}
+
+/// Create both ends of a connection.
+fn connection() -> (Connection, Connection) {
+    let (a_send, b_recv) = mpsc::unbounded_channel();
+    let (b_send, a_recv) = mpsc::unbounded_channel();
+    (
+        (LocalReader { recv: a_recv }, LocalWriter { send: a_send }),
+        (LocalReader { recv: b_recv }, LocalWriter { send: b_send }),
+    )
+}
+
+/// A namespace of [`LocalNetlayer`]s which can reach each other.
+#[derive(Debug, Clone, Default)]
+pub struct LocalNetwork {
+    nodes: Arc<RwLock<HashMap<String, mpsc::UnboundedSender<Connection>>>>,
+}
+
+impl LocalNetwork {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a netlayer reachable within this network at `name`.
+    pub fn bind(&self, name: impl Into<String>) -> Result<LocalNetlayer, Error> {
+        let name = name.into();
+        let mut nodes = self.nodes.write();
+        if nodes.contains_key(&name) {
+            return Err(Error::NameInUse);
+        }
+        let (accept_send, accept_recv) = mpsc::unbounded_channel();
+        nodes.insert(name.clone(), accept_send);
+        Ok(LocalNetlayer {
+            name,
+            network: self.clone(),
+            accept_recv: AsyncMutex::new(accept_recv),
+            manager: CapTpSessionManager::new(),
+        })
+    }
+}
+
+pub struct LocalNetlayer {
+    name: String,
+    network: LocalNetwork,
+    accept_recv: AsyncMutex<mpsc::UnboundedReceiver<Connection>>,
+    manager: CapTpSessionManager<LocalReader, LocalWriter>,
+}
+
+impl std::fmt::Debug for LocalNetlayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LocalNetlayer")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for LocalNetlayer {
+    fn drop(&mut self) {
+        // names can't be rebound while in use, so the entry must be this netlayer's
+        self.network.nodes.write().remove(&self.name);
+    }
+}
+
+impl LocalNetlayer {
+    fn locator(&self) -> NodeLocator<'_> {
+        NodeLocator::new(
+            Cow::Borrowed(self.name.as_str()),
+            Cow::Borrowed(TRANSPORT),
+        )
+    }
+}
+
+impl Netlayer for LocalNetlayer {
+    type Reader = LocalReader;
+    type Writer = LocalWriter;
+    type Error = Error;
+
+    async fn connect<'loc>(
+        &self,
+        locator: &NodeLocator<'loc>,
+    ) -> Result<CapTpSession<Self::Reader, Self::Writer>, Self::Error> {
+        if let Some(session) = self.manager.get(locator) {
+            return Ok(session);
+        }
+
+        let (local, remote) = connection();
+        self.network
+            .nodes
+            .read()
+            .get(&*locator.designator)
+            .ok_or(Error::NotFound)?
+            .send(remote)
+            .map_err(|_| Error::NotFound)?;
+
+        let (reader, writer) = local;
+        let session = self
+            .manager
+            .init_session(reader, writer)
+            .and_connect(self.locator())
+            .await?;
+        self.manager.alias(locator, *session.remote_vkey());
+        Ok(session)
+    }
+
+    async fn accept(&self) -> Result<CapTpSession<Self::Reader, Self::Writer>, Self::Error> {
+        let (reader, writer) = self
+            .accept_recv
+            .lock()
+            .await
+            .recv()
+            .await
+            .ok_or(Error::Closed)?;
+        self.manager
+            .init_session(reader, writer)
+            .and_accept(self.locator())
+            .await
+            .map_err(From::from)
+    }
+
+    fn locators(&self) -> Vec<NodeLocator<'_>> {
+        vec![self.locator()]
+    }
+}
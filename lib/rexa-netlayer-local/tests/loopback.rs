@@ -0,0 +1,48 @@
+use rexa::{captp::CapTpMessageWrite, netlayer::Netlayer};
+use rexa_netlayer_local::{Error, LocalNetwork, LocalWriter};
+use syrup::TokenTree;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+#[tokio::test]
+async fn loopback() -> Result<(), BoxError> {
+    let network = LocalNetwork::new();
+    let node_a = network.bind("a")?;
+    let node_b = network.bind("b")?;
+
+    let locator_b = node_b.locators().pop().unwrap();
+    let (session_ab, session_ba) = tokio::join!(node_a.connect(&locator_b), node_b.accept());
+    let (session_ab, session_ba) = (session_ab?, session_ba?);
+
+    assert_eq!(
+        session_ab.remote_vkey(),
+        &session_ba.signing_key().verifying_key()
+    );
+    assert_eq!(
+        session_ba.remote_vkey(),
+        &session_ab.signing_key().verifying_key()
+    );
+
+    session_ab.abort("done").await?;
+    Ok(())
+}
+
+#[test]
+fn names_are_per_network() -> Result<(), BoxError> {
+    let network = LocalNetwork::new();
+    let node = network.bind("a")?;
+    assert!(matches!(network.bind("a"), Err(Error::NameInUse)));
+    LocalNetwork::new().bind("a")?;
+
+    drop(node);
+    network.bind("a")?;
+    Ok(())
+}
+
+/// Messages are passed over the channel as the trees they were queued as, so there are no bytes to
+/// encode or lex on either end.
+#[test]
+fn messages_are_not_encoded() {
+    fn passes_trees<Writer: CapTpMessageWrite<Message = TokenTree<'static>>>() {}
+    passes_trees::<LocalWriter>();
+}
@@ -1,7 +1,6 @@
 use std::{
     borrow::Cow,
     collections::HashMap,
-    future::Future,
     sync::{Arc, Weak},
 };
 
@@ -14,18 +13,43 @@ use rexa::{
 
 use tokio::{
     io::{BufReader, DuplexStream},
-    sync::{mpsc, oneshot, Mutex as AsyncMutex, RwLock as AsyncRwLock},
+    sync::{mpsc, oneshot, Mutex as AsyncMutex},
 };
 
+mod raw;
+pub use raw::*;
+
+mod sim;
+use sim::Link;
+pub use sim::*;
+
 type MockReader = <MockNetlayer as Netlayer>::Reader;
 type MockWriter = <MockNetlayer as Netlayer>::Writer;
 type StreamSend = oneshot::Sender<(MockReader, MockWriter)>;
 
 type MockRegistry =
-    RwLock<HashMap<String, (Weak<MockNetlayer>, mpsc::UnboundedSender<StreamSend>)>>;
+    RwLock<HashMap<String, (Weak<MockNetlayer>, mpsc::UnboundedSender<ConnectRequest>)>>;
 
-lazy_static::lazy_static! {
-    static ref MOCK_REGISTRY: MockRegistry = RwLock::default();
+/// A request to connect to a node, answered by sending back the dialer's end of the connection.
+pub(crate) struct ConnectRequest {
+    /// The name of the node which dialed.
+    pub(crate) from: String,
+    /// Shared by both ends of a simulated connection.
+    pub(crate) link: Option<Arc<Link>>,
+    stream_send: StreamSend,
+}
+
+impl ConnectRequest {
+    /// Open the connection, returning the acceptor's end of it.
+    pub(crate) fn answer(self) -> Result<(MockReader, MockWriter), Error> {
+        // HACK :: there's probably a better way to set this number but whatever
+        let (local_reader, remote_writer) = tokio::io::duplex(1024);
+        let (remote_reader, local_writer) = tokio::io::duplex(1024);
+        self.stream_send
+            .send((BufReader::new(remote_reader), remote_writer))
+            .map_err(|_err| Error::Accept)?;
+        Ok((BufReader::new(local_reader), local_writer))
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -34,6 +58,10 @@ pub enum Error {
     NameInUse,
     #[error("address not found")]
     NotFound,
+    #[error("netlayer closed")]
+    Closed,
+    #[error("address partitioned from this node")]
+    Partitioned,
     #[error("address found in registry, but the receiver has been dropped")]
     ReceiverDropped,
     #[error("MockNetlayer registry poisoned")]
@@ -52,31 +80,165 @@ impl<Guard> From<std::sync::PoisonError<Guard>> for Error {
     }
 }
 
-pub struct MockNetlayer {
-    name: String,
-    connect_recv: AsyncMutex<mpsc::UnboundedReceiver<StreamSend>>,
-    manager: AsyncRwLock<CapTpSessionManager<MockReader, MockWriter>>,
+/// A namespace of [`MockNetlayer`]s which can reach each other.
+///
+/// Networks are independent of each other, so names only need to be unique within one network.
+#[derive(Clone, Default)]
+pub struct MockNetwork {
+    registry: Arc<MockRegistry>,
 }
 
-impl MockNetlayer {
-    pub fn bind(name: String) -> Result<Arc<Self>, Error> {
-        let mut reg = MOCK_REGISTRY.write();
+impl std::fmt::Debug for MockNetwork {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set()
+            .entries(self.registry.read().keys())
+            .finish()
+    }
+}
+
+impl MockNetwork {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the netlayer named `name`, creating it if it doesn't exist.
+    pub fn bind(&self, name: impl Into<String>) -> Result<Arc<MockNetlayer>, Error> {
+        let name = name.into();
+        let mut reg = self.registry.write();
         if let Some(res) = reg.get(&name).and_then(|(p, _)| Weak::upgrade(p)) {
             Ok(res)
         } else {
-            let (connect_send, connect_recv) = mpsc::unbounded_channel();
-            let res = Arc::new(Self {
-                name: name.clone(),
-                connect_recv: AsyncMutex::new(connect_recv),
-                manager: AsyncRwLock::new(CapTpSessionManager::new()),
-            });
-            reg.insert(name, (Arc::downgrade(&res), connect_send));
-            Ok(res)
+            Ok(self.insert(&mut reg, name))
         }
     }
 
-    pub fn close(self) {
-        MOCK_REGISTRY.write().remove(&self.name);
+    /// Create a netlayer named `name`, failing if it already exists.
+    pub(crate) fn bind_new(&self, name: String) -> Result<Arc<MockNetlayer>, Error> {
+        let mut reg = self.registry.write();
+        if reg.get(&name).is_some_and(|(p, _)| p.strong_count() > 0) {
+            return Err(Error::NameInUse);
+        }
+        Ok(self.insert(&mut reg, name))
+    }
+
+    fn insert(
+        &self,
+        reg: &mut HashMap<String, (Weak<MockNetlayer>, mpsc::UnboundedSender<ConnectRequest>)>,
+        name: String,
+    ) -> Arc<MockNetlayer> {
+        let (connect_send, connect_recv) = mpsc::unbounded_channel();
+        let res = Arc::new(MockNetlayer {
+            name: name.clone(),
+            network: self.clone(),
+            connect_recv: AsyncMutex::new(connect_recv),
+            manager: CapTpSessionManager::new(),
+        });
+        reg.insert(name, (Arc::downgrade(&res), connect_send));
+        res
+    }
+}
+
+pub struct MockNetlayer {
+    name: String,
+    network: MockNetwork,
+    connect_recv: AsyncMutex<mpsc::UnboundedReceiver<ConnectRequest>>,
+    manager: CapTpSessionManager<MockReader, MockWriter>,
+}
+
+impl std::fmt::Debug for MockNetlayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockNetlayer")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MockNetlayer {
+    /// Remove this netlayer from its network, stop accepting connections, and abort its sessions.
+    pub async fn close(&self) {
+        {
+            let mut reg = self.network.registry.write();
+            if reg
+                .get(&self.name)
+                .is_some_and(|(p, _)| std::ptr::eq(p.as_ptr(), self))
+            {
+                reg.remove(&self.name);
+            }
+        }
+        self.connect_recv.lock().await.close();
+        let sessions = self.manager.drain();
+        for session in sessions {
+            if let Err(error) = session.abort("netlayer closed").await {
+                tracing::debug!(name = %self.name, %error, "failed to abort session while closing");
+            }
+        }
+    }
+}
+
+impl MockNetlayer {
+    /// Open a connection to the node at `locator`, without starting a session.
+    async fn open(&self, locator: &NodeLocator<'_>) -> Result<(MockReader, MockWriter), Error> {
+        self.open_with(locator, None).await
+    }
+
+    /// Open a connection to the node at `locator`, sharing `link` with the other end.
+    pub(crate) async fn open_with(
+        &self,
+        locator: &NodeLocator<'_>,
+        link: Option<Arc<Link>>,
+    ) -> Result<(MockReader, MockWriter), Error> {
+        let (stream_send, stream_recv) = oneshot::channel();
+        let request = ConnectRequest {
+            from: self.name.clone(),
+            link,
+            stream_send,
+        };
+        if self
+            .network
+            .registry
+            .read()
+            .get(&*locator.designator)
+            .ok_or(Error::NotFound)?
+            .1
+            .send(request)
+            .is_err()
+        {
+            // send failed, therefore receiver has been dropped; clean registry
+            self.network.registry.write().remove(&*locator.designator);
+            return Err(Error::NotFound);
+        }
+        stream_recv.await.map_err(From::from)
+    }
+
+    /// Accept a connection, without starting a session.
+    async fn next_connection(&self) -> Result<(MockReader, MockWriter), Error> {
+        self.connect_recv
+            .lock()
+            .await
+            .recv()
+            .await
+            .ok_or(Error::Closed)?
+            .answer()
+    }
+
+    /// Wait for requests to connect, returning every one which has arrived.
+    pub(crate) async fn requests(&self) -> Result<Vec<ConnectRequest>, Error> {
+        let mut connect_recv = self.connect_recv.lock().await;
+        let mut requests = vec![connect_recv.recv().await.ok_or(Error::Closed)?];
+        while let Ok(request) = connect_recv.try_recv() {
+            requests.push(request);
+        }
+        Ok(requests)
+    }
+
+    /// Connect to the node at `locator` as a raw peer, leaving the handshake to the caller.
+    pub async fn connect_raw(&self, locator: &NodeLocator<'_>) -> Result<RawConnection, Error> {
+        self.open(locator).await.map(RawConnection::from)
+    }
+
+    /// Accept a connection as a raw peer, leaving the handshake to the caller.
+    pub async fn accept_raw(&self) -> Result<RawConnection, Error> {
+        self.next_connection().await.map(RawConnection::from)
     }
 }
 
@@ -85,61 +247,31 @@ impl Netlayer for MockNetlayer {
     type Writer = DuplexStream;
     type Error = Error;
 
-    fn connect<'locator>(
+    async fn connect<'locator>(
         &self,
-        locator: &rexa::locator::NodeLocator<'locator>,
-    ) -> impl Future<
-        Output = Result<rexa::captp::CapTpSession<Self::Reader, Self::Writer>, Self::Error>,
-    > + Send {
-        let remote_name = &locator.designator;
-        async move {
-            if let Some(session) = self.manager.read().await.get(remote_name) {
-                return Ok(session.clone());
-            }
-
-            let (stream_send, stream_recv) = oneshot::channel();
-            if MOCK_REGISTRY
-                .read()
-                .get(&*locator.designator)
-                .ok_or(Error::NotFound)?
-                .1
-                .send(stream_send)
-                .is_err()
-            {
-                // send failed, therefore receiver has been dropped; clean registry
-                MOCK_REGISTRY.write().remove(&*locator.designator);
-                return Err(Error::NotFound);
-            }
-
-            let (reader, writer) = stream_recv.await?;
-            self.manager
-                .write()
-                .await
-                .init_session(reader, writer)
-                .and_connect(NodeLocator::new(&self.name, "mock"))
-                .await
-                .map_err(From::from)
+        locator: &NodeLocator<'locator>,
+    ) -> Result<rexa::captp::CapTpSession<Self::Reader, Self::Writer>, Self::Error> {
+        if let Some(session) = self.manager.get(locator) {
+            return Ok(session);
         }
+
+        let (reader, writer) = self.open(locator).await?;
+        let session = self
+            .manager
+            .init_session(reader, writer)
+            .and_connect(NodeLocator::new(&self.name, "mock"))
+            .await?;
+        self.manager.alias(locator, *session.remote_vkey());
+        Ok(session)
     }
 
     async fn accept(
         &self,
     ) -> Result<rexa::captp::CapTpSession<Self::Reader, Self::Writer>, Self::Error> {
-        let stream_send = self.connect_recv.lock().await.recv().await.unwrap();
-        let (reader, writer) = {
-            // HACK :: there's probably a better way to set this number but whatever
-            let (local_reader, remote_writer) = tokio::io::duplex(1024);
-            let (remote_reader, local_writer) = tokio::io::duplex(1024);
-            stream_send
-                .send((BufReader::new(remote_reader), remote_writer))
-                .map_err(|_err| Error::Accept)?;
-            (local_reader, local_writer)
-        };
+        let (reader, writer) = self.next_connection().await?;
         self.manager
-            .write()
-            .await
-            .init_session(BufReader::new(reader), writer)
-            .and_connect(NodeLocator::new(&self.name, "mock"))
+            .init_session(reader, writer)
+            .and_accept(NodeLocator::new(&self.name, "mock"))
             .await
             .map_err(From::from)
     }
@@ -0,0 +1,100 @@
+use std::io;
+
+use rexa::captp::ReadSyrupError;
+use syrup::{
+    de::{Cursor, LexError, LexErrorKind},
+    TokenTree,
+};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream};
+
+/// Reads whole syrup values from a mock connection, however they're split up on the way.
+#[derive(Debug)]
+pub(crate) struct FrameReader {
+    reader: BufReader<DuplexStream>,
+    /// Bytes read but not yet returned as part of a frame.
+    buf: Vec<u8>,
+}
+
+impl FrameReader {
+    pub(crate) fn new(reader: BufReader<DuplexStream>) -> Self {
+        Self {
+            reader,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Receive the exact bytes of the next syrup value sent by the other end.
+    pub(crate) async fn recv_frame(&mut self) -> Result<Vec<u8>, ReadSyrupError> {
+        loop {
+            match TokenTree::tokenize(Cursor::new(&self.buf[..])) {
+                Ok((_, rem)) => {
+                    let len = self.buf.len() - rem.rem.len();
+                    return Ok(self.buf.drain(..len).collect());
+                }
+                Err(LexError {
+                    kind: LexErrorKind::Incomplete { .. },
+                    ..
+                }) => {}
+                Err(error) => return Err(error.into()),
+            }
+
+            let data = self.reader.fill_buf().await?;
+            if data.is_empty() {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+            let len = data.len();
+            self.buf.extend_from_slice(data);
+            self.reader.consume(len);
+        }
+    }
+
+    pub(crate) async fn recv(&mut self) -> Result<TokenTree<'static>, ReadSyrupError> {
+        let frame = self.recv_frame().await?;
+        let (tree, _) = TokenTree::tokenize_static(Cursor::new(&frame[..]))?;
+        Ok(tree)
+    }
+}
+
+/// One end of a mock connection, driven directly by a test rather than by a session.
+///
+/// Useful for checking exactly what a session sends, or how it reacts to arbitrary input.
+#[derive(Debug)]
+pub struct RawConnection {
+    reader: FrameReader,
+    writer: DuplexStream,
+}
+
+impl From<(BufReader<DuplexStream>, DuplexStream)> for RawConnection {
+    fn from((reader, writer): (BufReader<DuplexStream>, DuplexStream)) -> Self {
+        Self {
+            reader: FrameReader::new(reader),
+            writer,
+        }
+    }
+}
+
+impl RawConnection {
+    /// Send `bytes` as-is.
+    pub async fn send_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.writer.write_all(bytes).await?;
+        self.writer.flush().await
+    }
+
+    pub async fn send(&mut self, msg: &TokenTree<'_>) -> io::Result<()> {
+        self.send_bytes(&msg.encode()).await
+    }
+
+    /// Receive the exact bytes of the next syrup value sent by the other end.
+    pub async fn recv_frame(&mut self) -> Result<Vec<u8>, ReadSyrupError> {
+        self.reader.recv_frame().await
+    }
+
+    pub async fn recv(&mut self) -> Result<TokenTree<'static>, ReadSyrupError> {
+        self.reader.recv().await
+    }
+
+    /// Shut down this end of the connection, so that the other end reads EOF.
+    pub async fn close(mut self) -> io::Result<()> {
+        self.writer.shutdown().await
+    }
+}
@@ -0,0 +1,384 @@
+//! A mock netlayer driven by a seeded random number generator, for reproducible tests of failures.
+//!
+//! Runs are reproducible as long as the tasks using a [`Simulation`] are scheduled deterministically,
+//! e.g. on a current-thread runtime with paused time.
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    future::pending,
+    io,
+    ops::RangeInclusive,
+    pin::pin,
+    sync::Arc,
+    time::Duration,
+};
+
+use futures::future::{select, Either};
+use parking_lot::Mutex;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rexa::{
+    captp::{
+        CapTpMessageRead, CapTpMessageWrite, CapTpSession, CapTpSessionManager, ReadSyrupError,
+    },
+    locator::NodeLocator,
+    netlayer::Netlayer,
+};
+use syrup::TokenTree;
+use tokio::{
+    io::{AsyncWriteExt, DuplexStream},
+    sync::watch,
+};
+
+use super::{
+    ConnectRequest, Error, FrameReader, MockNetlayer, MockNetwork, MockReader, MockWriter,
+};
+
+const TRANSPORT: &str = "mock-sim";
+
+/// Faults applied to every message written within a [`Simulation`].
+#[derive(Debug, Clone)]
+pub struct Faults {
+    /// Delay before each message is written.
+    pub latency: RangeInclusive<Duration>,
+    /// Probability that a message is dropped entirely.
+    pub drop: f64,
+    /// Probability that only part of a message is written.
+    pub truncate: f64,
+}
+
+impl Default for Faults {
+    fn default() -> Self {
+        Self {
+            latency: Duration::ZERO..=Duration::ZERO,
+            drop: 0.0,
+            truncate: 0.0,
+        }
+    }
+}
+
+/// What happens to a message.
+enum Fate {
+    Deliver,
+    Drop,
+    Truncate(usize),
+}
+
+#[derive(Default)]
+struct SimState {
+    partitions: HashSet<(String, String)>,
+    kills: HashMap<(String, String), usize>,
+}
+
+struct SimInner {
+    network: MockNetwork,
+    rng: Mutex<StdRng>,
+    faults: Mutex<Faults>,
+    state: Mutex<SimState>,
+}
+
+fn link(a: &str, b: &str) -> (String, String) {
+    if a <= b {
+        (a.to_owned(), b.to_owned())
+    } else {
+        (b.to_owned(), a.to_owned())
+    }
+}
+
+/// A [`MockNetwork`] of named nodes with injected faults.
+#[derive(Clone)]
+pub struct Simulation {
+    inner: Arc<SimInner>,
+}
+
+impl std::fmt::Debug for Simulation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Simulation")
+            .field("faults", &*self.inner.faults.lock())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Simulation {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            inner: Arc::new(SimInner {
+                network: MockNetwork::default(),
+                rng: Mutex::new(StdRng::seed_from_u64(seed)),
+                faults: Mutex::default(),
+                state: Mutex::default(),
+            }),
+        }
+    }
+
+    pub fn bind(&self, name: impl Into<String>) -> Result<SimNetlayer, Error> {
+        let name = name.into();
+        Ok(SimNetlayer {
+            mock: self.inner.network.bind_new(name.clone())?,
+            name,
+            sim: self.clone(),
+            waiting: Mutex::default(),
+            manager: CapTpSessionManager::new(),
+        })
+    }
+
+    pub fn set_faults(&self, faults: Faults) {
+        *self.inner.faults.lock() = faults;
+    }
+
+    /// Prevent `a` and `b` from connecting, and drop every message between them, until
+    /// [`heal`](Self::heal) is called.
+    pub fn partition(&self, a: &str, b: &str) {
+        self.inner.state.lock().partitions.insert(link(a, b));
+    }
+
+    pub fn heal(&self, a: &str, b: &str) {
+        self.inner.state.lock().partitions.remove(&link(a, b));
+    }
+
+    pub fn is_partitioned(&self, a: &str, b: &str) -> bool {
+        self.inner.state.lock().partitions.contains(&link(a, b))
+    }
+
+    /// Kill each connection from `from` to `to` once `from` has written `messages` messages to it.
+    pub fn kill_after(&self, from: &str, to: &str, messages: usize) {
+        self.inner
+            .state
+            .lock()
+            .kills
+            .insert((from.to_owned(), to.to_owned()), messages);
+    }
+
+    fn kill_limit(&self, from: &str, to: &str) -> Option<usize> {
+        self.inner
+            .state
+            .lock()
+            .kills
+            .get(&(from.to_owned(), to.to_owned()))
+            .copied()
+    }
+
+    fn gen_index(&self, len: usize) -> usize {
+        self.inner.rng.lock().gen_range(0..len)
+    }
+
+    fn roll(&self, from: &str, to: &str, len: usize) -> (Duration, Fate) {
+        let faults = self.inner.faults.lock().clone();
+        let partitioned = self.is_partitioned(from, to);
+        let mut rng = self.inner.rng.lock();
+        let delay = rng.gen_range(faults.latency);
+        let fate = if rng.gen_bool(faults.drop) || partitioned {
+            Fate::Drop
+        } else if len > 0 && rng.gen_bool(faults.truncate) {
+            Fate::Truncate(rng.gen_range(0..len))
+        } else {
+            Fate::Deliver
+        };
+        (delay, fate)
+    }
+}
+
+/// Shared by both ends of a connection.
+pub(crate) struct Link {
+    killed: watch::Sender<bool>,
+}
+
+impl Link {
+    pub(crate) fn new() -> Self {
+        Self {
+            killed: watch::Sender::new(false),
+        }
+    }
+
+    fn kill(&self) {
+        self.killed.send_replace(true);
+    }
+
+    fn is_killed(&self) -> bool {
+        *self.killed.borrow()
+    }
+}
+
+fn killed_error() -> io::Error {
+    io::Error::from(io::ErrorKind::ConnectionReset)
+}
+
+pub struct SimReader {
+    inner: FrameReader,
+    link: Arc<Link>,
+}
+
+impl CapTpMessageRead for SimReader {
+    async fn read_message(&mut self) -> Result<TokenTree<'static>, ReadSyrupError> {
+        let mut killed = self.link.killed.subscribe();
+        let killed = async move {
+            if killed.wait_for(|killed| *killed).await.is_err() {
+                pending::<()>().await;
+            }
+        };
+        match select(pin!(self.inner.recv()), pin!(killed)).await {
+            Either::Left((res, _)) => res,
+            Either::Right(((), _)) => Err(killed_error().into()),
+        }
+    }
+}
+
+pub struct SimWriter {
+    inner: DuplexStream,
+    link: Arc<Link>,
+    sim: Simulation,
+    from: String,
+    to: String,
+    written: usize,
+}
+
+impl CapTpMessageWrite for SimWriter {
+    type Message = Vec<u8>;
+
+    fn prepare_message(msg: &TokenTree<'_>) -> Self::Message {
+        msg.encode().into_owned()
+    }
+
+    async fn write_message(&mut self, msg: Self::Message) -> io::Result<()> {
+        if self.link.is_killed() {
+            return Err(killed_error());
+        }
+        if self
+            .sim
+            .kill_limit(&self.from, &self.to)
+            .is_some_and(|limit| self.written >= limit)
+        {
+            tracing::debug!(from = %self.from, to = %self.to, written = self.written, "killing connection");
+            self.link.kill();
+            return Err(killed_error());
+        }
+        self.written += 1;
+
+        let (delay, fate) = self.sim.roll(&self.from, &self.to, msg.len());
+        tokio::time::sleep(delay).await;
+        match fate {
+            Fate::Deliver => self.inner.write_all(&msg).await,
+            Fate::Drop => {
+                tracing::trace!(from = %self.from, to = %self.to, "dropping message");
+                Ok(())
+            }
+            Fate::Truncate(len) => {
+                tracing::trace!(from = %self.from, to = %self.to, len, "truncating message");
+                self.inner.write_all(&msg[..len]).await
+            }
+        }
+    }
+
+    async fn flush_messages(&mut self) -> io::Result<()> {
+        self.inner.flush().await
+    }
+}
+
+pub struct SimNetlayer {
+    name: String,
+    sim: Simulation,
+    mock: Arc<MockNetlayer>,
+    /// Connections requested but not yet accepted.
+    waiting: Mutex<Vec<ConnectRequest>>,
+    manager: CapTpSessionManager<SimReader, SimWriter>,
+}
+
+impl std::fmt::Debug for SimNetlayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SimNetlayer")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SimNetlayer {
+    fn locator(&self) -> NodeLocator<'_> {
+        NodeLocator::new(
+            Cow::Borrowed(self.name.as_str()),
+            Cow::Borrowed(TRANSPORT),
+        )
+    }
+
+    /// Wrap one end of a connection from this node to `to`.
+    fn wrap(
+        &self,
+        (reader, writer): (MockReader, MockWriter),
+        to: String,
+        link: Arc<Link>,
+    ) -> (SimReader, SimWriter) {
+        (
+            SimReader {
+                inner: FrameReader::new(reader),
+                link: link.clone(),
+            },
+            SimWriter {
+                inner: writer,
+                link,
+                sim: self.sim.clone(),
+                from: self.name.clone(),
+                to,
+                written: 0,
+            },
+        )
+    }
+}
+
+impl Netlayer for SimNetlayer {
+    type Reader = SimReader;
+    type Writer = SimWriter;
+    type Error = Error;
+
+    async fn connect<'loc>(
+        &self,
+        locator: &NodeLocator<'loc>,
+    ) -> Result<CapTpSession<Self::Reader, Self::Writer>, Self::Error> {
+        if let Some(session) = self.manager.get(locator) {
+            return Ok(session);
+        }
+        if self.sim.is_partitioned(&self.name, &locator.designator) {
+            return Err(Error::Partitioned);
+        }
+
+        let link = Arc::new(Link::new());
+        let stream = self.mock.open_with(locator, Some(link.clone())).await?;
+        let (reader, writer) = self.wrap(stream, locator.designator.to_string(), link);
+
+        let session = self
+            .manager
+            .init_session(reader, writer)
+            .and_connect(self.locator())
+            .await?;
+        self.manager.alias(locator, *session.remote_vkey());
+        Ok(session)
+    }
+
+    /// Accept a pending connection, chosen at random.
+    async fn accept(&self) -> Result<CapTpSession<Self::Reader, Self::Writer>, Self::Error> {
+        let request = loop {
+            {
+                let mut waiting = self.waiting.lock();
+                if !waiting.is_empty() {
+                    let index = self.sim.gen_index(waiting.len());
+                    break waiting.remove(index);
+                }
+            }
+            let requests = self.mock.requests().await?;
+            self.waiting.lock().extend(requests);
+        };
+        let from = request.from.clone();
+        let link = request
+            .link
+            .clone()
+            .unwrap_or_else(|| Arc::new(Link::new()));
+        let (reader, writer) = self.wrap(request.answer()?, from, link);
+        self.manager
+            .init_session(reader, writer)
+            .and_accept(self.locator())
+            .await
+            .map_err(From::from)
+    }
+
+    fn locators(&self) -> Vec<NodeLocator<'_>> {
+        vec![self.locator()]
+    }
+}
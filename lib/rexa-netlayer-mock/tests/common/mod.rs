@@ -0,0 +1,59 @@
+use rexa::{
+    captp::{msg::DescImport, BootstrapEvent, CapTpSession, Event, IntoExport},
+    netlayer::Netlayer,
+};
+use rexa_netlayer_mock::{MockNetlayer, MockNetwork};
+
+pub(crate) type BoxError = Box<dyn std::error::Error + Send + Sync>;
+#[allow(dead_code)]
+pub(crate) type Session =
+    CapTpSession<<MockNetlayer as Netlayer>::Reader, <MockNetlayer as Netlayer>::Writer>;
+
+/// Connect `a` to `b`, returning the session from each end.
+#[allow(dead_code)]
+pub(crate) async fn connect<Nl: Netlayer>(
+    a: &Nl,
+    b: &Nl,
+) -> Result<
+    (
+        CapTpSession<Nl::Reader, Nl::Writer>,
+        CapTpSession<Nl::Reader, Nl::Writer>,
+    ),
+    BoxError,
+>
+where
+    Nl::Error: std::error::Error + Send + Sync + 'static,
+{
+    let locator_b = b.locators().pop().unwrap();
+    let (session_ab, session_ba) = tokio::join!(a.connect(&locator_b), b.accept());
+    Ok((session_ab?, session_ba?))
+}
+
+/// Connect two nodes on a network of their own.
+#[allow(dead_code)]
+pub(crate) async fn connected_pair() -> Result<(Session, Session), BoxError> {
+    let network = MockNetwork::new();
+    let node_a = network.bind("a")?;
+    let node_b = network.bind("b")?;
+    connect(&*node_a, &*node_b).await
+}
+
+/// Handle events on `session` until it's aborted, answering every fetch with the result of
+/// `export`.
+#[allow(dead_code)]
+pub(crate) async fn serve<Obj: IntoExport>(
+    session: Session,
+    export: impl Fn() -> Obj,
+) -> Result<(), BoxError> {
+    loop {
+        match session.recv_event().await? {
+            Event::Bootstrap(BootstrapEvent::Fetch { resolver, .. }) => {
+                let pos = session.export_object(export());
+                resolver
+                    .fulfill(pos.position.into(), None, DescImport::default())
+                    .await?;
+            }
+            Event::Abort(_) => break Ok(()),
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use std::{sync::Arc, time::Duration};
+
+use rexa::captp::{
+    msg::{DescExport, DescImport},
+    object::{CapError, DeliverError, FetchError},
+    AbstractCapTpSession, BootstrapEvent, Event,
+};
+use rexa_netlayer_mock::MockNetwork;
+use syrup::{symbol, Decode, Encode};
+
+mod common;
+use common::{connect, connected_pair, serve, BoxError, Session};
+
+struct Relay;
+
+#[rexa::impl_object]
+impl Relay {
+    /// Fetch from the node which delivered this, which can only be answered if its session keeps
+    /// being read while this runs.
+    #[deliver()]
+    async fn relay(
+        &self,
+        #[arg(session)] session: Arc<dyn AbstractCapTpSession + Send + Sync>,
+    ) -> Result<(), String> {
+        match session.into_remote_bootstrap().fetch(b"relay").await {
+            Ok(_) => Ok(()),
+            Err(error) => Err(error.to_string()),
+        }
+    }
+
+    #[deliver()]
+    async fn echo(&self, value: u64) -> Result<u64, String> {
+        Ok(value)
+    }
+}
+
+/// Like [`serve`] with a [`Relay`], but fetches are answered after a delay, and only `relay` is
+/// fulfilled.
+async fn serve_slowly(session: Session) -> Result<(), BoxError> {
+    loop {
+        match session.recv_event().await? {
+            Event::Bootstrap(BootstrapEvent::Fetch { resolver, swiss }) => {
+                let session = session.clone();
+                tokio::spawn(async move {
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    let res = if swiss == b"relay" {
+                        let pos = session.export_object(Arc::new(Relay));
+                        resolver
+                            .fulfill(pos.position.into(), None, DescImport::default())
+                            .await
+                    } else {
+                        resolver
+                            .break_promise("unknown swiss number".to_tokens())
+                            .await
+                    };
+                    res.expect("fetch should be resolved");
+                });
+            }
+            Event::Abort(_) => break Ok(()),
+        }
+    }
+}
+
+/// Fetches time out if the remote never answers.
+#[tokio::test]
+async fn fetch_timeout() -> Result<(), BoxError> {
+    // nothing handles events on `_session_ba`, so the fetch is never answered
+    let (session_ab, _session_ba) = connected_pair().await?;
+    let bootstrap = session_ab.clone().get_remote_bootstrap();
+
+    let timeout = Duration::from_millis(50);
+    match bootstrap.fetch_timeout(b"missing", Some(timeout)).await {
+        Err(FetchError::Deliver(DeliverError::Timeout { to_desc, after })) => {
+            assert_eq!(to_desc, DescExport::from(0).into());
+            assert_eq!(after, timeout);
+        }
+        res => panic!("expected timeout, found {res:?}"),
+    }
+
+    session_ab.set_default_timeout(Some(timeout));
+    assert!(matches!(
+        bootstrap.fetch(b"missing").await,
+        Err(FetchError::Deliver(DeliverError::Timeout { .. }))
+    ));
+
+    Ok(())
+}
+
+/// Handlers run alongside the event loop, so they can wait on answers from the remote.
+#[tokio::test]
+async fn handler_awaits_remote() -> Result<(), BoxError> {
+    let (session_ab, session_ba) = connected_pair().await?;
+    tokio::spawn(serve(session_ab.clone(), || Arc::new(Relay)));
+    tokio::spawn(serve(session_ba, || Arc::new(Relay)));
+
+    let relay = session_ab.get_remote_bootstrap().fetch(b"relay").await?;
+    tokio::time::timeout(
+        Duration::from_secs(5),
+        relay.deliver_and(syrup::sequence![symbol!["relay"]]),
+    )
+    .await??;
+
+    Ok(())
+}
+
+/// Deliveries to a pipelined answer are sent before it resolves, and handled or broken once it
+/// does.
+#[tokio::test]
+async fn pipelined_delivery() -> Result<(), BoxError> {
+    let (session_ab, session_ba) = connected_pair().await?;
+    tokio::spawn(serve(session_ab.clone(), || Arc::new(Relay)));
+    tokio::spawn(serve_slowly(session_ba));
+    let bootstrap = session_ab.get_remote_bootstrap();
+
+    let (fetched, relay) = bootstrap.fetch_pipelined(b"relay").await?;
+    let mut echoed = tokio::time::timeout(
+        Duration::from_secs(5),
+        relay.deliver_and(syrup::sequence![symbol!["echo"], 7u64]),
+    )
+    .await??;
+    assert_eq!(u64::decode(echoed.stream.pop().unwrap())?, 7);
+    assert!(fetched.await.is_ok());
+
+    let (fetched, missing) = bootstrap.fetch_pipelined(b"missing").await?;
+    let res = tokio::time::timeout(
+        Duration::from_secs(5),
+        missing.deliver_and(syrup::sequence![symbol!["echo"], 7u64]),
+    )
+    .await?;
+    assert!(matches!(res, Err(DeliverError::Broken(_))), "{res:?}");
+    assert!(fetched.await.is_err());
+
+    Ok(())
+}
+
+/// Remote objects can be passed back to the session which exported them, but not to others.
+#[tokio::test]
+async fn encode_remote_cap() -> Result<(), BoxError> {
+    let network = MockNetwork::new();
+    let node_a = network.bind("a")?;
+    let node_b = network.bind("b")?;
+    let node_c = network.bind("c")?;
+
+    let (session_ab, session_ba) = connect(&*node_a, &*node_b).await?;
+    let (session_ac, session_ca) = connect(&*node_a, &*node_c).await?;
+    tokio::spawn(serve(session_ab.clone(), || Arc::new(Relay)));
+    tokio::spawn(serve(session_ba, || Arc::new(Relay)));
+    tokio::spawn(serve(session_ac.clone(), || Arc::new(Relay)));
+    tokio::spawn(serve(session_ca, || Arc::new(Relay)));
+
+    let relay_b = session_ab.get_remote_bootstrap().fetch(b"relay").await?;
+    let relay_c = session_ac.get_remote_bootstrap().fetch(b"relay").await?;
+
+    assert!(relay_b.encode_cap(relay_b.clone()).is_ok());
+    assert!(matches!(
+        relay_c.encode_cap(relay_b.clone()),
+        Err(CapError::Handoff { .. })
+    ));
+
+    Ok(())
+}
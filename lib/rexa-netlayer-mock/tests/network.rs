@@ -0,0 +1,70 @@
+use rexa::{captp::Event, netlayer::Netlayer};
+use rexa_netlayer_mock::{Error, MockNetwork};
+
+mod common;
+use common::{connect, BoxError};
+
+#[test]
+fn networks_are_isolated() -> Result<(), BoxError> {
+    let network_a = MockNetwork::new();
+    let network_b = MockNetwork::new();
+    let node = network_a.bind("node")?;
+    assert!(std::sync::Arc::ptr_eq(&node, &network_a.bind("node")?));
+    assert!(!std::sync::Arc::ptr_eq(&node, &network_b.bind("node")?));
+    Ok(())
+}
+
+#[tokio::test]
+async fn close_aborts_sessions() -> Result<(), BoxError> {
+    let network = MockNetwork::new();
+    let node_a = network.bind("a")?;
+    let node_b = network.bind("b")?;
+    let locator_b = node_b.locators().pop().unwrap();
+    let (session_ab, session_ba) = connect(&*node_a, &*node_b).await?;
+
+    node_b.close().await;
+    assert!(session_ba.is_aborted());
+    assert!(matches!(session_ab.recv_event().await?, Event::Abort(_)));
+    assert!(matches!(node_b.accept().await, Err(Error::Closed)));
+
+    let node_c = network.bind("c")?;
+    assert!(matches!(
+        node_c.connect(&locator_b).await,
+        Err(Error::NotFound)
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn crossed_hellos() -> Result<(), BoxError> {
+    let network = MockNetwork::new();
+    let node_a = network.bind("a")?;
+    let node_b = network.bind("b")?;
+    let locator_a = node_a.locators().pop().unwrap();
+    let locator_b = node_b.locators().pop().unwrap();
+
+    let (ab, ba, a_acc, b_acc) = tokio::join!(
+        node_a.connect(&locator_b),
+        node_b.connect(&locator_a),
+        node_a.accept(),
+        node_b.accept()
+    );
+    for res in [ab, ba, a_acc, b_acc] {
+        res?;
+    }
+
+    // both nodes should have kept the same one of the two sessions
+    let session_ab = node_a.connect(&locator_b).await?;
+    let session_ba = node_b.connect(&locator_a).await?;
+    assert!(!session_ab.is_aborted());
+    assert!(!session_ba.is_aborted());
+    assert_eq!(
+        session_ab.signing_key().verifying_key(),
+        *session_ba.remote_vkey()
+    );
+    assert_eq!(
+        session_ba.signing_key().verifying_key(),
+        *session_ab.remote_vkey()
+    );
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use std::sync::{Arc, Mutex};
+
+use rexa::captp::object::RemoteObject;
+use syrup::symbol;
+
+mod common;
+use common::{connected_pair, serve, BoxError};
+
+const COUNT: u64 = 64;
+
+#[derive(Default)]
+struct Recorder {
+    received: Mutex<Vec<u64>>,
+}
+
+#[rexa::impl_object]
+impl Recorder {
+    #[deliver_only()]
+    fn record_only(&self, i: u64) -> Result<(), rexa::captp::object::ObjectError> {
+        self.received.lock().unwrap().push(i);
+        Ok(())
+    }
+
+    #[deliver()]
+    async fn record(&self, i: u64) {
+        // give other deliveries a chance to overtake this one
+        tokio::task::yield_now().await;
+        self.received.lock().unwrap().push(i);
+    }
+}
+
+/// Connect two nodes, serving a [`Recorder`] from the second with `loops` concurrent event loops.
+async fn setup(loops: usize) -> Result<(RemoteObject, Arc<Recorder>), BoxError> {
+    let (session_ab, session_ba) = connected_pair().await?;
+
+    let recorder = Arc::new(Recorder::default());
+    for _ in 0..loops {
+        let recorder = recorder.clone();
+        tokio::spawn(serve(session_ba.clone(), move || recorder.clone()));
+    }
+    tokio::spawn({
+        let session_ab = session_ab.clone();
+        async move { while session_ab.recv_event().await.is_ok() {} }
+    });
+
+    let obj = session_ab.get_remote_bootstrap().fetch(b"recorder").await?;
+    Ok((obj, recorder))
+}
+
+async fn wait_for(recorder: &Recorder) -> Vec<u64> {
+    loop {
+        {
+            let received = recorder.received.lock().unwrap();
+            if received.len() as u64 == COUNT {
+                break received.clone();
+            }
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+}
+
+/// Deliveries through clones of one `RemoteObject` are sent in the order they were made, even if
+/// their futures are awaited in reverse.
+#[tokio::test(flavor = "multi_thread")]
+async fn outbound_order() -> Result<(), BoxError> {
+    let (obj, recorder) = setup(1).await?;
+
+    let objs = (0..COUNT).map(|_| obj.clone()).collect::<Vec<_>>();
+    let sends = objs
+        .iter()
+        .zip(0..COUNT)
+        .map(|(obj, i)| obj.deliver_only(syrup::sequence![symbol!["record_only"], i]))
+        .collect::<Vec<_>>();
+    for send in sends.into_iter().rev() {
+        send.await?;
+    }
+
+    assert_eq!(wait_for(&recorder).await, (0..COUNT).collect::<Vec<_>>());
+    Ok(())
+}
+
+/// Deliveries to one export are handled in the order they were received, even with several
+/// concurrent event loops.
+#[tokio::test(flavor = "multi_thread")]
+async fn inbound_order() -> Result<(), BoxError> {
+    let (obj, recorder) = setup(4).await?;
+
+    futures::future::try_join_all(
+        (0..COUNT).map(|i| obj.deliver_and(syrup::sequence![symbol!["record"], i])),
+    )
+    .await?;
+
+    assert_eq!(wait_for(&recorder).await, (0..COUNT).collect::<Vec<_>>());
+    Ok(())
+}
@@ -0,0 +1,512 @@
+use std::{sync::Arc, time::Duration};
+
+use ed25519_dalek::{Signer, SigningKey};
+use futures::{channel::mpsc, future::BoxFuture, FutureExt, StreamExt};
+use rexa::{
+    captp::{
+        msg::{
+            DescAnswer, DescExport, DescImport, DescImportObject, DescImportPromise, OpAbort,
+            OpDeliver, OpDeliverOnly, OpGcAnswer, OpGcExport, OpListen, OpStartSession,
+        },
+        object::{Object, ObjectError, PromiseResolver, RemoteObject},
+        AbstractCapTpSession, GenericResolver, ImportKind, SendError,
+    },
+    locator::NodeLocator,
+    netlayer::Netlayer,
+};
+use rexa_netlayer_mock::{MockNetwork, RawConnection};
+use syrup::{
+    de::{Cursor, Sequence},
+    sequence, symbol, Encode, TokenTree,
+};
+
+mod common;
+use common::{BoxError, Session};
+
+fn hello(key: &SigningKey, name: &str) -> OpStartSession<'static> {
+    let locator = NodeLocator::new(name.to_owned(), "mock");
+    let sig = key.sign(&(&locator).to_tokens().encode());
+    OpStartSession::new(key.verifying_key().into(), locator, sig.into())
+}
+
+/// Receive an `op:start-session`, checking that it was encoded canonically.
+async fn recv_hello(raw: &mut RawConnection) -> Result<OpStartSession<'static>, BoxError> {
+    let frame = raw.recv_frame().await?;
+    let (tree, _) = TokenTree::tokenize_static(Cursor::new(&frame[..]))?;
+    let msg = tree.decode::<OpStartSession<'static>>()?;
+    assert_eq!(&*msg.to_tokens().encode(), &frame[..]);
+    msg.verify_location()?;
+    Ok(msg)
+}
+
+#[tokio::test(start_paused = true)]
+async fn connector_sends_hello_first() -> Result<(), BoxError> {
+    let network = MockNetwork::new();
+    let node = network.bind("node")?;
+    let peer = network.bind("peer")?;
+    let peer_key = SigningKey::from_bytes(&[1; 32]);
+    let locator = peer.locators().pop().unwrap();
+
+    let (session, ()) = tokio::try_join!(
+        async { node.connect(&locator).await.map_err(BoxError::from) },
+        async {
+            let mut raw = peer.accept_raw().await?;
+            let msg = recv_hello(&mut raw).await?;
+            assert_eq!(msg.acceptable_location.designator, "node");
+            raw.send(&hello(&peer_key, "peer").to_tokens()).await?;
+            Ok(())
+        }
+    )?;
+    assert_eq!(session.remote_vkey(), &peer_key.verifying_key());
+    Ok(())
+}
+
+#[tokio::test(start_paused = true)]
+async fn acceptor_waits_for_hello() -> Result<(), BoxError> {
+    let network = MockNetwork::new();
+    let node = network.bind("node")?;
+    let peer = network.bind("peer")?;
+    let peer_key = SigningKey::from_bytes(&[1; 32]);
+    let locator = node.locators().pop().unwrap();
+
+    let (session, ()) = tokio::try_join!(
+        async { node.accept().await.map_err(BoxError::from) },
+        async {
+            let mut raw = peer.connect_raw(&locator).await?;
+            let early = tokio::time::timeout(Duration::from_secs(10), raw.recv_frame()).await;
+            assert!(early.is_err(), "acceptor spoke first");
+            raw.send(&hello(&peer_key, "peer").to_tokens()).await?;
+            let msg = recv_hello(&mut raw).await?;
+            assert_eq!(msg.acceptable_location.designator, "node");
+            Ok(())
+        }
+    )?;
+    assert_eq!(session.remote_vkey(), &peer_key.verifying_key());
+    Ok(())
+}
+
+/// Passes on the objects delivered to it.
+struct Holder {
+    received: mpsc::UnboundedSender<RemoteObject>,
+}
+
+#[rexa::impl_object]
+impl Holder {
+    #[deliver_only()]
+    fn hold(
+        &self,
+        obj: RemoteObject,
+        promise: RemoteObject,
+    ) -> Result<(), rexa::captp::object::ObjectError> {
+        self.received.unbounded_send(obj).unwrap();
+        self.received.unbounded_send(promise).unwrap();
+        Ok(())
+    }
+}
+
+/// Records which of its lifecycle hooks have been called.
+#[derive(Default)]
+struct Hooked {
+    calls: std::sync::Mutex<Vec<&'static str>>,
+}
+
+impl Hooked {
+    fn calls(&self) -> Vec<&'static str> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+#[rexa::impl_object]
+impl Hooked {
+    #[exported]
+    fn on_exported(&self) {
+        self.calls.lock().unwrap().push("exported");
+    }
+
+    #[gc]
+    fn on_gc(&self) {
+        self.calls.lock().unwrap().push("gc");
+    }
+
+    #[session_aborted]
+    fn on_aborted(&self, reason: &str) {
+        assert_eq!(reason, "done");
+        self.calls.lock().unwrap().push("session_aborted");
+    }
+}
+
+/// Answers every delivery with the remote's bootstrap object, followed by some numbers.
+struct Values;
+
+impl Object for Values {
+    fn deliver_only(
+        self: Arc<Self>,
+        _session: Arc<dyn AbstractCapTpSession + Send + Sync>,
+        _args: Sequence<'static>,
+    ) -> Result<(), ObjectError> {
+        Ok(())
+    }
+
+    fn deliver(
+        self: Arc<Self>,
+        _session: Arc<dyn AbstractCapTpSession + Send + Sync>,
+        _args: Sequence<'static>,
+        resolver: GenericResolver,
+    ) -> BoxFuture<'static, Result<(), ObjectError>> {
+        async move {
+            let values = sequence![DescExport::from(0), 2u64, 3u64];
+            Ok(resolver
+                .fulfill(values, None, DescImport::default())
+                .await?)
+        }
+        .boxed()
+    }
+}
+
+/// Wait for the session to handle everything sent before this, by listening to a promise which
+/// doesn't exist and waiting to be told so.
+async fn sync(raw: &mut RawConnection) -> Result<(), BoxError> {
+    let listen = OpListen {
+        to_desc: DescExport::from(999),
+        listen_desc: DescImportObject { position: 999 }.into(),
+        wants_partial: false,
+    };
+    raw.send(&listen.to_tokens()).await?;
+    raw.recv().await?.decode::<OpDeliverOnly<'static>>()?;
+    Ok(())
+}
+
+/// Connect a node to a raw peer.
+async fn session_with_raw() -> Result<(Session, RawConnection), BoxError> {
+    let network = MockNetwork::new();
+    let node = network.bind("node")?;
+    let peer = network.bind("peer")?;
+    let peer_key = SigningKey::from_bytes(&[1; 32]);
+    let locator = peer.locators().pop().unwrap();
+
+    tokio::try_join!(
+        async { node.connect(&locator).await.map_err(BoxError::from) },
+        async {
+            let mut raw = peer.accept_raw().await?;
+            recv_hello(&mut raw).await?;
+            raw.send(&hello(&peer_key, "peer").to_tokens()).await?;
+            Ok(raw)
+        }
+    )
+}
+
+/// Dropping every reference to a pipelined answer tells the remote to forget it.
+#[tokio::test(start_paused = true)]
+async fn gc_answer_on_drop() -> Result<(), BoxError> {
+    let (session, mut raw) = session_with_raw().await?;
+
+    let (promise, answer) = session
+        .get_remote_bootstrap()
+        .fetch_pipelined(b"swiss")
+        .await?;
+    let fetch = raw.recv().await?.decode::<OpDeliver<'static>>()?;
+    let answer_pos = fetch.answer_pos.expect("fetch should be pipelined");
+
+    let clone = answer.clone();
+    drop(answer);
+    drop(promise);
+    drop(clone);
+    let gc = raw.recv().await?.decode::<OpGcAnswer>()?;
+    assert_eq!(gc.answer_position, answer_pos);
+    Ok(())
+}
+
+/// An answer dropped while another message is still being written is forgotten once that write
+/// finishes, without waiting for another send.
+#[tokio::test(start_paused = true)]
+async fn gc_answer_behind_blocked_write() -> Result<(), BoxError> {
+    let (session, mut raw) = session_with_raw().await?;
+
+    let (promise, answer) = session
+        .get_remote_bootstrap()
+        .fetch_pipelined(b"swiss")
+        .await?;
+    let fetch = raw.recv().await?.decode::<OpDeliver<'static>>()?;
+    let answer_pos = fetch.answer_pos.expect("fetch should be pipelined");
+
+    // larger than the connection's buffer, so it isn't written until the peer reads
+    let blocked = tokio::spawn({
+        let session = session.clone();
+        async move {
+            session
+                .get_remote_bootstrap()
+                .fetch_pipelined(&[0; 4096])
+                .await
+                .map(drop)
+        }
+    });
+    tokio::task::yield_now().await;
+    drop(answer);
+    drop(promise);
+
+    raw.recv().await?.decode::<OpDeliver<'static>>()?;
+    blocked.await??;
+    let gc = tokio::time::timeout(Duration::from_secs(10), raw.recv())
+        .await??
+        .decode::<OpGcAnswer>()?;
+    assert_eq!(gc.answer_position, answer_pos);
+    Ok(())
+}
+
+/// Listeners are notified when exported promises are resolved, and broken if they listen to
+/// something which isn't an exported promise.
+#[tokio::test(start_paused = true)]
+async fn listen() -> Result<(), BoxError> {
+    let (session, mut raw) = session_with_raw().await?;
+    let (fulfilled, fulfill) = PromiseResolver::new();
+    let (broken, break_promise) = PromiseResolver::new();
+    let fulfilled = session.as_dyn().exports().export_promise(fulfilled);
+    let broken = session.as_dyn().exports().export_promise(broken);
+    let events = tokio::spawn({
+        let session = session.clone();
+        async move { session.recv_event().await.map(|_| ()) }
+    });
+
+    let listen = |to_desc: u64, listener: u64| OpListen {
+        to_desc: to_desc.into(),
+        listen_desc: DescImportObject { position: listener }.into(),
+        wants_partial: false,
+    };
+
+    raw.send(&listen(99, 1).to_tokens()).await?;
+    let res = raw.recv().await?.decode::<OpDeliverOnly<'static>>()?;
+    assert_eq!(res.to_desc, DescExport::from(1).into());
+    assert_eq!(
+        res.args.to_tokens().encode(),
+        sequence![symbol!["break"], "unknown promise"]
+            .to_tokens()
+            .encode()
+    );
+
+    raw.send(&listen(fulfilled.position, 2).to_tokens()).await?;
+    raw.send(&listen(broken.position, 3).to_tokens()).await?;
+    fulfill.fulfill(sequence![7u64]).await;
+    let res = raw.recv().await?.decode::<OpDeliver<'static>>()?;
+    assert_eq!(res.to_desc, DescExport::from(2).into());
+    assert_eq!(
+        res.args.to_tokens().encode(),
+        sequence![symbol!["fulfill"], 7u64].to_tokens().encode()
+    );
+
+    break_promise.break_promise("reason".to_tokens()).await;
+    let res = raw.recv().await?.decode::<OpDeliverOnly<'static>>()?;
+    assert_eq!(res.to_desc, DescExport::from(3).into());
+    assert_eq!(
+        res.args.to_tokens().encode(),
+        sequence![symbol!["break"], "reason"].to_tokens().encode()
+    );
+
+    assert!(
+        !events.is_finished(),
+        "listening to an unknown promise ended recv_event"
+    );
+    Ok(())
+}
+
+/// Imports are recorded as they're received, and sends to anything else are rejected.
+#[tokio::test(start_paused = true)]
+async fn imports() -> Result<(), BoxError> {
+    let (session, mut raw) = session_with_raw().await?;
+    let (received, mut held) = mpsc::unbounded();
+    let holder = session.export_object(Arc::new(Holder { received }));
+    tokio::spawn({
+        let session = session.clone();
+        async move { session.recv_event().await.map(|_| ()) }
+    });
+
+    let hold = OpDeliverOnly::new(
+        DescExport::from(holder.position).into(),
+        sequence![
+            symbol!["hold"],
+            DescImportObject { position: 5 },
+            DescImportPromise { position: 6 }
+        ],
+    );
+    raw.send(&hold.to_tokens()).await?;
+    let obj = held.next().await.unwrap();
+    let _promise = held.next().await.unwrap();
+    assert_eq!(session.import_kind(5), Some(ImportKind::Object));
+    assert_eq!(session.import_kind(6), Some(ImportKind::Promise));
+    assert_eq!(session.import_kind(7), None);
+
+    obj.deliver_only(sequence![symbol!["ping"]]).await?;
+    let res = raw.recv().await?.decode::<OpDeliverOnly<'static>>()?;
+    assert_eq!(res.to_desc, DescExport::from(5).into());
+
+    assert!(session.clone().into_remote_object(7.into()).is_none());
+    let unknown = OpDeliverOnly::new(DescExport::from(7).into(), sequence![symbol!["ping"]]);
+    assert!(matches!(
+        obj.session().deliver_only(&unknown).await,
+        Err(SendError::UnknownImport(7))
+    ));
+    Ok(())
+}
+
+/// Exports are collected once the remote has released every reference it was sent, and their
+/// lifecycle hooks are called.
+#[tokio::test(start_paused = true)]
+async fn gc_export() -> Result<(), BoxError> {
+    let (session, mut raw) = session_with_raw().await?;
+    let collected = Arc::new(Hooked::default());
+    let aborted = Arc::new(Hooked::default());
+    let first = session.export_object(collected.clone());
+    let second = session.export_object(collected.clone());
+    session.export_object(aborted.clone());
+    assert_eq!(first.position, second.position);
+    assert_eq!(collected.calls(), ["exported"]);
+    tokio::spawn({
+        let session = session.clone();
+        async move { session.recv_event().await.map(|_| ()) }
+    });
+
+    let gc = OpGcExport {
+        export_position: first.position,
+        wire_delta: 1,
+    };
+    raw.send(&gc.to_tokens()).await?;
+    sync(&mut raw).await?;
+    assert_eq!(collected.calls(), ["exported"]);
+    raw.send(&gc.to_tokens()).await?;
+    sync(&mut raw).await?;
+    assert_eq!(collected.calls(), ["exported", "gc"]);
+
+    session.abort("done").await?;
+    assert_eq!(collected.calls(), ["exported", "gc"]);
+    assert_eq!(aborted.calls(), ["exported", "session_aborted"]);
+    Ok(())
+}
+
+/// An answer fulfilled with several values is sent in order, and deliveries pipelined on it go to
+/// the first.
+#[tokio::test(start_paused = true)]
+async fn pipeline_on_values() -> Result<(), BoxError> {
+    let (session, mut raw) = session_with_raw().await?;
+    let values = session.export_object(Arc::new(Values));
+    tokio::spawn({
+        let session = session.clone();
+        async move { session.recv_event().await.map(|_| ()) }
+    });
+
+    let deliver = OpDeliver::new(
+        DescExport::from(values.position).into(),
+        sequence![symbol!["values"]],
+        Some(5),
+        DescImportObject { position: 9 }.into(),
+    );
+    raw.send(&deliver.to_tokens()).await?;
+    let res = raw.recv().await?.decode::<OpDeliver<'static>>()?;
+    assert_eq!(res.to_desc, DescExport::from(9).into());
+    assert_eq!(
+        res.args.to_tokens().encode(),
+        sequence![symbol!["fulfill"], DescExport::from(0), 2u64, 3u64]
+            .to_tokens()
+            .encode()
+    );
+
+    let pipelined = OpDeliver::new(
+        DescAnswer { position: 5 }.into(),
+        sequence![symbol!["ping"]],
+        Some(6),
+        DescImportObject { position: 10 }.into(),
+    );
+    raw.send(&pipelined.to_tokens()).await?;
+    let res = raw.recv().await?.decode::<OpDeliver<'static>>()?;
+    assert_eq!(res.to_desc, DescExport::from(0).into());
+    assert_eq!(
+        res.args.to_tokens().encode(),
+        sequence![symbol!["ping"]].to_tokens().encode()
+    );
+    Ok(())
+}
+
+/// Deliveries to an exported promise are forwarded once it's fulfilled, and deliveries to unknown
+/// targets are broken without ending `recv_event`.
+#[tokio::test(start_paused = true)]
+async fn deliver_to_promise() -> Result<(), BoxError> {
+    let (session, mut raw) = session_with_raw().await?;
+    let (promise, resolver) = PromiseResolver::new();
+    let promise = session.as_dyn().exports().export_promise(promise);
+    let events = tokio::spawn({
+        let session = session.clone();
+        async move { session.recv_event().await.map(|_| ()) }
+    });
+
+    let ping = |to: u64, resolve_me: u64| {
+        OpDeliver::new(
+            DescExport::from(to).into(),
+            sequence![symbol!["ping"]],
+            None,
+            DescImportObject {
+                position: resolve_me,
+            }
+            .into(),
+        )
+    };
+
+    raw.send(&ping(99, 1).to_tokens()).await?;
+    let res = raw.recv().await?.decode::<OpDeliverOnly<'static>>()?;
+    assert_eq!(res.to_desc, DescExport::from(1).into());
+    assert_eq!(
+        res.args.to_tokens().encode(),
+        sequence![symbol!["break"], "unknown target"]
+            .to_tokens()
+            .encode()
+    );
+
+    raw.send(&ping(promise.position, 2).to_tokens()).await?;
+    sync(&mut raw).await?;
+    resolver.fulfill(sequence![DescExport::from(0)]).await;
+    let res = raw.recv().await?.decode::<OpDeliver<'static>>()?;
+    assert_eq!(res.to_desc, DescExport::from(0).into());
+    assert_eq!(
+        res.args.to_tokens().encode(),
+        sequence![symbol!["ping"]].to_tokens().encode()
+    );
+
+    assert!(
+        !events.is_finished(),
+        "delivering to an unknown target ended recv_event"
+    );
+    Ok(())
+}
+
+/// A node which connects again replaces its previous session, which is aborted and told so, and
+/// can still reconnect once its connection is gone.
+#[tokio::test(start_paused = true)]
+async fn reconnect_after_disconnect() -> Result<(), BoxError> {
+    let network = MockNetwork::new();
+    let node = network.bind("node")?;
+    let peer = network.bind("peer")?;
+    let peer_key = SigningKey::from_bytes(&[1; 32]);
+    let locator = node.locators().pop().unwrap();
+    let reconnect = || {
+        tokio::try_join!(
+            async { node.accept().await.map_err(BoxError::from) },
+            async {
+                let mut raw = peer.connect_raw(&locator).await?;
+                raw.send(&hello(&peer_key, "peer").to_tokens()).await?;
+                recv_hello(&mut raw).await?;
+                Ok(raw)
+            }
+        )
+    };
+
+    let (first, mut first_raw) = reconnect().await?;
+    let (second, second_raw) = reconnect().await?;
+    assert!(first.is_aborted());
+    assert!(!second.is_aborted());
+    let abort = first_raw.recv().await?.decode::<OpAbort<'static>>()?;
+    assert_eq!(abort.reason, "replaced by a new session");
+
+    drop(second_raw);
+    let (third, _third_raw) = reconnect().await?;
+    assert!(second.is_aborted());
+    assert!(!third.is_aborted());
+    assert_eq!(third.remote_vkey(), &peer_key.verifying_key());
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use rexa::captp::object::{CallError, Fetch};
+use syrup::Encode;
+
+mod common;
+use common::{connected_pair, serve, BoxError};
+
+#[derive(Debug, thiserror::Error, Encode)]
+#[error("{message}")]
+#[syrup(label = "counter:error")]
+struct CounterError {
+    message: String,
+}
+
+impl From<CallError> for CounterError {
+    fn from(error: CallError) -> Self {
+        Self {
+            message: error.to_string(),
+        }
+    }
+}
+
+#[rexa::remote_object]
+trait Counter {
+    #[deliver()]
+    async fn add(&self, amount: u64) -> Result<u64, CounterError>;
+
+    #[deliver(symbol = "add-checked")]
+    async fn add_checked(&self, amount: u64, limit: u64) -> Result<u64, CounterError>;
+}
+
+#[derive(Default)]
+struct LocalCounter {
+    count: AtomicU64,
+}
+
+#[rexa::impl_object]
+impl Counter for LocalCounter {
+    async fn add(&self, amount: u64) -> Result<u64, CounterError> {
+        Ok(self.count.fetch_add(amount, Ordering::Relaxed) + amount)
+    }
+
+    #[deliver(symbol = "add-checked")]
+    async fn add_checked(&self, amount: u64, limit: u64) -> Result<u64, CounterError> {
+        if self.count.load(Ordering::Relaxed) + amount > limit {
+            return Err(CounterError {
+                message: format!("count would exceed {limit}"),
+            });
+        }
+        self.add(amount).await
+    }
+}
+
+/// Add to a counter through the trait, whether it's local or remote.
+async fn add_twice(counter: &impl Counter, amount: u64) -> Result<u64, CounterError> {
+    counter.add(amount).await?;
+    counter.add(amount).await
+}
+
+/// Calls through a proxy reach the local object's implementation of the same trait.
+#[tokio::test]
+async fn local_and_remote() -> Result<(), BoxError> {
+    let (session_ab, session_ba) = connected_pair().await?;
+    let counter = Arc::new(LocalCounter::default());
+    let events_ab = session_ab.clone();
+    tokio::spawn(async move { while events_ab.recv_event().await.is_ok() {} });
+    tokio::spawn({
+        let counter = counter.clone();
+        serve(session_ba, move || counter.clone())
+    });
+
+    assert_eq!(add_twice(&*counter, 1).await?, 2);
+    let remote = RemoteCounter::fetch(&session_ab.get_remote_bootstrap(), b"counter").await?;
+    assert_eq!(add_twice(&remote, 2).await?, 6);
+    assert_eq!(counter.count.load(Ordering::Relaxed), 6);
+
+    assert_eq!(remote.add_checked(4, 10).await?, 10);
+    // the remote's error is only available as the reason the promise was broken
+    let error = remote.add_checked(1, 10).await.unwrap_err();
+    assert!(error.message.contains("count would exceed 10"), "{error:?}");
+    let error = counter.add_checked(1, 10).await.unwrap_err();
+    assert_eq!(error.message, "count would exceed 10");
+
+    Ok(())
+}
@@ -0,0 +1,202 @@
+use std::time::Duration;
+
+use rexa::{captp::Event, netlayer::Netlayer};
+use rexa_netlayer_mock::{Error, Faults, Simulation};
+use tokio::time::{timeout, Instant};
+
+mod common;
+use common::{connect, BoxError};
+
+/// Connect three nodes to a fourth, returning the order in which their connections were accepted.
+async fn accept_order(seed: u64) -> Result<Vec<usize>, BoxError> {
+    let sim = Simulation::new(seed);
+    let server = sim.bind("server")?;
+    let clients = [sim.bind("a")?, sim.bind("b")?, sim.bind("c")?];
+    let locator = server.locators().pop().unwrap();
+
+    let (a, b, c, accepted) = tokio::join!(
+        clients[0].connect(&locator),
+        clients[1].connect(&locator),
+        clients[2].connect(&locator),
+        async {
+            let mut accepted = Vec::new();
+            for _ in 0..3 {
+                accepted.push(server.accept().await?);
+            }
+            Ok::<_, Error>(accepted)
+        }
+    );
+    let keys = [a?, b?, c?].map(|session| session.signing_key().verifying_key());
+    Ok(accepted?
+        .iter()
+        .map(|session| {
+            keys.iter()
+                .position(|key| key == session.remote_vkey())
+                .unwrap()
+        })
+        .collect())
+}
+
+#[tokio::test(start_paused = true)]
+async fn accept_order_is_reproducible() -> Result<(), BoxError> {
+    assert_eq!(accept_order(7).await?, accept_order(7).await?);
+
+    let first = accept_order(0).await?;
+    let mut others = Vec::new();
+    for seed in 1..16 {
+        others.push(accept_order(seed).await?);
+    }
+    assert!(others.iter().any(|order| *order != first));
+    Ok(())
+}
+
+#[tokio::test(start_paused = true)]
+async fn latency_delays_messages() -> Result<(), BoxError> {
+    let sim = Simulation::new(0);
+    let node_a = sim.bind("a")?;
+    let node_b = sim.bind("b")?;
+
+    sim.set_faults(Faults {
+        latency: Duration::from_secs(1)..=Duration::from_secs(1),
+        ..Faults::default()
+    });
+    let start = Instant::now();
+    let (_session_ab, _session_ba) = connect(&node_a, &node_b).await?;
+    assert!(start.elapsed() >= Duration::from_secs(1));
+    Ok(())
+}
+
+#[tokio::test(start_paused = true)]
+async fn dropped_messages_never_arrive() -> Result<(), BoxError> {
+    let sim = Simulation::new(0);
+    let node_a = sim.bind("a")?;
+    let node_b = sim.bind("b")?;
+
+    let (session_ab, session_ba) = connect(&node_a, &node_b).await?;
+
+    sim.set_faults(Faults {
+        drop: 1.0,
+        ..Faults::default()
+    });
+    session_ab.abort("dropped").await?;
+    assert!(timeout(Duration::from_secs(10), session_ba.recv_event())
+        .await
+        .is_err());
+    Ok(())
+}
+
+#[tokio::test(start_paused = true)]
+async fn truncated_messages_fail_to_read() -> Result<(), BoxError> {
+    let sim = Simulation::new(0);
+    let node_a = sim.bind("a")?;
+    let node_b = sim.bind("b")?;
+
+    let (session_ab, session_ba) = connect(&node_a, &node_b).await?;
+
+    sim.set_faults(Faults {
+        truncate: 1.0,
+        ..Faults::default()
+    });
+    session_ab.abort("truncated").await?;
+    drop(session_ab);
+    drop(node_a);
+    assert!(session_ba.recv_event().await.is_err());
+    Ok(())
+}
+
+#[tokio::test(start_paused = true)]
+async fn abort_reaches_remote() -> Result<(), BoxError> {
+    let sim = Simulation::new(0);
+    let node_a = sim.bind("a")?;
+    let node_b = sim.bind("b")?;
+
+    let (session_ab, session_ba) = connect(&node_a, &node_b).await?;
+
+    session_ab.abort("done").await?;
+    assert!(matches!(session_ba.recv_event().await?, Event::Abort(_)));
+    assert!(session_ba.is_aborted());
+    Ok(())
+}
+
+#[tokio::test(start_paused = true)]
+async fn reconnect_after_kill() -> Result<(), BoxError> {
+    let sim = Simulation::new(0);
+    let node_a = sim.bind("a")?;
+    let node_b = sim.bind("b")?;
+
+    sim.kill_after("a", "b", 1);
+    let (session_ab, _session_ba) = connect(&node_a, &node_b).await?;
+    assert!(session_ab.abort("killed").await.is_err());
+
+    let (new_ab, new_ba) = connect(&node_a, &node_b).await?;
+    assert!(session_ab.is_aborted());
+    assert!(!new_ab.is_aborted());
+    assert!(!new_ba.is_aborted());
+    assert_eq!(new_ab.signing_key().verifying_key(), *new_ba.remote_vkey());
+    assert_eq!(new_ba.signing_key().verifying_key(), *new_ab.remote_vkey());
+    Ok(())
+}
+
+#[tokio::test(start_paused = true)]
+async fn crossed_hellos() -> Result<(), BoxError> {
+    let sim = Simulation::new(0);
+    let node_a = sim.bind("a")?;
+    let node_b = sim.bind("b")?;
+    let locator_a = node_a.locators().pop().unwrap();
+    let locator_b = node_b.locators().pop().unwrap();
+
+    let (ab, ba, a_acc, b_acc) = tokio::join!(
+        node_a.connect(&locator_b),
+        node_b.connect(&locator_a),
+        node_a.accept(),
+        node_b.accept()
+    );
+    for res in [ab, ba, a_acc, b_acc] {
+        res?;
+    }
+
+    // both nodes should have kept the same one of the two sessions
+    let session_ab = node_a.connect(&locator_b).await?;
+    let session_ba = node_b.connect(&locator_a).await?;
+    assert!(!session_ab.is_aborted());
+    assert!(!session_ba.is_aborted());
+    assert_eq!(
+        session_ab.signing_key().verifying_key(),
+        *session_ba.remote_vkey()
+    );
+    assert_eq!(
+        session_ba.signing_key().verifying_key(),
+        *session_ab.remote_vkey()
+    );
+    Ok(())
+}
+
+#[tokio::test(start_paused = true)]
+async fn partition_refuses_connections() -> Result<(), BoxError> {
+    let sim = Simulation::new(0);
+    let node_a = sim.bind("a")?;
+    let node_b = sim.bind("b")?;
+    let locator_b = node_b.locators().pop().unwrap();
+
+    sim.partition("b", "a");
+    assert!(matches!(
+        node_a.connect(&locator_b).await,
+        Err(Error::Partitioned)
+    ));
+    Ok(())
+}
+
+#[tokio::test(start_paused = true)]
+async fn kill_after_closes_both_ends() -> Result<(), BoxError> {
+    let sim = Simulation::new(0);
+    let node_a = sim.bind("a")?;
+    let node_b = sim.bind("b")?;
+
+    // only op:start-session gets through
+    sim.kill_after("a", "b", 1);
+    let (session_ab, session_ba) = connect(&node_a, &node_b).await?;
+
+    assert!(session_ab.abort("killed").await.is_err());
+    assert!(session_ba.recv_event().await.is_err());
+    Ok(())
+}
@@ -0,0 +1,36 @@
+//! Restricted discovery, which lets only authorized clients find an onion service.
+
+use arti_client::{HsClientDescEncKey, HsId};
+use tor_hsservice::{config::OnionServiceConfigBuilder, HsClientNickname};
+use tor_keymgr::KeystoreSelector;
+use tor_rtcompat::Runtime;
+
+use crate::{Error, OnionNetlayer};
+
+impl<Rt: Runtime> OnionNetlayer<Rt> {
+    /// Get the key with which this node can discover the onion service at `designator`, generating
+    /// it if necessary. The service must list the key among its authorized clients if it uses
+    /// restricted discovery; see [`restrict_discovery`].
+    pub fn discovery_key(&self, designator: &str) -> Result<HsClientDescEncKey, Error> {
+        let hsid = format!("{designator}.onion")
+            .parse::<HsId>()
+            .map_err(|_| Error::Designator(designator.to_owned()))?;
+        match self.client.get_service_discovery_key(hsid)? {
+            Some(key) => Ok(key),
+            None => self
+                .client
+                .generate_service_discovery_key(KeystoreSelector::Default, hsid)
+                .map_err(From::from),
+        }
+    }
+}
+
+/// Allow only the given clients to discover an onion service, identified by their discovery keys.
+pub fn restrict_discovery(
+    config: &mut OnionServiceConfigBuilder,
+    clients: impl IntoIterator<Item = (HsClientNickname, HsClientDescEncKey)>,
+) {
+    let restricted = config.restricted_discovery();
+    restricted.enabled(true);
+    restricted.static_keys().access().extend(clients);
+}
@@ -0,0 +1,55 @@
+use std::path::Path;
+
+use fs_mistrust::Mistrust;
+use tor_hscrypto::pk::HsIdKeypair;
+use tor_hsservice::{HsIdKeypairSpecifier, HsNickname};
+use tor_keymgr::{ArtiNativeKeystore, KeyMgr, KeyMgrBuilder, KeystoreSelector};
+
+use crate::Error;
+
+/// The identity key of an onion service, which determines its `.onion` address.
+///
+/// Arti keeps identity keys in its keystore (by default, the `keystore` directory within its state
+/// directory), keyed by the service's nickname. A service keeps its address across restarts as long
+/// as the key stays there; this allows it to be backed up, or moved into a new state directory.
+pub struct OnionIdentity {
+    keypair: HsIdKeypair,
+}
+
+impl std::fmt::Debug for OnionIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OnionIdentity").finish_non_exhaustive()
+    }
+}
+
+impl From<HsIdKeypair> for OnionIdentity {
+    fn from(keypair: HsIdKeypair) -> Self {
+        Self { keypair }
+    }
+}
+
+/// Open the Arti keystore at `path` with a key manager, as used by [`OnionIdentity`].
+pub fn open_keystore(path: &Path, mistrust: &Mistrust) -> Result<KeyMgr, Error> {
+    let keystore = ArtiNativeKeystore::from_path_and_mistrust(path, mistrust)?;
+    Ok(KeyMgrBuilder::default()
+        .default_store(Box::new(keystore))
+        .build()?)
+}
+
+impl OnionIdentity {
+    /// Get the identity of the service called `nickname`, if it has one.
+    pub fn from_keymgr(keymgr: &KeyMgr, nickname: &HsNickname) -> Result<Option<Self>, Error> {
+        let spec = HsIdKeypairSpecifier::new(nickname.clone());
+        Ok(keymgr.get::<HsIdKeypair>(&spec)?.map(Self::from))
+    }
+
+    /// Store this identity in the default keystore of `keymgr`, for the service called `nickname`.
+    pub fn store(self, keymgr: &KeyMgr, nickname: &HsNickname) -> Result<(), Error> {
+        let spec = HsIdKeypairSpecifier::new(nickname.clone());
+        Ok(keymgr.insert(self.keypair, &spec, KeystoreSelector::Default)?)
+    }
+
+    pub fn into_keypair(self) -> HsIdKeypair {
+        self.keypair
+    }
+}
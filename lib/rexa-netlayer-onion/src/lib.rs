@@ -11,7 +11,15 @@ use tor_cell::relaycell::msg::Connected;
 use tor_hsservice::{OnionServiceConfig, RunningOnionService, StreamRequest};
 use tor_rtcompat::Runtime;
 // TODO :: remove hard tokio dependency from rexa-netlayer-onion
-use tokio::{io::BufReader, sync::RwLock};
+use tokio::io::BufReader;
+
+mod identity;
+pub use identity::*;
+
+#[cfg(feature = "restricted-discovery")]
+mod discovery;
+#[cfg(feature = "restricted-discovery")]
+pub use discovery::*;
 
 #[repr(transparent)]
 struct TorLocator<'l>(&'l NodeLocator<'l>);
@@ -43,6 +51,14 @@ pub enum Error {
     Tor(#[from] arti_client::Error),
     #[error(transparent)]
     Client(#[from] tor_hsservice::ClientError),
+    #[error(transparent)]
+    Keystore(#[from] tor_keymgr::Error),
+    #[error(transparent)]
+    KeystoreBuilder(#[from] tor_keymgr::KeyMgrBuilderError),
+    #[error("invalid onion designator: {0}")]
+    Designator(String),
+    #[error("onion service has no identity")]
+    MissingIdentity,
     #[error("session manager lock poisoned")]
     LockPoisoned,
     #[error(transparent)]
@@ -57,9 +73,10 @@ impl<Guard> From<std::sync::PoisonError<Guard>> for Error {
 
 pub struct OnionNetlayer<AsyncRuntime: Runtime> {
     service: Arc<RunningOnionService>,
+    designator: String,
     req_stream: Mutex<BoxStream<'static, StreamRequest>>,
     client: TorClient<AsyncRuntime>,
-    manager: RwLock<CapTpSessionManager<<Self as Netlayer>::Reader, <Self as Netlayer>::Writer>>,
+    manager: CapTpSessionManager<<Self as Netlayer>::Reader, <Self as Netlayer>::Writer>,
 }
 
 impl<Rt: Runtime> std::fmt::Debug for OnionNetlayer<Rt> {
@@ -71,16 +88,18 @@ impl<Rt: Runtime> std::fmt::Debug for OnionNetlayer<Rt> {
 }
 
 impl<Rt: Runtime> OnionNetlayer<Rt> {
-    pub fn new(
-        client: TorClient<Rt>,
-        service_config: OnionServiceConfig,
-    ) -> Result<Self, arti_client::Error> {
+    pub fn new(client: TorClient<Rt>, service_config: OnionServiceConfig) -> Result<Self, Error> {
         let (service, stream) = client.launch_onion_service(service_config)?;
+        let name = service
+            .onion_name()
+            .ok_or(Error::MissingIdentity)?
+            .to_string();
         Ok(Self {
             service,
+            designator: name.strip_suffix(".onion").unwrap_or(&name).to_owned(),
             req_stream: tor_hsservice::handle_rend_requests(stream).boxed().into(),
             client,
-            manager: RwLock::new(CapTpSessionManager::new()),
+            manager: CapTpSessionManager::new(),
         })
     }
 
@@ -92,7 +111,7 @@ impl<Rt: Runtime> OnionNetlayer<Rt> {
         runtime: Rt,
         client_config: TorClientConfig,
         service_config: OnionServiceConfig,
-    ) -> Result<Self, arti_client::Error> {
+    ) -> Result<Self, Error> {
         let client = TorClient::with_runtime(runtime)
             .config(client_config)
             .create_bootstrapped()
@@ -100,15 +119,9 @@ impl<Rt: Runtime> OnionNetlayer<Rt> {
         Self::new(client, service_config)
     }
 
-    pub fn designator(&self) -> String {
-        // HACK :: there's probably a better way to do this
-        let mut name = self
-            .service
-            .onion_name()
-            .expect("OnionNetlayer should know own onion service name")
-            .to_string();
-        name.truncate(name.len() - ".onion".len());
-        name
+    /// The name of this node's onion service, without the `.onion` suffix.
+    pub fn designator(&self) -> &str {
+        &self.designator
     }
 }
 
@@ -122,14 +135,17 @@ impl<R: Runtime> Netlayer for OnionNetlayer<R> {
         &self,
         locator: &NodeLocator<'loc>,
     ) -> Result<CapTpSession<Self::Reader, Self::Writer>, Self::Error> {
+        if let Some(session) = self.manager.get(locator) {
+            return Ok(session);
+        }
         let (reader, writer) = self.client.connect(TorLocator(locator)).await?.split();
-        self.manager
-            .write()
-            .await
+        let session = self
+            .manager
             .init_session(BufReader::new(reader), writer)
             .and_connect(NodeLocator::new(self.designator(), "onion"))
-            .await
-            .map_err(From::from)
+            .await?;
+        self.manager.alias(locator, *session.remote_vkey());
+        Ok(session)
     }
 
     async fn accept(&self) -> Result<CapTpSession<Self::Reader, Self::Writer>, Self::Error> {
@@ -145,8 +161,6 @@ impl<R: Runtime> Netlayer for OnionNetlayer<R> {
             .split();
 
         self.manager
-            .write()
-            .await
             .init_session(BufReader::new(reader), writer)
             .and_accept(NodeLocator::new(self.designator(), "onion"))
             .await
@@ -0,0 +1,104 @@
+use std::path::Path;
+
+use arti_client::{
+    config::{BoolOrAuto, TorClientConfigBuilder},
+    BootstrapBehavior, TorClient,
+};
+use fs_mistrust::Mistrust;
+use rexa_netlayer_onion::{open_keystore, OnionIdentity, OnionNetlayer};
+#[cfg(feature = "restricted-discovery")]
+use rexa_netlayer_onion::{restrict_discovery, Error};
+#[cfg(feature = "restricted-discovery")]
+use tor_hsservice::HsClientNickname;
+use tor_hsservice::{config::OnionServiceConfigBuilder, HsNickname};
+use tor_rtcompat::PreferredRuntime;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+fn service_config(nickname: &HsNickname) -> OnionServiceConfigBuilder {
+    let mut config = OnionServiceConfigBuilder::default();
+    config.nickname(nickname.clone());
+    config
+}
+
+/// Launch an onion service without connecting to the tor network.
+fn launch(
+    state_dir: &Path,
+    service: &OnionServiceConfigBuilder,
+) -> Result<OnionNetlayer<PreferredRuntime>, BoxError> {
+    let mut config = TorClientConfigBuilder::from_directories(state_dir, state_dir.join("cache"));
+    config.storage().permissions().dangerously_trust_everyone();
+    config
+        .storage()
+        .keystore()
+        .enabled(BoolOrAuto::Explicit(true));
+    let client = TorClient::with_runtime(PreferredRuntime::current()?)
+        .config(config.build()?)
+        .bootstrap_behavior(BootstrapBehavior::Manual)
+        .create_unbootstrapped()?;
+    Ok(OnionNetlayer::new(client, service.build()?)?)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn identity_persists() -> Result<(), BoxError> {
+    let nickname = HsNickname::new("rexa_identity".to_owned())?;
+    let service = service_config(&nickname);
+    let state_dir = tempfile::tempdir()?;
+
+    let designator = launch(state_dir.path(), &service)?.designator().to_owned();
+    assert_eq!(
+        launch(state_dir.path(), &service)?.designator(),
+        designator,
+        "designator changed across restarts"
+    );
+
+    // move the identity into a fresh state directory
+    let mistrust = Mistrust::new_dangerously_trust_everyone();
+    let keymgr = open_keystore(&state_dir.path().join("keystore"), &mistrust)?;
+    let identity = OnionIdentity::from_keymgr(&keymgr, &nickname)?
+        .expect("launching a service should store its identity");
+    let new_state_dir = tempfile::tempdir()?;
+    let new_keymgr = open_keystore(&new_state_dir.path().join("keystore"), &mistrust)?;
+    identity.store(&new_keymgr, &nickname)?;
+    assert_eq!(
+        launch(new_state_dir.path(), &service)?.designator(),
+        designator
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "restricted-discovery")]
+#[tokio::test(flavor = "multi_thread")]
+async fn restricted_discovery() -> Result<(), BoxError> {
+    let server_nickname = HsNickname::new("rexa_restricted_server".to_owned())?;
+    let client_nickname = HsNickname::new("rexa_restricted_client".to_owned())?;
+    let server_dir = tempfile::tempdir()?;
+    let client_dir = tempfile::tempdir()?;
+
+    let server = launch(server_dir.path(), &service_config(&server_nickname))?;
+    let client = launch(client_dir.path(), &service_config(&client_nickname))?;
+
+    // the client keeps using the key it generated for the server
+    let key = client.discovery_key(server.designator())?;
+    assert_eq!(client.discovery_key(server.designator())?, key);
+    assert!(matches!(
+        client.discovery_key("not-an-onion-service"),
+        Err(Error::Designator(_))
+    ));
+
+    // restricting discovery doesn't change the server's address
+    let mut restricted = service_config(&server_nickname);
+    restrict_discovery(
+        &mut restricted,
+        [("rexa_client".parse::<HsClientNickname>()?, key)],
+    );
+    let designator = server.designator().to_owned();
+    drop(server);
+    assert_eq!(
+        launch(server_dir.path(), &restricted)?.designator(),
+        designator
+    );
+
+    Ok(())
+}
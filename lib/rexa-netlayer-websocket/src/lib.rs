@@ -0,0 +1,313 @@
+//! CapTP over WebSocket binary messages.
+//!
+//! Each CapTP message is sent as a single binary message, as in the Goblins and Endo WebSocket
+//! netlayers. Received messages are read as one continuous stream, so a peer which splits or joins
+//! CapTP messages across binary messages is still understood.
+//!
+//! Locators use the `websocket` transport, with `host` and `port` hints, and an optional `scheme`
+//! hint of `ws` (the default) or `wss`. Unlike the framing, these hints are this crate's own
+//! convention rather than one shared with Goblins, so locators from other implementations may need
+//! to be translated before they can be dialed.
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+
+use futures::{
+    stream::{SplitSink, SplitStream},
+    SinkExt, StreamExt,
+};
+use rexa::{
+    captp::{CapTpSession, CapTpSessionManager, SessionInitError},
+    locator::NodeLocator,
+    netlayer::Netlayer,
+};
+use syrup::symbol;
+use tokio::{
+    io::{AsyncBufRead, AsyncRead, AsyncWrite, ReadBuf},
+    net::{TcpListener, TcpStream},
+    sync::{mpsc, Mutex},
+    task::JoinHandle,
+};
+use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+const TRANSPORT: &str = "websocket";
+/// How long an accepted connection has to complete its opening handshake before it's dropped.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// How many connections may be handshaking or waiting for `accept` at once.
+const BACKLOG: usize = 32;
+
+type Stream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    WebSocket(#[from] tokio_tungstenite::tungstenite::Error),
+    #[error(transparent)]
+    Init(#[from] SessionInitError),
+    #[error("expected connect address to specify {0}")]
+    MissingHint(&'static str),
+    #[error("unsupported websocket scheme: {0}")]
+    Scheme(String),
+}
+
+fn into_io(error: tokio_tungstenite::tungstenite::Error) -> io::Error {
+    match error {
+        tokio_tungstenite::tungstenite::Error::Io(error) => error,
+        error => io::Error::new(io::ErrorKind::Other, error),
+    }
+}
+
+/// Get the url referred to by a `websocket` locator.
+fn url(locator: &NodeLocator<'_>) -> Result<String, Error> {
+    let host = locator.hint("host").ok_or(Error::MissingHint("host"))?;
+    let port = locator.hint("port").ok_or(Error::MissingHint("port"))?;
+    let scheme = locator.hint("scheme").map_or("ws", |s| s.as_ref());
+    if scheme != "ws" && scheme != "wss" {
+        return Err(Error::Scheme(scheme.to_owned()));
+    }
+    Ok(if host.contains(':') {
+        format!("{scheme}://[{host}]:{port}/")
+    } else {
+        format!("{scheme}://{host}:{port}/")
+    })
+}
+
+/// Reads the contents of binary messages as a continuous stream.
+pub struct WebSocketReader {
+    stream: SplitStream<Stream>,
+    msg: Vec<u8>,
+    pos: usize,
+}
+
+impl From<SplitStream<Stream>> for WebSocketReader {
+    fn from(stream: SplitStream<Stream>) -> Self {
+        Self {
+            stream,
+            msg: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl AsyncBufRead for WebSocketReader {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+        while this.pos == this.msg.len() {
+            match ready!(this.stream.poll_next_unpin(cx)) {
+                Some(Ok(Message::Binary(msg))) => {
+                    this.msg = msg;
+                    this.pos = 0;
+                }
+                Some(Ok(Message::Close(_))) | None => return Poll::Ready(Ok(&[])),
+                Some(Ok(msg)) => tracing::trace!(?msg, "ignoring non-binary message"),
+                Some(Err(error)) => return Poll::Ready(Err(into_io(error))),
+            }
+        }
+        Poll::Ready(Ok(&this.msg[this.pos..]))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let this = self.get_mut();
+        this.pos = (this.pos + amt).min(this.msg.len());
+    }
+}
+
+impl AsyncRead for WebSocketReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let available = ready!(Pin::new(&mut *this).poll_fill_buf(cx))?;
+        let amt = available.len().min(buf.remaining());
+        buf.put_slice(&available[..amt]);
+        Pin::new(this).consume(amt);
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Sends each write as a binary message.
+///
+/// Writes are always accepted in full, so each CapTP message becomes a single binary message.
+pub struct WebSocketWriter {
+    sink: SplitSink<Stream, Message>,
+}
+
+impl From<SplitSink<Stream, Message>> for WebSocketWriter {
+    fn from(sink: SplitSink<Stream, Message>) -> Self {
+        Self { sink }
+    }
+}
+
+impl AsyncWrite for WebSocketWriter {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.sink.poll_ready_unpin(cx)).map_err(into_io)?;
+        this.sink
+            .start_send_unpin(Message::Binary(buf.to_vec()))
+            .map_err(into_io)?;
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().sink.poll_flush_unpin(cx).map_err(into_io)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().sink.poll_close_unpin(cx).map_err(into_io)
+    }
+}
+
+fn split(stream: Stream) -> (WebSocketReader, WebSocketWriter) {
+    let (sink, stream) = stream.split();
+    (stream.into(), sink.into())
+}
+
+pub struct WebSocketNetlayer {
+    listener: Arc<TcpListener>,
+    accepted: Mutex<mpsc::Receiver<io::Result<Stream>>>,
+    task: JoinHandle<()>,
+    manager: CapTpSessionManager<WebSocketReader, WebSocketWriter>,
+}
+
+impl std::fmt::Debug for WebSocketNetlayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebSocketNetlayer")
+            .field("listener", &self.listener)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for WebSocketNetlayer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Accept connections from `listener`, running each opening handshake in a task of its own so that
+/// a stalled peer doesn't hold up the others. Failed handshakes are logged and dropped.
+async fn accept_loop(listener: Arc<TcpListener>, sender: mpsc::Sender<io::Result<Stream>>) {
+    // reserve a slot before accepting, so that at most BACKLOG connections are held
+    while let Ok(permit) = sender.clone().reserve_owned().await {
+        let (stream, addr) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(error) => {
+                permit.send(Err(error));
+                continue;
+            }
+        };
+        tokio::spawn(async move {
+            let handshake = tokio_tungstenite::accept_async(MaybeTlsStream::Plain(stream));
+            match tokio::time::timeout(HANDSHAKE_TIMEOUT, handshake).await {
+                Ok(Ok(stream)) => {
+                    permit.send(Ok(stream));
+                }
+                Ok(Err(error)) => tracing::debug!(%addr, %error, "handshake failed"),
+                Err(_) => tracing::debug!(%addr, "handshake timed out"),
+            }
+        });
+    }
+}
+
+impl WebSocketNetlayer {
+    /// Start accepting connections from `listener`. Must be called within a tokio runtime.
+    pub fn new(listener: TcpListener) -> Self {
+        let listener = Arc::new(listener);
+        let (sender, receiver) = mpsc::channel(BACKLOG);
+        Self {
+            task: tokio::spawn(accept_loop(listener.clone(), sender)),
+            listener,
+            accepted: Mutex::new(receiver),
+            manager: CapTpSessionManager::new(),
+        }
+    }
+
+    pub async fn bind(addr: &SocketAddr) -> Result<Self, Error> {
+        Ok(Self::new(TcpListener::bind(addr).await?))
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        self.listener.local_addr().map_err(From::from)
+    }
+
+    fn locator(&self) -> NodeLocator<'static> {
+        let addr = self
+            .local_addr()
+            .expect("WebSocketNetlayer should know its own address");
+        let host = addr.ip().to_string();
+        NodeLocator {
+            designator: Cow::Owned(host.clone()),
+            transport: Cow::Borrowed(TRANSPORT),
+            hints: HashMap::from_iter([
+                (symbol!["host"], Cow::Owned(host)),
+                (symbol!["port"], Cow::Owned(addr.port().to_string())),
+            ]),
+        }
+    }
+}
+
+impl Netlayer for WebSocketNetlayer {
+    type Reader = WebSocketReader;
+    type Writer = WebSocketWriter;
+    type Error = Error;
+
+    async fn connect<'loc>(
+        &self,
+        locator: &NodeLocator<'loc>,
+    ) -> Result<CapTpSession<Self::Reader, Self::Writer>, Self::Error> {
+        if let Some(session) = self.manager.get(locator) {
+            return Ok(session);
+        }
+
+        let url = url(locator)?;
+        tracing::debug!(local = ?self.locator(), %url, "starting connection");
+        let (stream, _) = tokio_tungstenite::connect_async(url).await?;
+        let (reader, writer) = split(stream);
+
+        let session = self
+            .manager
+            .init_session(reader, writer)
+            .and_connect(self.locator())
+            .await?;
+        self.manager.alias(locator, *session.remote_vkey());
+        Ok(session)
+    }
+
+    async fn accept(&self) -> Result<CapTpSession<Self::Reader, Self::Writer>, Self::Error> {
+        tracing::debug!(local = ?self.locator(), "accepting connection");
+        let stream = self
+            .accepted
+            .lock()
+            .await
+            .recv()
+            .await
+            // the task holds a sender until it's aborted on drop
+            .expect("accept task should run until the netlayer is dropped")?;
+        let (reader, writer) = split(stream);
+
+        self.manager
+            .init_session(reader, writer)
+            .and_accept(self.locator())
+            .await
+            .map_err(From::from)
+    }
+
+    fn locators(&self) -> Vec<NodeLocator<'_>> {
+        vec![self.locator()]
+    }
+}
@@ -0,0 +1,98 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+};
+
+use futures::StreamExt;
+use rexa::{captp::msg::OpStartSession, locator::NodeLocator, netlayer::Netlayer};
+use rexa_netlayer_websocket::WebSocketNetlayer;
+use syrup::{de::Cursor, symbol, TokenTree};
+use tokio_tungstenite::tungstenite::Message;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+const LOOPBACK: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+
+#[tokio::test]
+async fn loopback() -> Result<(), BoxError> {
+    let node_a = WebSocketNetlayer::bind(&LOOPBACK).await?;
+    let node_b = WebSocketNetlayer::bind(&LOOPBACK).await?;
+
+    let locator_b = node_b.locators().pop().unwrap();
+    let (session_ab, session_ba) = tokio::join!(node_a.connect(&locator_b), node_b.accept());
+    let (session_ab, session_ba) = (session_ab?, session_ba?);
+
+    assert_eq!(
+        session_ab.remote_vkey(),
+        &session_ba.signing_key().verifying_key()
+    );
+    assert_eq!(
+        session_ba.remote_vkey(),
+        &session_ab.signing_key().verifying_key()
+    );
+
+    session_ab.abort("done").await?;
+    Ok(())
+}
+
+/// A connection which never sends its opening handshake doesn't hold up the ones behind it.
+#[tokio::test]
+async fn stalled_handshake() -> Result<(), BoxError> {
+    let node_a = WebSocketNetlayer::bind(&LOOPBACK).await?;
+    let node_b = WebSocketNetlayer::bind(&LOOPBACK).await?;
+
+    let _stalled = tokio::net::TcpStream::connect(node_b.local_addr()?).await?;
+    let locator_b = node_b.locators().pop().unwrap();
+    let (session_ab, session_ba) = tokio::join!(node_a.connect(&locator_b), node_b.accept());
+    let (session_ab, _) = (session_ab?, session_ba?);
+
+    session_ab.abort("done").await?;
+    Ok(())
+}
+
+/// Each CapTP message is sent as a binary message of its own, and the node advertises itself with
+/// `host` and `port` hints.
+#[tokio::test]
+async fn framing_and_locator() -> Result<(), BoxError> {
+    let node = WebSocketNetlayer::bind(&LOOPBACK).await?;
+    let node_addr = node.local_addr()?;
+    let listener = tokio::net::TcpListener::bind(LOOPBACK).await?;
+    let addr = listener.local_addr()?;
+    let locator = NodeLocator {
+        designator: Cow::Owned(addr.ip().to_string()),
+        transport: Cow::Borrowed("websocket"),
+        hints: HashMap::from_iter([
+            (symbol!["host"], Cow::Owned(addr.ip().to_string())),
+            (symbol!["port"], Cow::Owned(addr.port().to_string())),
+        ]),
+    };
+    let connect = tokio::spawn(async move { node.connect(&locator).await.map(drop) });
+
+    let (stream, _) = listener.accept().await?;
+    let mut peer = tokio_tungstenite::accept_async(stream).await?;
+    let frame = match peer.next().await.ok_or("connection closed")?? {
+        Message::Binary(frame) => frame,
+        msg => return Err(format!("expected a binary message, found {msg:?}").into()),
+    };
+    let (tree, rem) = TokenTree::tokenize_static(Cursor::new(&frame[..]))?;
+    assert!(rem.rem.is_empty(), "frame should hold exactly one message");
+    let hello = tree.decode::<OpStartSession<'static>>()?;
+    hello.verify_location()?;
+
+    let location = &hello.acceptable_location;
+    assert_eq!(location.transport, "websocket");
+    assert_eq!(location.designator, node_addr.ip().to_string());
+    assert_eq!(
+        location.hint("host").map(|host| &**host),
+        Some(&*node_addr.ip().to_string())
+    );
+    assert_eq!(
+        location.hint("port").map(|port| &**port),
+        Some(&*node_addr.port().to_string())
+    );
+
+    drop(peer);
+    assert!(connect.await?.is_err());
+    Ok(())
+}
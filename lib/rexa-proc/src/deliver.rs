@@ -1,11 +1,14 @@
 use proc_macro2::{Ident, TokenStream};
-use quote::ToTokens;
+use quote::{quote_spanned, ToTokens};
 use syn::{
     parse_quote, parse_quote_spanned, spanned::Spanned, Arm, Attribute, Expr, LitBool, LitStr,
     ReturnType, Signature, Type,
 };
 
-use crate::{attr::ParseNestedMetaExt, process_inputs, DeliverInput, Metadata};
+use crate::{
+    attr::ParseNestedMetaExt, decode_inputs, doc_string, method_info, process_inputs, DeliverInput,
+    Metadata,
+};
 
 pub(crate) struct DeliverFn<'cx> {
     context: &'cx Metadata,
@@ -14,6 +17,7 @@ pub(crate) struct DeliverFn<'cx> {
     is_async: bool,
     inputs: Vec<DeliverInput<'cx>>,
     output: ReturnType,
+    doc: String,
 }
 
 impl<'cx> DeliverFn<'cx> {
@@ -22,6 +26,7 @@ impl<'cx> DeliverFn<'cx> {
         attr: DeliverAttr,
         sig: &Signature,
         inputs: Vec<DeliverInput<'cx>>,
+        attrs: &[Attribute],
     ) -> syn::Result<Self> {
         Ok(Self {
             context,
@@ -30,9 +35,21 @@ impl<'cx> DeliverFn<'cx> {
             is_async: sig.asyncness.is_some(),
             inputs,
             output: sig.output.clone(),
+            doc: doc_string(attrs),
         })
     }
 
+    /// Construct the [`MethodInfo`](rexa::captp::object::MethodInfo) describing this function.
+    pub(crate) fn method_info(&self) -> Expr {
+        method_info(
+            self.context,
+            &self.symbol(),
+            parse_quote!(Deliver),
+            &self.inputs,
+            &self.doc,
+        )
+    }
+
     pub(crate) fn symbol(&self) -> LitStr {
         match &self.attr {
             DeliverAttr::Normal {
@@ -88,16 +105,25 @@ impl<'cx> ToTokens for DeliverFn<'cx> {
         // this function does a lot of magic to figure how to handle the returned type
 
         let ident = &self.ident;
-        let args = &self.inputs;
         let from_fn = &self.context.from_fn;
 
+        // arguments which fail to decode break the promise before the function is called
+        let on_error: Expr = parse_quote_spanned! {self.ident.span()=>
+            return __error.break_promise(resolver).await
+        };
+        let (decode, args) = decode_inputs(self.context, &self.inputs, &self.symbol(), &on_error);
+
         let mut call: Expr = parse_quote_spanned! {self.ident.span()=> Self::#ident(#(#args),*)};
         if self.is_async {
             call = parse_quote_spanned! {self.ident.span()=> #call.await};
         }
 
         let DeliverAttr::Normal { resolution, .. } = &self.attr else {
-            return call.to_tokens(tokens);
+            return quote_spanned! {self.ident.span()=> {
+                #decode
+                #call
+            }}
+            .to_tokens(tokens);
         };
 
         match &resolution {
@@ -105,13 +131,12 @@ impl<'cx> ToTokens for DeliverFn<'cx> {
                 call = parse_quote_spanned! {internal.span()=> #call.map_err(#from_fn) }
             }
             DeliverResolution::External(external) => {
-                let (ok_span, err_span) = match get_result_spans(true, &self.output) {
-                    Ok(spans) => spans,
-                    Err(e) => return e.into_compile_error().to_tokens(tokens),
-                };
-
                 match external {
                     ResolveExternal::Normal { ok_map, err_map } => {
+                        let (ok_span, err_span) = match get_result_spans(true, &self.output) {
+                            Ok(spans) => spans,
+                            Err(e) => return e.into_compile_error().to_tokens(tokens),
+                        };
                         let ok_arm: Arm = parse_quote_spanned! {ok_span.span()=> Ok(__ok) => {
                             resolver.fulfill(#ok_map, None, Default::default()).await.map_err(#from_fn)
                         }};
@@ -123,6 +148,24 @@ impl<'cx> ToTokens for DeliverFn<'cx> {
                             }
                         }};
                     }
+                    ResolveExternal::Optional { ok_map, none_map } => {
+                        call = parse_quote_spanned! {self.output.span()=> {
+                            match #call {
+                                ::std::option::Option::Some(__ok) => {
+                                    resolver.fulfill(#ok_map, None, Default::default()).await.map_err(#from_fn)
+                                }
+                                ::std::option::Option::None => {
+                                    resolver.break_promise(#none_map).await.map_err(#from_fn)
+                                }
+                            }
+                        }};
+                    }
+                    ResolveExternal::Value { ok_map } => {
+                        call = parse_quote_spanned! {self.output.span()=> {
+                            let __ok = #call;
+                            resolver.fulfill(#ok_map, None, Default::default()).await.map_err(#from_fn)
+                        }};
+                    }
                     ResolveExternal::AlwaysFulfill { res_map } => {
                         call = parse_quote_spanned! { self.output.span() => {
                             let __res = #call;
@@ -139,21 +182,85 @@ impl<'cx> ToTokens for DeliverFn<'cx> {
             }
         }
 
-        call.to_tokens(tokens);
+        quote_spanned! {self.ident.span()=> {
+            #decode
+            #call
+        }}
+        .to_tokens(tokens);
+    }
+}
+
+/// The shape of an object function's return type, which determines how its promise is resolved by
+/// default.
+enum ReturnKind<'ty> {
+    /// `()`, fulfilled with no arguments.
+    Unit,
+    /// `T`, fulfilled with the value.
+    Value,
+    /// `Option<T>`, fulfilled with `T` or broken if `None`.
+    Option,
+    /// `Result<T, E>`, fulfilled with `T` or broken with `E`.
+    Result { ok_unit: bool, err: Option<&'ty Type> },
+}
+
+impl<'ty> ReturnKind<'ty> {
+    fn of(output: &'ty ReturnType) -> Self {
+        let ty = match output {
+            ReturnType::Default => return Self::Unit,
+            ReturnType::Type(_, ty) => &**ty,
+        };
+        let tpath = match ty {
+            Type::Tuple(tuple) if tuple.elems.is_empty() => return Self::Unit,
+            Type::Path(tpath) => tpath,
+            _ => return Self::Value,
+        };
+        let Some(final_segment) = tpath.path.segments.last() else {
+            return Self::Value;
+        };
+        if final_segment.ident == "Option" {
+            Self::Option
+        } else if final_segment.ident == "Result" {
+            let (ok, err) = match &final_segment.arguments {
+                syn::PathArguments::AngleBracketed(args) => {
+                    let mut args = args.args.iter().map(|arg| match arg {
+                        syn::GenericArgument::Type(ty) => Some(ty),
+                        _ => None,
+                    });
+                    (args.next().flatten(), args.next().flatten())
+                }
+                _ => (None, None),
+            };
+            Self::Result {
+                ok_unit: matches!(ok, Some(Type::Tuple(tuple)) if tuple.elems.is_empty()),
+                err,
+            }
+        } else {
+            Self::Value
+        }
     }
 }
 
+/// Whether a type is, syntactically, [`ObjectError`](rexa::captp::object::ObjectError).
+fn is_object_error(ty: &Type) -> bool {
+    matches!(
+        ty,
+        Type::Path(tpath)
+            if tpath.path.segments.last().is_some_and(|seg| seg.ident == "ObjectError")
+    )
+}
+
 pub(crate) enum ResolveExternal {
+    /// Fulfill with `Ok`, break with `Err`.
     Normal { ok_map: Expr, err_map: Expr },
+    /// Fulfill with `Some`, break with `None`.
+    Optional { ok_map: Expr, none_map: Expr },
+    /// Fulfill with the returned value.
+    Value { ok_map: Expr },
     AlwaysFulfill { res_map: Expr },
     AlwaysBreak { res_map: Expr },
 }
 
 impl ResolveExternal {
-    fn normal(ok_map: Expr, err_map: Expr) -> Self {
-        Self::Normal { ok_map, err_map }
-    }
-
     fn always_fulfill(res_map: Expr) -> Self {
         Self::AlwaysFulfill { res_map }
     }
@@ -171,8 +278,44 @@ pub(crate) enum DeliverResolution {
 }
 
 impl DeliverResolution {
-    fn normal(ok_map: Expr, err_map: Expr) -> Self {
-        Self::External(ResolveExternal::normal(ok_map, err_map))
+    /// Choose how to resolve the promise from the return type of the function.
+    fn from_output(
+        context: &Metadata,
+        output: &ReturnType,
+        ok_map: Option<Expr>,
+        err_map: Option<Expr>,
+    ) -> Self {
+        let Metadata { rexa, syrup, .. } = context;
+        let default_ok = || parse_quote! { #syrup::sequence![__ok] };
+        Self::External(match ReturnKind::of(output) {
+            ReturnKind::Unit => ResolveExternal::Value {
+                ok_map: ok_map.unwrap_or_else(|| parse_quote! { #syrup::sequence![] }),
+            },
+            ReturnKind::Value => ResolveExternal::Value {
+                ok_map: ok_map.unwrap_or_else(default_ok),
+            },
+            ReturnKind::Option => ResolveExternal::Optional {
+                ok_map: ok_map.unwrap_or_else(default_ok),
+                none_map: err_map.unwrap_or_else(|| {
+                    parse_quote! {
+                        #syrup::Encode::to_tokens(&#rexa::captp::object::ErrorRecord::new("no value"))
+                    }
+                }),
+            },
+            ReturnKind::Result { ok_unit, err } => ResolveExternal::Normal {
+                ok_map: ok_map.unwrap_or_else(|| match ok_unit {
+                    true => parse_quote! { #syrup::sequence![] },
+                    false => default_ok(),
+                }),
+                // object errors are sent as their `ErrorRecord`; anything else must be `Encode`
+                err_map: err_map.unwrap_or_else(|| match err {
+                    Some(err) if is_object_error(err) => {
+                        parse_quote! { #syrup::Encode::to_tokens(&__err.to_record()) }
+                    }
+                    _ => parse_quote! { #syrup::Encode::to_tokens(&__err) },
+                }),
+            },
+        })
     }
 
     fn always_fulfill(res_map: Expr) -> Self {
@@ -206,8 +349,9 @@ impl DeliverAttr {
         let mut resolution = None;
         let mut symbol = None;
 
-        let mut ok_map: Expr = parse_quote! { [&__ok] };
-        let mut err_map: Expr = parse_quote! { &__err };
+        let Metadata { syrup, .. } = context;
+        let mut ok_map = None;
+        let mut err_map = None;
 
         let (takes_resolver, inputs) = process_inputs(context, &mut sig.inputs)?;
 
@@ -222,20 +366,20 @@ impl DeliverAttr {
                 if takes_resolver.value {
                     return Err(meta.error(RESOLVER_CONFLICT));
                 }
-                ok_map = meta.value()?.parse()?;
+                ok_map = Some(meta.value()?.parse()?);
                 Ok(())
             } else if meta.path.is_ident("err") {
                 if takes_resolver.value {
                     return Err(meta.error(RESOLVER_CONFLICT));
                 }
-                err_map = meta.value()?.parse()?;
+                err_map = Some(meta.value()?.parse()?);
                 Ok(())
             } else if meta.path.is_ident("always_fulfill") {
                 if takes_resolver.value {
                     return Err(meta.error(RESOLVER_CONFLICT));
                 }
                 resolution = Some(DeliverResolution::always_fulfill(
-                    meta.value_or_else(|| parse_quote! { [&__res] })?,
+                    meta.value_or_else(|| parse_quote! { #syrup::sequence![__res] })?,
                 ));
                 Ok(())
             } else if meta.path.is_ident("always_break") {
@@ -243,7 +387,7 @@ impl DeliverAttr {
                     return Err(meta.error(RESOLVER_CONFLICT));
                 }
                 resolution = Some(DeliverResolution::always_break(
-                    meta.value_or_else(|| parse_quote! { &__res })?,
+                    meta.value_or_else(|| parse_quote! { #syrup::Encode::to_tokens(&__res) })?,
                 ));
                 Ok(())
             } else if meta.path.is_ident("symbol") {
@@ -264,7 +408,7 @@ impl DeliverAttr {
                         if takes_resolver.value {
                             DeliverResolution::Internal(takes_resolver)
                         } else {
-                            DeliverResolution::normal(ok_map, err_map)
+                            DeliverResolution::from_output(context, &sig.output, ok_map, err_map)
                         }
                     }),
                     symbol,
@@ -1,10 +1,11 @@
 use proc_macro2::{Ident, TokenStream};
 use quote::ToTokens;
 use syn::{
-    parse_quote_spanned, spanned::Spanned, Attribute, Expr, LitBool, LitStr, ReturnType, Signature,
+    parse_quote, parse_quote_spanned, spanned::Spanned, Attribute, Expr, LitBool, LitStr,
+    ReturnType, Signature,
 };
 
-use crate::{process_inputs, DeliverInput, Metadata};
+use crate::{decode_inputs, doc_string, method_info, process_inputs, DeliverInput, Metadata};
 
 pub(crate) struct DeliverOnlyFn<'context> {
     context: &'context Metadata,
@@ -12,6 +13,7 @@ pub(crate) struct DeliverOnlyFn<'context> {
     ident: Ident,
     inputs: Vec<DeliverInput<'context>>,
     output: ReturnType,
+    doc: String,
 }
 
 impl<'cx> DeliverOnlyFn<'cx> {
@@ -20,6 +22,7 @@ impl<'cx> DeliverOnlyFn<'cx> {
         attr: DeliverOnlyAttr,
         sig: &Signature,
         inputs: Vec<DeliverInput<'cx>>,
+        attrs: &[Attribute],
     ) -> Result<Self, syn::Error> {
         if let Some(token) = sig.asyncness {
             error!(token => "deliver_only object functions must not be async");
@@ -30,9 +33,21 @@ impl<'cx> DeliverOnlyFn<'cx> {
             ident: sig.ident.clone(),
             inputs,
             output: sig.output.clone(),
+            doc: doc_string(attrs),
         })
     }
 
+    /// Construct the [`MethodInfo`](rexa::captp::object::MethodInfo) describing this function.
+    pub(crate) fn method_info(&self) -> Expr {
+        method_info(
+            self.context,
+            &self.symbol(),
+            parse_quote!(DeliverOnly),
+            &self.inputs,
+            &self.doc,
+        )
+    }
+
     pub(crate) fn symbol(&self) -> LitStr {
         match &self.attr {
             DeliverOnlyAttr::Normal {
@@ -50,11 +65,18 @@ impl<'cx> DeliverOnlyFn<'cx> {
 impl<'cx> ToTokens for DeliverOnlyFn<'cx> {
     fn to_tokens(&self, tokens: &mut TokenStream) {
         let ident = &self.ident;
-        let args = &self.inputs;
         let from_fn = &self.context.from_fn;
 
+        let on_error: Expr = parse_quote_spanned! {self.ident.span()=>
+            return ::std::result::Result::Err(__error)
+        };
+        let (decode, args) = decode_inputs(self.context, &self.inputs, &self.symbol(), &on_error);
+
         let mut call: Expr = parse_quote_spanned! {self.ident.span()=> Self::#ident(#(#args),*)};
-        call = parse_quote_spanned! {self.output.span()=> #call.map_err(#from_fn)};
+        call = parse_quote_spanned! {self.output.span()=> {
+            #decode
+            #call.map_err(#from_fn)
+        }};
 
         call.to_tokens(tokens);
     }
@@ -0,0 +1,170 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::ToTokens;
+use syn::{
+    parse_quote_spanned, spanned::Spanned, Expr, FnArg, ImplItemFn, PatType, Signature, Type,
+};
+
+use crate::{receiver_arg, Metadata};
+
+/// An object lifecycle callback.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HookKind {
+    /// `#[exported]`
+    Exported,
+    /// `#[gc]`
+    Gc,
+    /// `#[session_aborted]`
+    SessionAborted,
+}
+
+impl HookKind {
+    pub(crate) fn from_attr(attr: &syn::Attribute) -> Option<Self> {
+        let path = attr.path();
+        if path.is_ident("exported") {
+            Some(Self::Exported)
+        } else if path.is_ident("gc") {
+            Some(Self::Gc)
+        } else if path.is_ident("session_aborted") {
+            Some(Self::SessionAborted)
+        } else {
+            None
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Exported => "exported",
+            Self::Gc => "gc",
+            Self::SessionAborted => "session_aborted",
+        }
+    }
+
+    /// The arguments passed to the hook by [`Object`](rexa::captp::object::Object), along with their
+    /// types.
+    fn context(self, context: &Metadata) -> [(&'static str, Type); 2] {
+        let rexa = &context.rexa;
+        match self {
+            Self::Exported | Self::Gc => [
+                ("remote_key", parse_quote_spanned! {rexa.span()=> &#rexa::captp::RemoteKey }),
+                ("position", parse_quote_spanned! {rexa.span()=> #rexa::captp::msg::DescExport }),
+            ],
+            Self::SessionAborted => [
+                ("remote_key", parse_quote_spanned! {rexa.span()=> &#rexa::captp::RemoteKey }),
+                ("reason", parse_quote_spanned! {rexa.span()=> &str }),
+            ],
+        }
+    }
+}
+
+pub(crate) struct HookFn<'cx> {
+    context: &'cx Metadata,
+    pub(crate) kind: HookKind,
+    ident: Ident,
+    inputs: Vec<Expr>,
+}
+
+impl<'cx> HookFn<'cx> {
+    pub(crate) fn process(
+        context: &'cx Metadata,
+        kind: HookKind,
+        sig: &mut Signature,
+    ) -> syn::Result<Self> {
+        if let Some(token) = sig.asyncness {
+            error!(token => "#[{}] object functions must not be async", kind.name());
+        }
+        let available = kind.context(context);
+        let mut inputs = Vec::with_capacity(sig.inputs.len());
+        for input in &mut sig.inputs {
+            match input {
+                FnArg::Receiver(rec) => inputs.push(receiver_arg(rec)?),
+                FnArg::Typed(pat) => inputs.push(Self::process_input(kind, &available, pat)?),
+            }
+        }
+        Ok(Self {
+            context,
+            kind,
+            ident: sig.ident.clone(),
+            inputs,
+        })
+    }
+
+    /// Find the context argument for an input, either from `#[arg(name)]` or from the name of
+    /// the input.
+    fn process_input(
+        kind: HookKind,
+        available: &[(&'static str, Type)],
+        input: &mut PatType,
+    ) -> syn::Result<Expr> {
+        let mut name = None;
+        let mut attr_index = None;
+        for (i, attr) in input.attrs.iter().enumerate() {
+            if attr.path().is_ident("arg") {
+                attr_index = Some(i);
+                attr.parse_nested_meta(|meta| match meta.path.get_ident() {
+                    Some(ident) => {
+                        name = Some(ident.clone());
+                        Ok(())
+                    }
+                    None => Err(meta.error("expected argument name")),
+                })?;
+                break;
+            }
+        }
+        if let Some(i) = attr_index {
+            input.attrs.remove(i);
+        }
+
+        let name = match (name, &*input.pat) {
+            (Some(name), _) => name,
+            (None, syn::Pat::Ident(id)) => id.ident.clone(),
+            (None, pat) => error!(pat => "expected identifier or #[arg(...)]"),
+        };
+        match available.iter().find(|(id, _)| name == id) {
+            Some(_) => Ok(parse_quote_spanned! {name.span()=> #name }),
+            None => {
+                let names = available
+                    .iter()
+                    .map(|(id, _)| format!("`{id}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                error!(name => "#[{}] functions may only take {names}", kind.name())
+            }
+        }
+    }
+
+    /// The parameter for the context argument `name`, prefixed with `_` if the hook doesn't take it.
+    fn context_ident(&self, name: &str) -> Ident {
+        let used = self
+            .inputs
+            .iter()
+            .any(|input| matches!(input, Expr::Path(path) if path.path.is_ident(name)));
+        if used {
+            Ident::new(name, self.ident.span())
+        } else {
+            Ident::new(&format!("_{name}"), self.ident.span())
+        }
+    }
+
+    /// Construct the [`Object`](rexa::captp::object::Object) function calling this hook.
+    pub(crate) fn to_object_fn(&self) -> ImplItemFn {
+        let ident = &self.ident;
+        let hook = Ident::new(self.kind.name(), ident.span());
+        let [(a, a_t), (b, b_t)] = self.kind.context(self.context);
+        let a = self.context_ident(a);
+        let b = self.context_ident(b);
+        parse_quote_spanned! {ident.span()=>
+            fn #hook(self: ::std::sync::Arc<Self>, #a: #a_t, #b: #b_t) {
+                #self
+            }
+        }
+    }
+}
+
+impl<'cx> ToTokens for HookFn<'cx> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let ident = &self.ident;
+        let args = &self.inputs;
+        let call: Expr = parse_quote_spanned! {ident.span()=> Self::#ident(#(#args),*) };
+        call.to_tokens(tokens);
+    }
+}
@@ -1,26 +1,264 @@
-use proc_macro2::{Span, TokenStream};
+use proc_macro2::{Ident, Span, TokenStream};
 use quote::{quote_spanned, ToTokens};
-use syn::{parse_quote_spanned, spanned::Spanned, Expr, FnArg, LitBool, PatType, Receiver, Type};
+use syn::{
+    parse_quote, parse_quote_spanned, spanned::Spanned, Expr, FnArg, LitBool, LitStr, PatType,
+    Receiver, Type,
+};
 
 use crate::{attr::ParseNestedMetaExt, Metadata};
 
 pub(crate) enum DeliverInput<'cx> {
-    Receiver(Receiver),
+    Receiver(Expr),
     Mapped {
         is_resolver: bool,
+        /// Whether the mapping may use the delivery's remaining syrup arguments.
+        uses_args: bool,
         map: Expr,
     },
-    Syrup {
-        ty: Type,
-        map: Expr,
-        context: &'cx Metadata,
-    },
+    Syrup(SyrupInput<'cx>),
+}
+
+/// An input decoded from the syrup arguments of a delivery.
+pub(crate) struct SyrupInput<'cx> {
+    /// The type of each decoded argument.
+    ty: Type,
+    /// The type of the function parameter.
+    input_ty: Type,
+    map: SyrupMap,
+    arity: SyrupArity,
+    context: &'cx Metadata,
+}
+
+/// How a syrup argument is converted into the value passed to an object function.
+pub(crate) enum SyrupMap {
+    /// Decode the argument as the input type.
+    Decode,
+    /// Decode the argument, then convert it into the given type.
+    Into(Type),
+    /// Evaluate an expression in which the raw argument is bound to `arg`.
+    Custom(Expr),
+    /// Decode the argument as a dictionary of keyword arguments, then convert it with
+    /// `FromKeywords`.
+    Keywords,
+    /// Decode the argument as an import descriptor, then get the `RemoteObject` it refers to from
+    /// the session.
+    Import,
+}
+
+/// How many syrup arguments are consumed by an input.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SyrupArity {
+    /// Exactly one argument.
+    One,
+    /// One argument, if any remain; `Option<T>`.
+    Optional,
+    /// All remaining arguments; `#[arg(rest)] Vec<T>`.
+    Rest,
+}
+
+/// Whether a type is a path ending in `RemoteObject`.
+pub(crate) fn is_remote_object(ty: &Type) -> bool {
+    let Type::Path(tpath) = ty else {
+        return false;
+    };
+    matches!(
+        tpath.path.segments.last(),
+        Some(seg) if seg.ident == "RemoteObject" && seg.arguments.is_empty()
+    )
+}
+
+/// Get `T` from a type of the form `#wrapper<T>`.
+fn unwrap_type<'ty>(ty: &'ty Type, wrapper: &str) -> Option<&'ty Type> {
+    let Type::Path(tpath) = ty else {
+        return None;
+    };
+    let final_segment = tpath.path.segments.last()?;
+    if final_segment.ident != wrapper {
+        return None;
+    }
+    match &final_segment.arguments {
+        syn::PathArguments::AngleBracketed(args) if args.args.len() == 1 => {
+            match args.args.first() {
+                Some(syn::GenericArgument::Type(inner)) => Some(inner),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+impl<'cx> SyrupInput<'cx> {
+    fn new(context: &'cx Metadata, input_ty: Type, map: SyrupMap) -> Self {
+        let (ty, arity) = match (&map, unwrap_type(&input_ty, "Option")) {
+            (SyrupMap::Decode | SyrupMap::Keywords, Some(inner)) => {
+                (inner.clone(), SyrupArity::Optional)
+            }
+            _ => (input_ty.clone(), SyrupArity::One),
+        };
+        let map = match map {
+            SyrupMap::Decode if is_remote_object(&ty) => SyrupMap::Import,
+            map => map,
+        };
+        Self {
+            ty,
+            input_ty,
+            map,
+            arity,
+            context,
+        }
+    }
+
+    fn rest(context: &'cx Metadata, input_ty: Type) -> syn::Result<Self> {
+        let Some(ty) = unwrap_type(&input_ty, "Vec") else {
+            error!(input_ty => "rest arguments must be of the form `Vec<T>`");
+        };
+        let map = if is_remote_object(ty) {
+            SyrupMap::Import
+        } else {
+            SyrupMap::Decode
+        };
+        Ok(Self {
+            ty: ty.clone(),
+            input_ty,
+            map,
+            arity: SyrupArity::Rest,
+            context,
+        })
+    }
+
+    /// Construct an expression which converts `__arg` into a single value, evaluating to
+    /// `Result<T, ObjectError>`.
+    fn decode_arg(&self, symbol: &LitStr, position: &Expr) -> Expr {
+        let Self { ty, map, context, .. } = self;
+        let Metadata {
+            rexa,
+            syrup,
+            error_t,
+            ..
+        } = context;
+        let span = ty.span();
+        let decoded: Expr = match map {
+            SyrupMap::Custom(map) => {
+                return parse_quote_spanned! {span=> {
+                    let arg = __arg;
+                    ::std::result::Result::Ok(#map)
+                }}
+            }
+            SyrupMap::Keywords => {
+                return parse_quote_spanned! {span=>
+                    match <::std::collections::HashMap<
+                        #syrup::Symbol<'static>,
+                        #syrup::TokenTree<'static>,
+                    > as #syrup::Decode>::decode(::std::clone::Clone::clone(&__arg)) {
+                        ::std::result::Result::Ok(__entries) => {
+                            #rexa::captp::object::Keywords::from(__entries)
+                                .into_keywords::<#ty>()
+                            .map_err(|__error| #error_t::keywords(#symbol, #position, __error))
+                        }
+                        ::std::result::Result::Err(_) => ::std::result::Result::Err(
+                            #error_t::unexpected(#symbol, #position, "dictionary", __arg)
+                        ),
+                    }
+                }
+            }
+            SyrupMap::Import => {
+                return parse_quote_spanned! {span=>
+                    match <#rexa::captp::msg::DescImport as #syrup::Decode>::decode(
+                        ::std::clone::Clone::clone(&__arg)
+                    ) {
+                        ::std::result::Result::Ok(__desc) => ::std::result::Result::Ok(
+                            #rexa::captp::AbstractCapTpSession::import(
+                                ::std::clone::Clone::clone(&session),
+                                __desc,
+                            )
+                        ),
+                        ::std::result::Result::Err(_) => ::std::result::Result::Err(
+                            #error_t::unexpected(#symbol, #position, "import descriptor", __arg)
+                        ),
+                    }
+                }
+            }
+            SyrupMap::Decode => parse_quote_spanned! {span=> __value },
+            SyrupMap::Into(to) => {
+                parse_quote_spanned! {span=> ::std::convert::Into::<#to>::into(__value) }
+            }
+        };
+        parse_quote_spanned! {span=>
+            match <#ty as #syrup::Decode>::decode(::std::clone::Clone::clone(&__arg)) {
+                ::std::result::Result::Ok(__value) => ::std::result::Result::Ok(#decoded),
+                ::std::result::Result::Err(_) => ::std::result::Result::Err(
+                    #error_t::unexpected(#symbol, #position, ::std::stringify!(#ty), __arg)
+                ),
+            }
+        }
+    }
+
+    /// Construct an expression which pops this input's syrup arguments and evaluates to
+    /// `Result<T, ObjectError>`.
+    ///
+    /// `position` is the position of the first argument within the delivery, counting the method
+    /// symbol as position 0.
+    fn decode(&self, symbol: &LitStr, position: usize) -> Expr {
+        let Self {
+            ty,
+            input_ty,
+            context: Metadata { error_t, .. },
+            ..
+        } = self;
+        let span = input_ty.span();
+        match self.arity {
+            SyrupArity::One => {
+                let decode = self.decode_arg(symbol, &parse_quote!(#position));
+                parse_quote_spanned! {span=>
+                    match args.stream.pop() {
+                        ::std::option::Option::Some(__arg) => #decode,
+                        ::std::option::Option::None => ::std::result::Result::Err(
+                            #error_t::missing(#symbol, #position, ::std::stringify!(#ty))
+                        ),
+                    }
+                }
+            }
+            SyrupArity::Optional => {
+                let decode = self.decode_arg(symbol, &parse_quote!(#position));
+                parse_quote_spanned! {span=>
+                    match args.stream.pop() {
+                        ::std::option::Option::Some(__arg) => {
+                            #decode.map(::std::option::Option::Some)
+                        }
+                        ::std::option::Option::None => {
+                            ::std::result::Result::Ok(::std::option::Option::None)
+                        }
+                    }
+                }
+            }
+            SyrupArity::Rest => {
+                let decode = self.decode_arg(symbol, &parse_quote!(#position + __index));
+                parse_quote_spanned! {span=>
+                    'rest: {
+                        let mut __values = ::std::vec::Vec::new();
+                        let mut __index = 0usize;
+                        while let ::std::option::Option::Some(__arg) = args.stream.pop() {
+                            match #decode {
+                                ::std::result::Result::Ok(__value) => __values.push(__value),
+                                ::std::result::Result::Err(__error) => {
+                                    break 'rest ::std::result::Result::Err(__error)
+                                }
+                            }
+                            __index += 1;
+                        }
+                        ::std::result::Result::Ok(__values)
+                    }
+                }
+            }
+        }
+    }
 }
 
 impl<'cx> DeliverInput<'cx> {
     fn session(span: Span) -> Self {
         Self::Mapped {
             is_resolver: false,
+            uses_args: false,
             map: parse_quote_spanned! {span=> session},
         }
     }
@@ -28,6 +266,7 @@ impl<'cx> DeliverInput<'cx> {
     fn resolver(span: Span) -> Self {
         Self::Mapped {
             is_resolver: true,
+            uses_args: false,
             map: parse_quote_spanned! {span=> resolver},
         }
     }
@@ -35,36 +274,51 @@ impl<'cx> DeliverInput<'cx> {
     fn args(span: Span) -> Self {
         Self::Mapped {
             is_resolver: false,
+            uses_args: true,
             map: parse_quote_spanned! {span=> args.into()},
         }
     }
 
-    fn syrup_inner(context: &'cx Metadata, span: Span, ty: &Type) -> Expr {
-        let error_t = &context.error_t;
-        let syrup = &context.syrup;
-        parse_quote_spanned! {span=>
-            <#ty as #syrup::FromSyrupItem>::from_syrup_item(&arg).map_err(|_| #error_t::unexpected(::std::stringify!(#ty), args_pos - 1, arg))?
-        }
+    fn syrup(context: &'cx Metadata, ty: Type) -> Self {
+        Self::Syrup(SyrupInput::new(context, ty, SyrupMap::Decode))
     }
 
-    fn syrup(context: &'cx Metadata, span: Span, ty: Type) -> Self {
-        Self::Syrup {
-            map: Self::syrup_inner(context, span, &ty),
-            ty,
-            context,
+    fn syrup_from(context: &'cx Metadata, from: Type, to: Type) -> Self {
+        Self::Syrup(SyrupInput::new(context, from, SyrupMap::Into(to)))
+    }
+
+    /// Construct an expression which pops the next syrup argument(s) and evaluates to
+    /// `Result<T, ObjectError>`, or `None` if this input doesn't consume syrup arguments.
+    fn decode(&self, symbol: &LitStr, position: usize) -> Option<Expr> {
+        match self {
+            Self::Syrup(input) => Some(input.decode(symbol, position)),
+            Self::Receiver(_) | Self::Mapped { .. } => None,
         }
     }
 
-    fn syrup_from(context: &'cx Metadata, span: Span, from: Type, to: Type) -> Self {
-        let mut map = Self::syrup_inner(context, span, &from);
-        map = parse_quote_spanned! {span=> ::std::convert::Into::<#to>::into(#map)};
-        Self::Syrup {
-            map,
-            ty: from,
-            context,
+    /// The type of the syrup argument consumed by this input, if any.
+    pub(crate) fn wire_type(&self) -> Option<&Type> {
+        match self {
+            Self::Syrup(SyrupInput {
+                ty,
+                map: SyrupMap::Into(_),
+                ..
+            }) => Some(ty),
+            Self::Syrup(SyrupInput { input_ty, .. }) => Some(input_ty),
+            Self::Receiver(_) | Self::Mapped { .. } => None,
         }
     }
 
+    fn uses_args(&self) -> bool {
+        matches!(
+            self,
+            Self::Mapped {
+                uses_args: true,
+                ..
+            }
+        )
+    }
+
     fn is_resolver(&self) -> bool {
         matches!(
             self,
@@ -94,25 +348,35 @@ impl<'cx> DeliverInput<'cx> {
                     } else if meta.path.is_ident("map") {
                         res = Some(Self::Mapped {
                             is_resolver: false,
+                            uses_args: true,
                             map: meta.value()?.parse()?,
                         });
                         Ok(())
                     } else if meta.path.is_ident("syrup_from") {
                         let from: Type = meta.value()?.parse()?;
-                        res = Some(Self::syrup_from(
+                        res = Some(Self::syrup_from(context, from, (*input.ty).clone()));
+                        Ok(())
+                    } else if meta.path.is_ident("syrup") {
+                        res = Some(Self::Syrup(SyrupInput::new(
                             context,
-                            meta.path.span(),
-                            from,
                             (*input.ty).clone(),
-                        ));
+                            SyrupMap::Custom(
+                                meta.value_or_else(|| parse_quote_spanned! {meta.path.span()=> arg })?,
+                            ),
+                        )));
                         Ok(())
-                    } else if meta.path.is_ident("syrup") {
-                        res = Some(Self::Syrup {
-                            ty: (*input.ty).clone(),
-                            map: meta
-                                .value_or_else(|| parse_quote_spanned! {meta.path.span()=> arg })?,
+                    } else if meta.path.is_ident("rest") {
+                        res = Some(Self::Syrup(SyrupInput::rest(
                             context,
-                        });
+                            (*input.ty).clone(),
+                        )?));
+                        Ok(())
+                    } else if meta.path.is_ident("dict") {
+                        res = Some(Self::Syrup(SyrupInput::new(
+                            context,
+                            (*input.ty).clone(),
+                            SyrupMap::Keywords,
+                        )));
                         Ok(())
                     } else {
                         Err(meta.error("unrecognized arg attribute"))
@@ -127,7 +391,7 @@ impl<'cx> DeliverInput<'cx> {
 
         match res {
             Some(arg) => Ok(arg),
-            None => Ok(Self::syrup(context, input.span(), (*input.ty).clone())),
+            None => Ok(Self::syrup(context, (*input.ty).clone())),
         }
     }
 }
@@ -135,25 +399,96 @@ impl<'cx> DeliverInput<'cx> {
 impl<'cx> ToTokens for DeliverInput<'cx> {
     fn to_tokens(&self, tokens: &mut TokenStream) {
         match self {
-            DeliverInput::Receiver(receiver) => {
-                let expr: Expr = parse_quote_spanned! {receiver.span()=> self};
-                expr.to_tokens(tokens);
-            }
+            DeliverInput::Receiver(receiver) => receiver.to_tokens(tokens),
             DeliverInput::Mapped { map, .. } => map.to_tokens(tokens),
-            DeliverInput::Syrup {
-                ty,
-                map,
-                context: Metadata { error_t, .. },
-            } => {
-                quote_spanned! {ty.span()=>
-                    match args.pop_front() {
-                        Some(arg) => { args_pos += 1; #map },
-                        None => return ::std::result::Result::Err(#error_t::missing(args_pos, ::std::stringify!(#ty))), // ::std::todo!(::std::concat!("missing argument: ", #id, ": ", ::std::stringify!(#ty)))
-                    }
-                }
-                .to_tokens(tokens);
+            // syrup inputs are decoded ahead of the call by `decode_inputs`
+            DeliverInput::Syrup(SyrupInput { input_ty, .. }) => {
+                tokens_error!(tokens, input_ty => "syrup inputs must be decoded before the call")
+            }
+        }
+    }
+}
+
+/// Decode the syrup inputs of an object function, in order, before calling it.
+///
+/// Returns the statements binding the decoded values, along with the arguments with which to call
+/// the function. If any argument fails to decode, `on_error` is evaluated with the
+/// [`ObjectError`](rexa::captp::object::ObjectError) bound to `__error`.
+pub(crate) fn decode_inputs(
+    context: &Metadata,
+    inputs: &[DeliverInput<'_>],
+    symbol: &LitStr,
+    on_error: &Expr,
+) -> (TokenStream, Vec<TokenStream>) {
+    let mut bindings = Vec::new();
+    let mut decoders = Vec::new();
+    let mut call_args = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        // position 0 is the method symbol
+        match input.decode(symbol, bindings.len() + 1) {
+            Some(decode) => {
+                let binding = Ident::new(&format!("__arg_{}", bindings.len()), input.span());
+                decoders.push(quote_spanned! {input.span()=>
+                    let #binding = match #decode {
+                        ::std::result::Result::Ok(__value) => __value,
+                        ::std::result::Result::Err(__error) => {
+                            break 'decode ::std::result::Result::Err(__error)
+                        }
+                    };
+                });
+                call_args.push(binding.to_token_stream());
+                bindings.push(binding);
+            }
+            None => call_args.push(input.to_token_stream()),
+        }
+    }
+
+    // arguments left over once every input is decoded were not expected, unless an input takes
+    // them as they are
+    if !inputs.iter().any(DeliverInput::uses_args) {
+        let error_t = &context.error_t;
+        let position = bindings.len() + 1;
+        decoders.push(quote_spanned! {symbol.span()=>
+            if let ::std::option::Option::Some(__arg) = args.stream.pop() {
+                break 'decode ::std::result::Result::Err(
+                    #error_t::unexpected(#symbol, #position, "no further arguments", __arg)
+                );
             }
+        });
+    }
+
+    if decoders.is_empty() {
+        return (TokenStream::new(), call_args);
+    }
+
+    let stmts = quote_spanned! {symbol.span()=>
+        let (#(#bindings,)*) = match 'decode: {
+            #(#decoders)*
+            ::std::result::Result::Ok((#(#bindings,)*))
+        } {
+            ::std::result::Result::Ok(__inputs) => __inputs,
+            ::std::result::Result::Err(__error) => #on_error,
+        };
+    };
+    (stmts, call_args)
+}
+
+/// Construct the argument passed for the receiver of an object function, from the
+/// `self: Arc<Self>` of the generated [`Object`](rexa::captp::object::Object) function.
+///
+/// Object functions may take `&self`, `self: &Arc<Self>`, or `self: Arc<Self>`.
+pub(crate) fn receiver_arg(receiver: &Receiver) -> syn::Result<Expr> {
+    match &*receiver.ty {
+        Type::Reference(reference) if reference.mutability.is_none() => {
+            Ok(parse_quote_spanned! {receiver.span()=> &self })
+        }
+        Type::Reference(_) => {
+            error!(receiver => "object functions cannot take `self` by mutable reference")
         }
+        _ if receiver.colon_token.is_some() => {
+            Ok(parse_quote_spanned! {receiver.span()=> ::std::clone::Clone::clone(&self) })
+        }
+        _ => error!(receiver => "object functions cannot take `self` by value; use `self: Arc<Self>`"),
     }
 }
 
@@ -163,19 +498,58 @@ pub(crate) fn process_inputs<'cx, 'arg>(
 ) -> syn::Result<(LitBool, Vec<DeliverInput<'cx>>)> {
     let mut takes_resolver = LitBool::new(false, Span::call_site());
     let mut res = Vec::<DeliverInput<'cx>>::new();
+    // optional arguments may only be followed by other optional arguments or a rest argument,
+    // and nothing may follow a rest argument
+    let mut prev_arity = SyrupArity::One;
     for input in inputs {
         match input {
             FnArg::Typed(input) => {
                 let input = DeliverInput::process(context, input)?;
+                if let DeliverInput::Syrup(SyrupInput { arity, input_ty, .. }) = &input {
+                    match (prev_arity, arity) {
+                        (SyrupArity::Rest, _) => {
+                            error!(input_ty => "rest arguments must be the final syrup argument")
+                        }
+                        (SyrupArity::Optional, SyrupArity::One) => {
+                            error!(input_ty => "required arguments cannot follow optional arguments")
+                        }
+                        _ => {}
+                    }
+                    prev_arity = *arity;
+                }
                 if input.is_resolver() {
                     takes_resolver = LitBool::new(true, input.span());
                 }
                 res.push(input);
             }
             FnArg::Receiver(receiver) => {
-                res.push(DeliverInput::Receiver(receiver.clone()));
+                res.push(DeliverInput::Receiver(receiver_arg(receiver)?));
             }
         }
     }
     Ok((takes_resolver, res))
 }
+
+/// Construct a [`MethodInfo`](rexa::captp::object::MethodInfo) from the parts of an object
+/// function.
+pub(crate) fn method_info(
+    context: &Metadata,
+    symbol: &LitStr,
+    kind: Ident,
+    inputs: &[DeliverInput<'_>],
+    doc: &str,
+) -> Expr {
+    let rexa = &context.rexa;
+    let args = inputs
+        .iter()
+        .filter_map(DeliverInput::wire_type)
+        .map(|ty| LitStr::new(&ty.to_token_stream().to_string(), ty.span()));
+    parse_quote_spanned! {symbol.span()=>
+        #rexa::captp::object::MethodInfo {
+            symbol: #symbol,
+            kind: #rexa::captp::object::MethodKind::#kind,
+            args: &[#(#args),*],
+            doc: #doc,
+        }
+    }
+}
@@ -1,32 +1,25 @@
 use std::collections::HashMap;
 
 use proc_macro2::{Span, TokenStream};
-use quote::quote_spanned;
+use quote::{quote_spanned, ToTokens};
 use syn::{
     parse::{Parse, Parser},
     parse_macro_input, parse_quote, parse_quote_spanned,
     punctuated::Punctuated,
     spanned::Spanned,
-    Arm, Attribute, Expr, ImplItemFn, ItemImpl, Path, Signature, Stmt, Token, Type,
+    Arm, Attribute, Expr, Ident, ImplItemFn, ItemImpl, ItemTrait, LitByteStr, Path, Signature,
+    Stmt, Token, Type,
 };
 
 // WARNING :: got way too "clever" with this one
 
+#[allow(unused_macros)]
 macro_rules! fmt_line_in {
     () => {
         ::std::concat!("(@ line ", ::std::line!(), " in ", ::std::file!(), ")")
     };
 }
 
-macro_rules! todo_str {
-    () => {
-        ::std::concat!("not yet implemented ", fmt_line_in!())
-    };
-    ($extra:literal) => {
-        ::std::concat!("not yet implemented: ", $extra, " ", fmt_line_in!())
-    };
-}
-
 #[allow(unused_macro_rules)]
 macro_rules! format_error {
     ($span:expr => $($arg:tt)+) => {
@@ -50,22 +43,6 @@ macro_rules! internal_error {
     }
 }
 
-/// Like [`std::todo`], but it expands to return a [`syn::parse::Error`] instead of panic.
-#[allow(unused_macro_rules)]
-macro_rules! todo {
-    () => {
-        return ::std::result::Result::Err(format_error!(todo_str!()))
-    };
-    ($span:expr) => {
-        return ::std::result::Result::Err(format_error!($span => todo_str!()))
-    };
-    ($span:expr => $($arg:tt)+) => {
-        return ::std::result::Result::Err(
-                format_error!($span => todo_str!("{}"), ::std::format!($($arg)+))
-            )
-    };
-}
-
 #[allow(unused_macro_rules)]
 macro_rules! tokens_error {
     ($tokens:expr, $span:expr => $($arg:tt)+) => {
@@ -76,22 +53,6 @@ macro_rules! tokens_error {
     };
 }
 
-#[allow(unused_macro_rules)]
-macro_rules! tokens_todo {
-    ($tokens:expr, $span:expr => $($arg:tt)+) => {
-        tokens_error!($tokens, $span => todo_str!("{}"), ::std::format!($($arg)+))
-    };
-    ($tokens:expr => $($arg:tt)+) => {
-        tokens_error!($tokens => todo_str!("{}"), ::std::format!($($arg)+))
-    };
-    ($tokens:expr, $span:expr) => {
-        tokens_error!($tokens, $span => todo_str!())
-    };
-    ($tokens:expr) => {
-        tokens_error!($tokens => todo_str!())
-    };
-}
-
 mod deliver;
 use deliver::*;
 
@@ -101,38 +62,100 @@ use deliver_only::*;
 mod input;
 use input::*;
 
-mod export;
-use export::*;
+mod hook;
+use hook::*;
 
 mod attr;
 use attr::*;
 
+mod remote;
+use remote::*;
+
+/// Returns the contents of the `#[doc]` attributes in `attrs`, joined by newlines.
+fn doc_string(attrs: &[Attribute]) -> String {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value:
+                    Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(doc),
+                        ..
+                    }),
+                ..
+            }) => Some(doc.value().trim().to_owned()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Construct the dispatch arms for a set of object functions, ordered by symbol.
+fn sorted_arms<'f, F: 'f>(
+    fns: &'f HashMap<String, F>,
+    arm: impl Fn(LitByteStr, &'f F) -> Arm,
+) -> Vec<Arm> {
+    let mut fns = fns.iter().collect::<Vec<_>>();
+    fns.sort_by(|(a, _), (b, _)| a.cmp(b));
+    fns.into_iter()
+        .map(|(symbol, f)| arm(LitByteStr::new(symbol.as_bytes(), Span::call_site()), f))
+        .collect()
+}
+
+enum MetadataArg {
+    Property(AttrProperty<Path>),
+    Flag(Ident),
+}
+
+impl Parse for MetadataArg {
+    fn parse(input: syn::parse::ParseStream<'_>) -> syn::Result<Self> {
+        if input.peek2(Token![=]) {
+            input.parse().map(Self::Property)
+        } else {
+            input.parse().map(Self::Flag)
+        }
+    }
+}
+
 struct Metadata {
     rexa: Path,
     syrup: Path,
     futures: Path,
     tracing: Option<Path>,
+    /// Whether to handle `__describe` deliveries.
+    describe: bool,
 
     object_t: Type,
     session_t: Type,
-    item_t: Type,
     error_t: Type,
     deliver_only_result_t: Type,
     deliver_result_t: Type,
     args_t: Type,
     resolver_t: Type,
+    remote_object_t: Type,
+    call_error_t: Type,
     from_fn: Path,
 }
 
 impl Parse for Metadata {
     fn parse(input: syn::parse::ParseStream<'_>) -> syn::Result<Self> {
-        let args = Punctuated::<AttrProperty<Path>, Token![,]>::parse_terminated(input)?;
+        let args = Punctuated::<MetadataArg, Token![,]>::parse_terminated(input)?;
+        let mut describe = false;
         let (rexa, syrup, futures, tracing) = {
             let mut rexa = None;
             let mut syrup = None;
             let mut futures = None;
             let mut tracing = None;
             for arg in args {
+                let arg = match arg {
+                    MetadataArg::Flag(flag) if flag == "describe" => {
+                        describe = true;
+                        continue;
+                    }
+                    MetadataArg::Flag(flag) => error!(flag => "unrecognized impl_object flag"),
+                    MetadataArg::Property(arg) => arg,
+                };
                 if arg.ident == "rexa" {
                     rexa = Some(arg.right);
                 } else if arg.ident == "syrup" {
@@ -153,7 +176,6 @@ impl Parse for Metadata {
             )
         };
 
-        let item_t: Type = parse_quote!(#syrup::Item);
         // let deliver_error_t: Type = parse_quote!(#rexa::captp::object::DeliverError);
         // let deliver_only_error_t: Type = parse_quote!(#rexa::captp::object::DeliverOnlyError);
         let error_t: Type = parse_quote!(#rexa::captp::object::ObjectError);
@@ -162,9 +184,10 @@ impl Parse for Metadata {
             session_t: parse_quote!(::std::sync::Arc<dyn #rexa::captp::AbstractCapTpSession + ::std::marker::Send + ::std::marker::Sync>),
             deliver_only_result_t: parse_quote!(::std::result::Result<(), #error_t>),
             deliver_result_t: parse_quote!(::std::result::Result<(), #error_t>),
-            args_t: parse_quote!(#syrup::de::Sequence<'args>),
+            args_t: parse_quote!(#syrup::de::Sequence<'static>),
             resolver_t: parse_quote!(#rexa::captp::GenericResolver),
-            item_t,
+            remote_object_t: parse_quote!(#rexa::captp::object::RemoteObject),
+            call_error_t: parse_quote!(#rexa::captp::object::CallError),
             error_t,
             from_fn: parse_quote!(::std::convert::From::from),
 
@@ -172,6 +195,7 @@ impl Parse for Metadata {
             syrup,
             futures,
             tracing,
+            describe,
         })
     }
 }
@@ -179,11 +203,17 @@ impl Parse for Metadata {
 enum ObjectFn<'context> {
     Deliver(DeliverFn<'context>),
     DeliverOnly(DeliverOnlyFn<'context>),
-    Export(ExportFn),
+    Hook(HookFn<'context>),
 }
 
 impl<'cx> ObjectFn<'cx> {
-    fn process(context: &'cx Metadata, f: &mut ImplItemFn) -> Result<Option<Self>, syn::Error> {
+    /// Functions in trait impls are treated as `#[deliver]` unless marked otherwise, so that traits
+    /// generated with [`remote_object`] can be implemented without repeating their annotations.
+    fn process(
+        context: &'cx Metadata,
+        f: &mut ImplItemFn,
+        is_trait_impl: bool,
+    ) -> Result<Option<Self>, syn::Error> {
         let mut res = None;
         let mut attr_index = None;
         for (i, attr) in f.attrs.iter().enumerate() {
@@ -191,24 +221,31 @@ impl<'cx> ObjectFn<'cx> {
                 attr_index = Some(i);
                 let (attr, inputs) = DeliverAttr::process(context, attr, &mut f.sig)?;
                 res = Some(Self::Deliver(DeliverFn::process(
-                    context, attr, &f.sig, inputs,
+                    context, attr, &f.sig, inputs, &f.attrs,
                 )?));
                 break;
             } else if attr.path().is_ident("deliver_only") {
                 attr_index = Some(i);
                 let (attr, inputs) = DeliverOnlyAttr::process(context, attr, &mut f.sig)?;
                 res = Some(Self::DeliverOnly(DeliverOnlyFn::process(
-                    context, attr, &f.sig, inputs,
+                    context, attr, &f.sig, inputs, &f.attrs,
                 )?));
                 break;
-            } else if attr.path().is_ident("exported") {
+            } else if let Some(kind) = HookKind::from_attr(attr) {
                 attr_index = Some(i);
-                res = Some(Self::Export(ExportFn::process(&mut f.sig)?));
+                res = Some(Self::Hook(HookFn::process(context, kind, &mut f.sig)?));
+                break;
             }
         }
 
         if let Some(i) = attr_index {
             f.attrs.remove(i);
+        } else if is_trait_impl {
+            let attr: Attribute = parse_quote_spanned! {f.sig.ident.span()=> #[deliver()] };
+            let (attr, inputs) = DeliverAttr::process(context, &attr, &mut f.sig)?;
+            res = Some(Self::Deliver(DeliverFn::process(
+                context, attr, &f.sig, inputs, &f.attrs,
+            )?));
         }
 
         Ok(res)
@@ -232,12 +269,16 @@ impl<'cx> Parser for ObjectDefParser<'cx> {
         let mut deliver_only_fallback = None;
         let mut deliver_only_fns = HashMap::new();
         let mut deliver_only_verbatim = None;
-        let mut export_fn = None;
+        let mut hooks = Vec::<HookFn<'cx>>::new();
+        let is_trait_impl = base.trait_.is_some();
         for item in &mut base.items {
             match item {
-                syn::ImplItem::Fn(f) => match ObjectFn::process(self.context, f)? {
-                    Some(ObjectFn::Export(export)) => {
-                        export_fn = Some(export);
+                syn::ImplItem::Fn(f) => match ObjectFn::process(self.context, f, is_trait_impl)? {
+                    Some(ObjectFn::Hook(hook)) => {
+                        if hooks.iter().any(|h| h.kind == hook.kind) {
+                            error!(&f.sig => "duplicate object lifecycle hook");
+                        }
+                        hooks.push(hook);
                     }
                     Some(ObjectFn::Deliver(del)) => {
                         let DeliverAttr::Normal { fallback, .. } = &del.attr else {
@@ -263,14 +304,12 @@ impl<'cx> Parser for ObjectDefParser<'cx> {
                     }
                     None => { /* skip */ }
                 },
-                syn::ImplItem::Verbatim(tt) => {
-                    todo!(&tt => "handle verbatim impl item: {tt:?}")
-                }
-                syn::ImplItem::Macro(m) => {
-                    error!(m => "macro items not supported in #[impl_object] blocks")
-                }
-                syn::ImplItem::Const(_) | syn::ImplItem::Type(_) => { /* ignore */ }
-                item => todo!(&item => "unrecognized impl item: {item:?}"),
+                // passed through unchanged; anything they expand to can't be dispatched to
+                syn::ImplItem::Verbatim(_)
+                | syn::ImplItem::Macro(_)
+                | syn::ImplItem::Const(_)
+                | syn::ImplItem::Type(_) => { /* ignore */ }
+                _ => { /* ignore */ }
             }
         }
         Ok(Self::Output {
@@ -283,7 +322,7 @@ impl<'cx> Parser for ObjectDefParser<'cx> {
             deliver_only_fns,
             deliver_only_fallback,
             deliver_only_verbatim,
-            export_fn,
+            hooks,
         })
     }
 }
@@ -302,7 +341,7 @@ struct ObjectDef<'context> {
     deliver_only_fallback: Option<DeliverOnlyFn<'context>>,
     deliver_only_verbatim: Option<DeliverOnlyFn<'context>>,
 
-    export_fn: Option<ExportFn>,
+    hooks: Vec<HookFn<'context>>,
 }
 
 #[proc_macro_attribute]
@@ -316,15 +355,16 @@ pub fn impl_object(
         syrup,
         futures,
         tracing,
+        describe,
         object_t,
         session_t,
-        item_t,
         error_t,
         args_t,
         resolver_t,
         deliver_only_result_t,
         deliver_result_t,
         from_fn,
+        ..
     } = &metadata;
 
     let ObjectDef {
@@ -337,148 +377,179 @@ pub fn impl_object(
         deliver_only_fallback,
         deliver_verbatim,
         deliver_only_verbatim,
-        export_fn,
+        hooks,
     } = {
         let parser = ObjectDefParser { context: &metadata };
         parse_macro_input!(obj_input with parser)
     };
 
-    let (impl_generics, _, where_clause) = base.generics.split_for_impl();
+    // deliveries are handled through `Arc<Self>`, and `deliver` returns a `'static` future
+    let mut generics = base.generics.clone();
+    generics.make_where_clause().predicates.push(parse_quote! {
+        #self_ty: ::std::marker::Send + ::std::marker::Sync + 'static
+    });
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
 
     let deliver_only_sig: Signature = parse_quote! {
-        fn deliver_only(&self, session: #session_t, mut args: #args_t) -> #deliver_only_result_t
+        fn deliver_only(
+            self: ::std::sync::Arc<Self>,
+            session: #session_t,
+            mut args: #args_t
+        ) -> #deliver_only_result_t
     };
 
     let deliver_sig: Signature = parse_quote! {
-        fn deliver<'result>(&'result self,
+        fn deliver(
+            self: ::std::sync::Arc<Self>,
             session: #session_t,
             mut args: #args_t,
             resolver: #resolver_t
-        ) -> #futures::future::BoxFuture<'result, #deliver_result_t>
+        ) -> #futures::future::BoxFuture<'static, #deliver_result_t>
     };
 
-    let get_id: Vec<Stmt> = parse_quote! {
-        let mut args = ::std::collections::VecDeque::from(args);
-        let __id = match args.pop_front() {
-            Some(#item_t::Symbol(__id)) => __id,
-            Some(__item) => return Err(#error_t::unexpected("Symbol", 0, __item)),
-            None => return Err(#error_t::missing(0, "Symbol"))
-        };
-        let mut args_pos = 1;
+    // errors while dispatching break the promise in `deliver`, but can only be returned from
+    // `deliver_only`
+    let deliver_on_error: Expr = parse_quote! { return __error.break_promise(resolver).await };
+    let deliver_only_on_error: Expr = parse_quote! { return ::std::result::Result::Err(__error) };
+
+    let get_id = |on_error: &Expr| -> Vec<Stmt> {
+        parse_quote! {
+            let __id = match args.stream.pop() {
+                ::std::option::Option::Some(#syrup::TokenTree::Literal(#syrup::de::Literal {
+                    repr: #syrup::de::LiteralValue::Symbol(__id),
+                    ..
+                })) => __id,
+                ::std::option::Option::Some(__item) => {
+                    let __error = #error_t::InvalidMethod(__item);
+                    #on_error
+                }
+                ::std::option::Option::None => {
+                    let __error = #error_t::MissingMethod;
+                    #on_error
+                }
+            };
+        }
     };
 
-    //let deliver_only: ImplItemFn = if let Some(verbatim) = deliver_only_verbatim {
-    //    parse_quote! {
-    //        #deliver_only_sig {
-    //            #verbatim
-    //        }
-    //    }
-    //} else if deliver_only_fns.is_empty() {
-    //    if let Some(tracing) = tracing {
-    //        parse_quote! {
-    //            #deliver_only_sig {
-    //                #tracing::warn!(session_key_hash = #rexa::hash(session.remote_vkey()), ?args, "unexpected deliver_only");
-    //                Ok(())
-    //            }
-    //        }
-    //    } else {
-    //        parse_quote! {
-    //            #deliver_only_sig {
-    //                Ok(())
-    //            }
-    //        }
-    //    }
-    //} else {
-    //    let mut deliver_only_arms = deliver_only_fns
-    //        .into_iter()
-    //        .map(|(symbol, del)| {
-    //            parse_quote_spanned! {del.span()=> #symbol => #del }
-    //        })
-    //        .collect::<Vec<Arm>>();
-    //    deliver_only_arms.push(match deliver_only_fallback {
-    //        Some(fallback) => parse_quote_spanned! {fallback.span()=> id => #fallback},
-    //        _ => parse_quote! { id => todo!("unrecognized deliver_only function: {id}") },
-    //    });
-    //    parse_quote! {
-    //        #deliver_only_sig {
-    //            #(#get_id)*
-    //            match __id.as_str() {
-    //                #(#deliver_only_arms),*
-    //            }
-    //        }
-    //    }
-    //};
-
-    //let deliver: ImplItemFn = if let Some(verbatim) = deliver_verbatim {
-    //    parse_quote! {
-    //        #deliver_sig {
-    //            #verbatim
-    //        }
-    //    }
-    //} else if deliver_fns.is_empty() {
-    //    let result: Expr = parse_quote! {
-    //        #futures::FutureExt::boxed(async move {
-    //            resolver.break_promise(&::std::format!("unrecognized delivery: {args:?}")).await.map_err(#from_fn)
-    //        })
-    //    };
-    //    if let Some(tracing) = tracing {
-    //        parse_quote! {
-    //            #deliver_sig {
-    //                #tracing::warn!(session_key_hash = #rexa::hash(session.remote_vkey()), ?args, "unexpected deliver");
-    //                #result
-    //            }
-    //        }
-    //    } else {
-    //        parse_quote! {
-    //            #deliver_sig {
-    //                #result
-    //            }
-    //        }
-    //    }
-    //} else {
-    //    let mut deliver_arms = deliver_fns
-    //        .into_iter()
-    //        .map(|(symbol, del)| {
-    //            parse_quote_spanned! {del.span()=> #symbol => #del }
-    //        })
-    //        .collect::<Vec<Arm>>();
-    //
-    //    deliver_arms.push(match deliver_fallback {
-    //        Some(fallback) => parse_quote_spanned! {fallback.span()=> id => #fallback},
-    //        _ => parse_quote! { id => resolver.break_promise(&::std::format!("unrecognized function: {id}")).await.map_err(#from_fn) },
-    //    });
-    //    parse_quote! {
-    //        #deliver_sig {
-    //            use #futures::FutureExt;
-    //            async move {
-    //                #(#get_id)*
-    //                // `let ...` so that we get more helpful errors
-    //                let __res: #deliver_result_t = match __id.as_str() {
-    //                    #(#deliver_arms),*
-    //                };
-    //                __res
-    //            }.boxed()
-    //        }
-    //    }
-    //};
-
-    let deliver_only: ImplItemFn = parse_quote! {
-        #deliver_only_sig {
-            ::std::todo!()
-        }
+    let unknown_method = |on_error: &Expr| -> Expr {
+        let warn: Option<Stmt> = tracing.as_ref().map(|tracing| {
+            parse_quote! {
+                #tracing::warn!(
+                    session_key_hash = #rexa::hash(&session.remote_vkey()),
+                    symbol = %::std::string::String::from_utf8_lossy(id),
+                    "unrecognized method"
+                );
+            }
+        });
+        parse_quote! {{
+            #warn
+            let __error = #error_t::unknown_method(
+                ::std::string::String::from_utf8_lossy(id).into_owned()
+            );
+            #on_error
+        }}
     };
 
-    let deliver: ImplItemFn = parse_quote! {
-        #deliver_sig {
-            ::std::todo!()
+    let deliver_only: ImplItemFn = if let Some(verbatim) = deliver_only_verbatim {
+        parse_quote_spanned! {verbatim.span()=>
+            #deliver_only_sig {
+                #verbatim
+            }
+        }
+    } else {
+        let get_id = get_id(&deliver_only_on_error);
+        let mut deliver_only_arms = sorted_arms(&deliver_only_fns, |symbol, del| {
+            parse_quote_spanned! {del.span()=> #symbol => #del }
+        });
+        deliver_only_arms.push(match deliver_only_fallback {
+            Some(fallback) => parse_quote_spanned! {fallback.span()=> _ => #fallback },
+            None => {
+                let unknown = unknown_method(&deliver_only_on_error);
+                parse_quote! { id => #unknown }
+            }
+        });
+        parse_quote! {
+            #deliver_only_sig {
+                #(#get_id)*
+                match &*__id {
+                    #(#deliver_only_arms),*
+                }
+            }
         }
     };
 
-    let exported: Option<ImplItemFn> = export_fn.map(|exported| parse_quote! {
-            fn exported(&self, remote_key: &#rexa::captp::RemoteKey, position: #rexa::captp::msg::DescExport) {
-                #exported
+    let deliver: ImplItemFn = if let Some(verbatim) = deliver_verbatim {
+        parse_quote_spanned! {verbatim.span()=>
+            #deliver_sig {
+                #futures::FutureExt::boxed(async move {
+                    #verbatim
+                })
             }
+        }
+    } else {
+        let get_id = get_id(&deliver_on_error);
+        let mut deliver_arms = sorted_arms(&deliver_fns, |symbol, del| {
+            parse_quote_spanned! {del.span()=> #symbol => #del }
         });
+        if *describe {
+            deliver_arms.insert(
+                0,
+                parse_quote! {
+                    b"__describe" => {
+                        let __methods = #object_t::methods(&*self)
+                            .iter()
+                            .map(#rexa::captp::object::MethodDescription::from)
+                            .collect::<::std::vec::Vec<_>>();
+                        resolver
+                            .fulfill(#syrup::sequence![__methods], None, Default::default())
+                            .await
+                            .map_err(#from_fn)
+                    }
+                },
+            );
+        }
+        deliver_arms.push(match deliver_fallback {
+            Some(fallback) => parse_quote_spanned! {fallback.span()=> _ => #fallback },
+            None => {
+                let unknown = unknown_method(&deliver_on_error);
+                parse_quote! { id => #unknown }
+            }
+        });
+        parse_quote! {
+            #deliver_sig {
+                #futures::FutureExt::boxed(async move {
+                    #(#get_id)*
+                    // `let ...` so that we get more helpful errors
+                    let __res: #deliver_result_t = match &*__id {
+                        #(#deliver_arms),*
+                    };
+                    __res
+                })
+            }
+        }
+    };
+
+    let method_infos = {
+        let mut infos = deliver_fns
+            .iter()
+            .map(|(symbol, del)| (symbol, del.method_info()))
+            .chain(
+                deliver_only_fns
+                    .iter()
+                    .map(|(symbol, del)| (symbol, del.method_info())),
+            )
+            .collect::<Vec<_>>();
+        infos.sort_by(|(a, _), (b, _)| a.cmp(b));
+        infos.into_iter().map(|(_, info)| info)
+    };
+    let methods: ImplItemFn = parse_quote! {
+        fn methods(&self) -> &'static [#rexa::captp::object::MethodInfo] {
+            const METHODS: &[#rexa::captp::object::MethodInfo] = &[#(#method_infos),*];
+            METHODS
+        }
+    };
+
+    let hooks = hooks.iter().map(HookFn::to_object_fn);
 
     // let instrument_only: Option<Attribute> = tracing.as_ref().map(|tracing| parse_quote_spanned! {tracing.span()=> #[#tracing::instrument(fields(session = #rexa::hash(&session.remote_vkey()), args = %#syrup::ser::to_pretty(&args).unwrap()))]});
 
@@ -491,8 +562,37 @@ pub fn impl_object(
 
             #deliver
 
-            #exported
+            #(#hooks)*
+
+            #methods
         }
     }
     .into()
 }
+
+/// Generate a `Remote{Trait}` proxy for a trait, wrapping a
+/// [`RemoteObject`](rexa::captp::object::RemoteObject).
+///
+/// Functions marked with `#[deliver]` are implemented for the proxy by encoding their arguments and
+/// decoding the answer to the `Ok` type of their `Result`, whose error type must implement
+/// `From<CallError>`. Local objects can implement the same trait with `#[impl_object]`, so code can
+/// be generic over local and remote targets.
+///
+/// Arguments of type `RemoteObject`, `Promise` or `Arc<dyn Object>` are encoded relative to the
+/// proxy's session with `RemoteObject::encode_cap`, and `RemoteObject` answers are decoded from
+/// import descriptors.
+///
+/// Functions marked with `#[deliver_only]` are removed from the trait and generated as inherent
+/// methods on the proxy, because local `deliver_only` handlers are synchronous.
+#[proc_macro_attribute]
+pub fn remote_object(
+    attr_input: proc_macro::TokenStream,
+    trait_input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let metadata = parse_macro_input!(attr_input as Metadata);
+    let base = parse_macro_input!(trait_input as ItemTrait);
+    match RemoteDef::process(&metadata, base) {
+        Ok(def) => def.into_token_stream().into(),
+        Err(e) => e.into_compile_error().into(),
+    }
+}
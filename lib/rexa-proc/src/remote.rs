@@ -0,0 +1,372 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::{quote_spanned, ToTokens};
+use syn::{
+    parse_quote, parse_quote_spanned, spanned::Spanned, Attribute, Block, FnArg, ImplItemFn,
+    ItemTrait, LitStr, ReturnType, Signature, TraitItem, TraitItemFn, Type, Visibility,
+};
+
+use crate::{input::is_remote_object, Metadata};
+
+pub(crate) enum RemoteKind {
+    Deliver,
+    DeliverOnly,
+}
+
+pub(crate) struct RemoteFn<'cx> {
+    context: &'cx Metadata,
+    kind: RemoteKind,
+    symbol: LitStr,
+    sig: Signature,
+    args: Vec<Ident>,
+    /// Arguments which are capabilities, and so must be encoded relative to the target's session.
+    caps: Vec<Ident>,
+    ok_t: Type,
+}
+
+/// Whether a type is a capability: a `RemoteObject`, a `Promise` or an `Arc<dyn Object>`.
+fn is_capability(ty: &Type) -> bool {
+    let Type::Path(tpath) = ty else {
+        return false;
+    };
+    let Some(final_segment) = tpath.path.segments.last() else {
+        return false;
+    };
+    match &final_segment.arguments {
+        syn::PathArguments::None => {
+            final_segment.ident == "RemoteObject" || final_segment.ident == "Promise"
+        }
+        syn::PathArguments::AngleBracketed(args) if final_segment.ident == "Arc" => matches!(
+            args.args.first(),
+            Some(syn::GenericArgument::Type(Type::TraitObject(_)))
+        ),
+        _ => false,
+    }
+}
+
+impl<'cx> RemoteFn<'cx> {
+    /// Process a trait function, returning `None` if it isn't marked with `#[deliver]` or
+    /// `#[deliver_only]`.
+    pub(crate) fn process(context: &'cx Metadata, f: &mut TraitItemFn) -> syn::Result<Option<Self>> {
+        let mut res = None;
+        let mut attr_index = None;
+        for (i, attr) in f.attrs.iter().enumerate() {
+            let kind = if attr.path().is_ident("deliver") {
+                RemoteKind::Deliver
+            } else if attr.path().is_ident("deliver_only") {
+                RemoteKind::DeliverOnly
+            } else {
+                continue;
+            };
+            attr_index = Some(i);
+            res = Some(Self::process_sig(context, kind, attr, &f.sig)?);
+            break;
+        }
+
+        if let Some(i) = attr_index {
+            f.attrs.remove(i);
+        }
+
+        Ok(res)
+    }
+
+    fn process_sig(
+        context: &'cx Metadata,
+        kind: RemoteKind,
+        attr: &Attribute,
+        sig: &Signature,
+    ) -> syn::Result<Self> {
+        let mut symbol = None;
+        if !matches!(attr.meta, syn::Meta::Path(_)) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("symbol") {
+                    symbol = Some(meta.value()?.parse::<LitStr>()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("unrecognized remote object property"))
+                }
+            })?;
+        }
+
+        if sig.asyncness.is_none() {
+            error!(sig => "remote object functions must be async");
+        }
+
+        let mut inputs = sig.inputs.iter();
+        match inputs.next() {
+            Some(FnArg::Receiver(rec)) if rec.reference.is_some() && rec.mutability.is_none() => {}
+            Some(input) => error!(input => "remote object functions must take `&self`"),
+            None => error!(sig => "remote object functions must take `&self`"),
+        }
+
+        let mut args = Vec::new();
+        let mut caps = Vec::new();
+        for input in inputs {
+            match input {
+                FnArg::Typed(pat) => match &*pat.pat {
+                    syn::Pat::Ident(id) => {
+                        if is_capability(&pat.ty) {
+                            caps.push(id.ident.clone());
+                        }
+                        args.push(id.ident.clone());
+                    }
+                    pat => error!(pat => "expected identifier"),
+                },
+                FnArg::Receiver(rec) => error!(rec => "unexpected receiver"),
+            }
+        }
+
+        Ok(Self {
+            context,
+            kind,
+            symbol: symbol.unwrap_or_else(|| LitStr::new(&sig.ident.to_string(), sig.ident.span())),
+            ok_t: result_ok_type(&sig.output)?.clone(),
+            sig: sig.clone(),
+            args,
+            caps,
+        })
+    }
+
+    pub(crate) fn to_impl_fn(&self, vis: &Visibility) -> ImplItemFn {
+        let sig = &self.sig;
+        let block = self.block();
+        parse_quote_spanned! {sig.span()=>
+            #vis #sig #block
+        }
+    }
+
+    pub(crate) fn to_trait_fn(&self) -> ImplItemFn {
+        let sig = &self.sig;
+        let block = self.block();
+        parse_quote_spanned! {sig.span()=>
+            #sig #block
+        }
+    }
+
+    fn block(&self) -> Block {
+        let Metadata {
+            rexa,
+            syrup,
+            call_error_t,
+            ..
+        } = self.context;
+        let symbol = &self.symbol;
+        let args = &self.args;
+        let caps = &self.caps;
+        let ok_t = &self.ok_t;
+        let mut call_args = quote_spanned! {symbol.span()=>
+            #syrup::call_sequence![#symbol #(, #args)*]
+        };
+        if !caps.is_empty() {
+            call_args = quote_spanned! {symbol.span()=> {
+                #(let #caps = self.base.encode_cap(#caps).map_err(#call_error_t::from)?;)*
+                #call_args
+            }};
+        }
+        match self.kind {
+            RemoteKind::DeliverOnly => parse_quote_spanned! {self.sig.span()=> {
+                self.base
+                    .deliver_only(#call_args)
+                    .await
+                    .map_err(#call_error_t::from)?;
+                ::std::result::Result::Ok(())
+            }},
+            RemoteKind::Deliver if is_unit(ok_t) => parse_quote_spanned! {self.sig.span()=> {
+                self.base
+                    .deliver_and(#call_args)
+                    .await
+                    .map_err(#call_error_t::from)?;
+                ::std::result::Result::Ok(())
+            }},
+            RemoteKind::Deliver if is_remote_object(ok_t) => {
+                parse_quote_spanned! {self.sig.span()=> {
+                    let mut __answer = self
+                        .base
+                        .deliver_and(#call_args)
+                        .await
+                        .map_err(#call_error_t::from)?;
+                    let __desc = __answer
+                        .stream
+                        .require(::std::borrow::Cow::Borrowed("import descriptor"))
+                        .and_then(<#rexa::captp::msg::DescImport as #syrup::Decode<'static>>::decode)
+                        .map_err(#call_error_t::from)?;
+                    ::std::result::Result::Ok(#rexa::captp::CapTpDeliver::import(
+                        ::std::clone::Clone::clone(self.base.session()),
+                        __desc,
+                    ))
+                }}
+            }
+            RemoteKind::Deliver => parse_quote_spanned! {self.sig.span()=> {
+                let mut __answer = self
+                    .base
+                    .deliver_and(#call_args)
+                    .await
+                    .map_err(#call_error_t::from)?;
+                ::std::result::Result::Ok(
+                    __answer
+                        .stream
+                        .require(::std::borrow::Cow::Borrowed(::std::stringify!(#ok_t)))
+                        .and_then(<#ok_t as #syrup::Decode<'static>>::decode)
+                        .map_err(#call_error_t::from)?,
+                )
+            }},
+        }
+    }
+}
+
+fn is_unit(ty: &Type) -> bool {
+    matches!(ty, Type::Tuple(tuple) if tuple.elems.is_empty())
+}
+
+/// Get `T` from a return type of the form `Result<T, E>`.
+fn result_ok_type(output: &ReturnType) -> syn::Result<&Type> {
+    const EXPECTED_RESULT: &str = "remote object functions must return a `Result`";
+    let ReturnType::Type(_, ty) = output else {
+        error!(output => "{EXPECTED_RESULT}");
+    };
+    let Type::Path(tpath) = &**ty else {
+        error!(ty => "{EXPECTED_RESULT}");
+    };
+    let Some(final_segment) = tpath.path.segments.last() else {
+        error!(tpath => "{EXPECTED_RESULT}");
+    };
+    if final_segment.ident != "Result" {
+        error!(final_segment => "{EXPECTED_RESULT}");
+    }
+    match &final_segment.arguments {
+        syn::PathArguments::AngleBracketed(args) => match args.args.first() {
+            Some(syn::GenericArgument::Type(ok)) => Ok(ok),
+            _ => error!(args => "expected `Result<T, E>`"),
+        },
+        _ => error!(final_segment => "expected `Result<T, E>`"),
+    }
+}
+
+pub(crate) struct RemoteDef<'context> {
+    context: &'context Metadata,
+    base: ItemTrait,
+    ident: Ident,
+    deliver_fns: Vec<RemoteFn<'context>>,
+    deliver_only_fns: Vec<RemoteFn<'context>>,
+}
+
+impl<'cx> RemoteDef<'cx> {
+    pub(crate) fn process(context: &'cx Metadata, mut base: ItemTrait) -> syn::Result<Self> {
+        let mut deliver_fns = Vec::new();
+        let mut deliver_only_fns = Vec::new();
+        let mut items = Vec::with_capacity(base.items.len());
+        for mut item in std::mem::take(&mut base.items) {
+            if let TraitItem::Fn(f) = &mut item {
+                match RemoteFn::process(context, f)? {
+                    Some(remote @ RemoteFn {
+                        kind: RemoteKind::Deliver,
+                        ..
+                    }) => {
+                        make_send(f);
+                        deliver_fns.push(remote);
+                    }
+                    // local deliver_only handlers are synchronous, so these can't be shared
+                    // between local objects and proxies
+                    Some(remote @ RemoteFn {
+                        kind: RemoteKind::DeliverOnly,
+                        ..
+                    }) => {
+                        deliver_only_fns.push(remote);
+                        continue;
+                    }
+                    None => {}
+                }
+            }
+            items.push(item);
+        }
+        base.items = items;
+        Ok(Self {
+            context,
+            ident: Ident::new(&format!("Remote{}", base.ident), base.ident.span()),
+            base,
+            deliver_fns,
+            deliver_only_fns,
+        })
+    }
+}
+
+/// Desugar `async fn f(..) -> T` into `fn f(..) -> impl Future<Output = T> + Send`, so that code
+/// generic over the trait can spawn the returned futures.
+fn make_send(f: &mut TraitItemFn) {
+    let Some(asyncness) = f.sig.asyncness.take() else {
+        return;
+    };
+    let span = asyncness.span();
+    let output: Type = match &f.sig.output {
+        ReturnType::Default => parse_quote_spanned! {span=> () },
+        ReturnType::Type(_, ty) => (**ty).clone(),
+    };
+    f.sig.output = parse_quote_spanned! {span=>
+        -> impl ::std::future::Future<Output = #output> + ::std::marker::Send
+    };
+    if let Some(block) = f.default.take() {
+        f.default = Some(parse_quote_spanned! {span=> { async move #block } });
+    }
+}
+
+impl<'cx> ToTokens for RemoteDef<'cx> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let Metadata {
+            rexa,
+            remote_object_t,
+            ..
+        } = self.context;
+        let base = &self.base;
+        let vis = &base.vis;
+        let ident = &self.ident;
+        let trait_ident = &base.ident;
+        let doc = format!(" Remote proxy for [`{trait_ident}`].");
+        let deliver_fns = self.deliver_fns.iter().map(RemoteFn::to_trait_fn);
+        let deliver_only_fns = self.deliver_only_fns.iter().map(|f| f.to_impl_fn(vis));
+        let (impl_generics, ty_generics, where_clause) = base.generics.split_for_impl();
+
+        let proxy_t: Type = parse_quote!(#ident);
+        quote_spanned! {trait_ident.span()=>
+            #base
+
+            #[doc = #doc]
+            #[derive(Debug, Clone)]
+            #vis struct #ident {
+                base: #remote_object_t,
+            }
+
+            impl #proxy_t {
+                #vis fn new(base: #remote_object_t) -> Self {
+                    Self { base }
+                }
+
+                #vis fn remote_object(&self) -> &#remote_object_t {
+                    &self.base
+                }
+
+                #(#deliver_only_fns)*
+            }
+
+            impl #impl_generics #trait_ident #ty_generics for #proxy_t #where_clause {
+                #(#deliver_fns)*
+            }
+
+            impl ::std::convert::From<#remote_object_t> for #proxy_t {
+                fn from(base: #remote_object_t) -> Self {
+                    Self::new(base)
+                }
+            }
+
+            impl #rexa::captp::object::Fetch for #proxy_t {
+                type Swiss<'swiss> = &'swiss [u8];
+
+                async fn fetch<'swiss>(
+                    bootstrap: &#rexa::captp::object::RemoteBootstrap,
+                    swiss: Self::Swiss<'swiss>,
+                ) -> ::std::result::Result<Self, #rexa::captp::object::FetchError> {
+                    bootstrap.fetch(swiss).await.map(Self::new)
+                }
+            }
+        }
+        .to_tokens(tokens);
+    }
+}
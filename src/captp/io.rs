@@ -202,3 +202,61 @@ impl<Reader: futures::AsyncBufRead + ?Sized> CapTpRead for Reader {
         futures::AsyncBufRead::consume(self, amt)
     }
 }
+
+/// Reads whole CapTP messages.
+///
+/// Implemented for every [`CapTpReadExt`], and by transports which receive messages whole rather
+/// than as a byte stream.
+pub trait CapTpMessageRead {
+    /// Read the next message.
+    fn read_message(
+        &mut self,
+    ) -> impl Future<Output = Result<TokenTree<'static>, ReadSyrupError>> + Send;
+}
+
+impl<Reader: CapTpReadExt + Send> CapTpMessageRead for Reader {
+    #[inline]
+    fn read_message(
+        &mut self,
+    ) -> impl Future<Output = Result<TokenTree<'static>, ReadSyrupError>> + Send {
+        self.consume_syrup()
+    }
+}
+
+/// Writes whole CapTP messages.
+///
+/// Implemented for every [`AsyncWrite`](crate::async_compat::AsyncWrite), which encodes each
+/// message, and by transports which send messages whole rather than as a byte stream.
+pub trait CapTpMessageWrite {
+    /// A message prepared to be written, which no longer borrows from the tree it was prepared
+    /// from.
+    type Message: Send + 'static;
+
+    /// Prepare a message to be written. Messages are prepared as they're queued, and may be
+    /// written some time later.
+    fn prepare_message(msg: &TokenTree<'_>) -> Self::Message;
+    /// Write a prepared message. It may not be sent until
+    /// [`flush_messages`](Self::flush_messages) is called.
+    fn write_message(
+        &mut self,
+        msg: Self::Message,
+    ) -> impl Future<Output = std::io::Result<()>> + Send;
+    /// Send every written message.
+    fn flush_messages(&mut self) -> impl Future<Output = std::io::Result<()>> + Send;
+}
+
+impl<Writer: crate::async_compat::AsyncWrite + Send + Unpin> CapTpMessageWrite for Writer {
+    type Message = Vec<u8>;
+
+    fn prepare_message(msg: &TokenTree<'_>) -> Self::Message {
+        msg.encode().into_owned()
+    }
+
+    async fn write_message(&mut self, msg: Self::Message) -> std::io::Result<()> {
+        crate::async_compat::AsyncWriteExt::write_all(self, &msg).await
+    }
+
+    async fn flush_messages(&mut self) -> std::io::Result<()> {
+        crate::async_compat::AsyncWriteExt::flush(self).await
+    }
+}
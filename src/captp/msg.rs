@@ -6,10 +6,16 @@ pub use start_session::*;
 mod abort;
 pub use abort::*;
 
+mod gc;
+pub use gc::*;
+
+mod listen;
+pub use listen::*;
+
 mod import_export {
     use syrup::{Decode, Encode, Symbol};
 
-    #[derive(Clone, Copy, Encode, Decode)]
+    #[derive(Clone, Copy, PartialEq, Eq, Hash, Encode, Decode)]
     #[syrup(label = "desc:export")]
     pub struct DescExport {
         pub position: u64,
@@ -98,17 +104,117 @@ mod import_export {
             Self::Promise(value)
         }
     }
+
+    impl DescImport {
+        pub fn position(&self) -> u64 {
+            match self {
+                DescImport::Object(DescImportObject { position })
+                | DescImport::Promise(DescImportPromise { position }) => *position,
+            }
+        }
+    }
+
+    /// Any descriptor referring to a capability, as passed in the arguments of a delivery.
+    #[derive(Clone, Copy, Decode, Encode)]
+    #[syrup(transparent)]
+    pub enum DescCapability {
+        /// An object exported by the receiver.
+        Export(DescExport),
+        /// An object exported by the sender.
+        Object(DescImportObject),
+        /// A promise exported by the sender.
+        Promise(DescImportPromise),
+    }
+
+    impl std::fmt::Debug for DescCapability {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                DescCapability::Export(e) => e.fmt(f),
+                DescCapability::Object(o) => o.fmt(f),
+                DescCapability::Promise(p) => p.fmt(f),
+            }
+        }
+    }
+
+    /// The answer to a delivery sent with an `answer_pos`, which further deliveries can be sent to
+    /// before it resolves.
+    #[derive(Clone, Copy, PartialEq, Eq, Hash, Encode, Decode)]
+    #[syrup(label = "desc:answer")]
+    pub struct DescAnswer {
+        pub position: u64,
+    }
+
+    impl std::fmt::Debug for DescAnswer {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            self.to_tokens().fmt(f)
+        }
+    }
+
+    impl From<u64> for DescAnswer {
+        fn from(position: u64) -> Self {
+            Self { position }
+        }
+    }
+
+    /// The target of a delivery.
+    #[derive(Clone, Copy, PartialEq, Eq, Hash, Decode, Encode)]
+    #[syrup(transparent)]
+    pub enum DeliverTarget {
+        /// An object exported by the receiver.
+        Export(DescExport),
+        /// The answer to an earlier delivery from the sender.
+        Answer(DescAnswer),
+    }
+
+    impl std::fmt::Debug for DeliverTarget {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                DeliverTarget::Export(e) => e.fmt(f),
+                DeliverTarget::Answer(a) => a.fmt(f),
+            }
+        }
+    }
+
+    impl From<DescExport> for DeliverTarget {
+        fn from(value: DescExport) -> Self {
+            Self::Export(value)
+        }
+    }
+
+    impl From<DescAnswer> for DeliverTarget {
+        fn from(value: DescAnswer) -> Self {
+            Self::Answer(value)
+        }
+    }
+
+    impl From<DescExport> for DescCapability {
+        fn from(value: DescExport) -> Self {
+            Self::Export(value)
+        }
+    }
+
+    impl From<DescImportObject> for DescCapability {
+        fn from(value: DescImportObject) -> Self {
+            Self::Object(value)
+        }
+    }
+
+    impl From<DescImportPromise> for DescCapability {
+        fn from(value: DescImportPromise) -> Self {
+            Self::Promise(value)
+        }
+    }
 }
 pub use import_export::*;
 
 mod deliver {
-    use super::{DescExport, DescImport};
+    use super::{DeliverTarget, DescImport};
     use syrup::{de::Sequence, Decode, Encode};
 
     #[derive(Clone, Encode, Decode)]
     #[syrup(label = "op:deliver-only")]
     pub struct OpDeliverOnly<'arg> {
-        pub to_desc: DescExport,
+        pub to_desc: DeliverTarget,
         pub args: Sequence<'arg>,
     }
 
@@ -119,7 +225,7 @@ mod deliver {
     }
 
     impl<'arg> OpDeliverOnly<'arg> {
-        pub const fn new(to_desc: DescExport, args: Sequence<'arg>) -> Self {
+        pub const fn new(to_desc: DeliverTarget, args: Sequence<'arg>) -> Self {
             Self { to_desc, args }
         }
     }
@@ -127,7 +233,7 @@ mod deliver {
     #[derive(Clone, Encode, Decode)]
     #[syrup(label = "op:deliver")]
     pub struct OpDeliver<'arg> {
-        pub to_desc: DescExport,
+        pub to_desc: DeliverTarget,
         pub args: Sequence<'arg>,
         pub answer_pos: Option<u64>,
         pub resolve_me_desc: DescImport,
@@ -141,7 +247,7 @@ mod deliver {
 
     impl<'arg> OpDeliver<'arg> {
         pub const fn new(
-            to_desc: DescExport,
+            to_desc: DeliverTarget,
             args: Sequence<'arg>,
             answer_pos: Option<u64>,
             resolve_me_desc: DescImport,
@@ -168,9 +274,9 @@ pub(super) enum Operation<'inner> {
     Deliver(OpDeliver<'inner>),
     // Pick(OpPick),
     Abort(OpAbort<'inner>),
-    // Listen(OpListen),
-    // GcExport(OpGcExport),
-    // GcAnswer(OpGcAnswer),
+    Listen(OpListen),
+    GcExport(OpGcExport),
+    GcAnswer(OpGcAnswer),
 }
 
 impl<'i> std::fmt::Debug for Operation<'i> {
@@ -0,0 +1,30 @@
+use syrup::{Decode, Encode};
+
+/// Sent when the remote no longer references an object exported to it.
+#[derive(Clone, Copy, Encode, Decode)]
+#[syrup(label = "op:gc-export")]
+pub struct OpGcExport {
+    pub export_position: u64,
+    /// The number of times the export has been received by the sender since the last
+    /// `op:gc-export`.
+    pub wire_delta: u64,
+}
+
+impl std::fmt::Debug for OpGcExport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.to_tokens().fmt(f)
+    }
+}
+
+/// Sent when the sender no longer needs the answer at `answer_position`.
+#[derive(Clone, Copy, Encode, Decode)]
+#[syrup(label = "op:gc-answer")]
+pub struct OpGcAnswer {
+    pub answer_position: u64,
+}
+
+impl std::fmt::Debug for OpGcAnswer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.to_tokens().fmt(f)
+    }
+}
@@ -0,0 +1,17 @@
+use super::{DescExport, DescImport};
+use syrup::{Decode, Encode};
+
+/// Sent to request that `listen_desc` be notified when the promise at `to_desc` is resolved.
+#[derive(Clone, Copy, Encode, Decode)]
+#[syrup(label = "op:listen")]
+pub struct OpListen {
+    pub to_desc: DescExport,
+    pub listen_desc: DescImport,
+    pub wants_partial: bool,
+}
+
+impl std::fmt::Debug for OpListen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.to_tokens().fmt(f)
+    }
+}
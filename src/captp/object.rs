@@ -1,11 +1,11 @@
-use std::sync::Arc;
+use std::{borrow::Cow, sync::Arc};
 
 use ed25519_dalek::VerifyingKey;
-use futures::future::BoxFuture;
-use syrup::{de::Sequence, literal, Encode, Symbol, TokenTree};
+use futures::{future::BoxFuture, FutureExt};
+use syrup::{de::Sequence, literal, Decode, Encode, Symbol, TokenTree};
 
 use super::{
-    msg::{DescExport, DescImport},
+    msg::{DeliverTarget, DescAnswer, DescCapability, DescExport, DescImport},
     AbstractCapTpSession, CapTpDeliver, Delivery, GenericResolver, RemoteKey, SendError,
 };
 use crate::{
@@ -16,6 +16,18 @@ use crate::{
 mod bootstrap;
 pub use bootstrap::*;
 
+mod capability;
+pub use capability::*;
+
+mod describe;
+pub use describe::*;
+
+mod keywords;
+pub use keywords::*;
+
+mod promise;
+pub use promise::*;
+
 /// Sending half of an object pipe.
 pub type DeliverySender<'args> = mpsc::UnboundedSender<Delivery<'args>>;
 /// Receiving half of an object pipe.
@@ -31,43 +43,181 @@ pub enum ObjectError {
     Deliver(#[from] SendError),
     #[error(transparent)]
     Lex(#[from] syrup::de::LexError),
+    #[error("delivery does not specify a method")]
+    MissingMethod,
+    #[error("expected method symbol, received {0:?}")]
+    InvalidMethod(TokenTree<'static>),
+    #[error("unrecognized method: {0}")]
+    UnknownMethod(Cow<'static, str>),
+    #[error("{symbol}: missing argument {position}, expected {expected}")]
+    MissingArgument {
+        symbol: Cow<'static, str>,
+        position: usize,
+        expected: &'static str,
+    },
+    #[error("{symbol}: unexpected argument {position}, expected {expected}, received {received:?}")]
+    UnexpectedArgument {
+        symbol: Cow<'static, str>,
+        position: usize,
+        expected: &'static str,
+        received: TokenTree<'static>,
+    },
+    #[error("{symbol}: invalid keyword argument {position}: {source}")]
+    InvalidKeywords {
+        symbol: Cow<'static, str>,
+        position: usize,
+        source: KeywordError,
+    },
 }
 
-//impl<'i> ObjectError<'i> {
-//    pub fn missing(position: usize, expected: &'static str) -> Self {
-//        Self::MissingArgument { position, expected }
-//    }
-//
-//    pub fn unexpected(
-//        expected: &'static str,
-//        position: usize,
-//        received: syrup::TokenStream<'i>,
-//    ) -> Self {
-//        Self::UnexpectedArgument {
-//            expected,
-//            position,
-//            received,
-//        }
-//    }
-//}
+impl ObjectError {
+    pub fn missing(
+        symbol: impl Into<Cow<'static, str>>,
+        position: usize,
+        expected: &'static str,
+    ) -> Self {
+        Self::MissingArgument {
+            symbol: symbol.into(),
+            position,
+            expected,
+        }
+    }
+
+    pub fn unexpected(
+        symbol: impl Into<Cow<'static, str>>,
+        position: usize,
+        expected: &'static str,
+        received: TokenTree<'static>,
+    ) -> Self {
+        Self::UnexpectedArgument {
+            symbol: symbol.into(),
+            position,
+            expected,
+            received,
+        }
+    }
+
+    pub fn keywords(
+        symbol: impl Into<Cow<'static, str>>,
+        position: usize,
+        source: KeywordError,
+    ) -> Self {
+        Self::InvalidKeywords {
+            symbol: symbol.into(),
+            position,
+            source,
+        }
+    }
+
+    pub fn unknown_method(symbol: impl Into<Cow<'static, str>>) -> Self {
+        Self::UnknownMethod(symbol.into())
+    }
+
+    /// Get the [`ErrorRecord`] describing this error, to be sent to the remote.
+    pub fn to_record(&self) -> ErrorRecord<'_> {
+        let mut record = ErrorRecord::new(self.to_string());
+        match self {
+            Self::UnknownMethod(symbol) => {
+                record.method = Some(Cow::Borrowed(symbol));
+            }
+            Self::MissingArgument {
+                symbol,
+                position,
+                expected,
+            }
+            | Self::UnexpectedArgument {
+                symbol,
+                position,
+                expected,
+                ..
+            } => {
+                record.method = Some(Cow::Borrowed(symbol));
+                record.position = Some(*position as u64);
+                record.expected = Some(Cow::Borrowed(expected));
+            }
+            Self::InvalidKeywords {
+                symbol, position, ..
+            } => {
+                record.method = Some(Cow::Borrowed(symbol));
+                record.position = Some(*position as u64);
+            }
+            _ => {}
+        }
+        record
+    }
+
+    /// Break the promise associated with `resolver`, using this error's [record](Self::to_record)
+    /// as the reason.
+    ///
+    /// Only fails if the promise couldn't be broken.
+    pub async fn break_promise(self, resolver: GenericResolver) -> Result<(), Self> {
+        tracing::debug!(error = %self, "breaking promise");
+        resolver
+            .break_promise(self.to_record().to_tokens())
+            .await
+            .map_err(From::from)
+    }
+}
+
+/// Reason sent when a promise is broken by an object error.
+#[derive(Clone, Encode, Decode)]
+#[syrup(label = "rexa:error")]
+pub struct ErrorRecord<'input> {
+    pub message: Cow<'input, str>,
+    /// The symbol of the method that failed, if known.
+    pub method: Option<Cow<'input, str>>,
+    /// The position of the offending argument, if any.
+    pub position: Option<u64>,
+    /// The expected type of the offending argument, if any.
+    pub expected: Option<Cow<'input, str>>,
+}
+
+impl<'i> std::fmt::Debug for ErrorRecord<'i> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.to_tokens().fmt(f)
+    }
+}
+
+impl<'i> ErrorRecord<'i> {
+    pub fn new(message: impl Into<Cow<'i, str>>) -> Self {
+        Self {
+            message: message.into(),
+            method: None,
+            position: None,
+            expected: None,
+        }
+    }
+}
 
 pub trait Object {
     fn deliver_only(
-        &self,
+        self: Arc<Self>,
         session: Arc<dyn AbstractCapTpSession + Send + Sync>,
         args: Sequence<'static>,
     ) -> Result<(), ObjectError>;
 
-    fn deliver<'object>(
-        &'object self,
+    fn deliver(
+        self: Arc<Self>,
         session: Arc<dyn AbstractCapTpSession + Send + Sync>,
         args: Sequence<'static>,
         resolver: GenericResolver,
-    ) -> BoxFuture<'object, Result<(), ObjectError>>;
+    ) -> BoxFuture<'static, Result<(), ObjectError>>;
+
+    /// Called when this object is exported at a new position. By default, does nothing.
+    fn exported(self: Arc<Self>, _remote_key: &VerifyingKey, _position: DescExport) {}
+
+    /// Called when the remote drops its last reference to this object, after it's removed from
+    /// the session's exports. By default, does nothing.
+    fn gc(self: Arc<Self>, _remote_key: &VerifyingKey, _position: DescExport) {}
 
-    /// Called when this object is exported. By default, does nothing.
-    #[allow(unused_variables)]
-    fn exported(&self, remote_key: &VerifyingKey, position: DescExport) {}
+    /// Called when a session to which this object is exported is aborted, by either side. By
+    /// default, does nothing.
+    fn session_aborted(self: Arc<Self>, _remote_key: &VerifyingKey, _reason: &str) {}
+
+    /// Describe the methods handled by this object. By default, returns an empty slice.
+    fn methods(&self) -> &'static [MethodInfo] {
+        &[]
+    }
 }
 
 // /// An object to which the answer to a Promise may be sent.
@@ -178,18 +328,42 @@ pub enum DeliverError<'input> {
     Recv(#[from] OneshotRecvError),
     #[error("promise broken, reason: {0:?}")]
     Broken(syrup::TokenTree<'input>),
+    #[error("delivery to {to_desc:?} timed out after {after:?}")]
+    Timeout {
+        to_desc: DeliverTarget,
+        after: std::time::Duration,
+    },
+}
+
+/// Returned by proxies generated with [`remote_object`](crate::remote_object).
+#[derive(Debug, thiserror::Error)]
+pub enum CallError {
+    #[error(transparent)]
+    Deliver(#[from] DeliverError<'static>),
+    #[error(transparent)]
+    Decode(#[from] syrup::de::DecodeError<'static>),
+    #[error(transparent)]
+    Capability(#[from] CapError),
+}
+
+impl From<SendError> for CallError {
+    fn from(value: SendError) -> Self {
+        Self::Deliver(value.into())
+    }
 }
 
 #[derive(Clone)]
 pub struct RemoteObject {
-    position: DescExport,
+    target: DeliverTarget,
     session: Arc<dyn CapTpDeliver + Send + Sync + 'static>,
+    /// Present if this object is the pipelined answer to a delivery.
+    question: Option<Arc<Question>>,
 }
 
 impl std::fmt::Debug for RemoteObject {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("RemoteObject")
-            .field("position", &self.position)
+            .field("target", &self.target)
             .finish_non_exhaustive()
     }
 }
@@ -199,7 +373,11 @@ impl RemoteObject {
         session: Arc<dyn CapTpDeliver + Send + Sync + 'static>,
         position: DescExport,
     ) -> Self {
-        Self { session, position }
+        Self {
+            target: position.into(),
+            session,
+            question: None,
+        }
     }
 
     pub fn session(&self) -> &Arc<dyn CapTpDeliver + Send + Sync + 'static> {
@@ -210,33 +388,89 @@ impl RemoteObject {
         self.session.remote_vkey()
     }
 
-    pub async fn deliver_only<'i>(&self, args: Sequence<'i>) -> Result<(), SendError> {
+    /// Get the descriptor referring to `cap` from the perspective of this object's session, so
+    /// that it can be passed as an argument to this object.
+    ///
+    /// See [`Capability::encode_for`].
+    pub fn encode_cap(&self, cap: impl Into<Capability>) -> Result<DescCapability, CapError> {
+        cap.into().encode_for(&*self.session)
+    }
+
+    /// The message is queued immediately, so that deliveries through this object are sent in the
+    /// order in which these functions are called; see [`CapTpDeliver`].
+    pub fn deliver_only(&self, args: Sequence<'_>) -> BoxFuture<'_, Result<(), SendError>> {
         self.session
-            .deliver_only(&OpDeliverOnly::new(self.position, args))
-            .await
+            .deliver_only(&OpDeliverOnly::new(self.target, args))
     }
 
-    pub async fn deliver<'i>(
+    pub fn deliver(
         &self,
-        args: Sequence<'i>,
+        args: Sequence<'_>,
         answer_pos: Option<u64>,
         resolve_me_desc: DescImport,
-    ) -> Result<(), SendError> {
-        self.session
-            .deliver(&OpDeliver::new(
-                self.position,
-                args,
-                answer_pos,
-                resolve_me_desc,
-            ))
-            .await
+    ) -> BoxFuture<'_, Result<(), SendError>> {
+        self.session.deliver(&OpDeliver::new(
+            self.target,
+            args,
+            answer_pos,
+            resolve_me_desc,
+        ))
+    }
+
+    /// Send a delivery and await its answer, for at most the session's default timeout.
+    pub fn deliver_and(
+        &self,
+        args: Sequence<'_>,
+    ) -> BoxFuture<'_, Result<Sequence<'static>, DeliverError<'static>>> {
+        self.deliver_and_timeout(args, self.session.default_timeout())
     }
 
-    pub async fn deliver_and<'i>(
+    /// Send a delivery and await its answer, for at most `timeout` once it's been sent.
+    ///
+    /// If the answer doesn't arrive in time, or the returned future is dropped first, the answer
+    /// is abandoned and anything the remote later sends to it is ignored.
+    pub fn deliver_and_timeout(
         &self,
-        args: Sequence<'i>,
-    ) -> Result<Sequence<'static>, DeliverError<'static>> {
-        self.session.deliver_and(self.position, args).await
+        args: Sequence<'_>,
+        timeout: Option<std::time::Duration>,
+    ) -> BoxFuture<'_, Result<Sequence<'static>, DeliverError<'static>>> {
+        self.session.deliver_and(self.target, args, timeout)
+    }
+
+    /// Send a delivery, returning a [`Promise`] for its result once it has been sent.
+    pub fn deliver_promise(&self, args: Sequence<'_>) -> BoxFuture<'_, Result<Promise, SendError>> {
+        self.session.deliver_promise(self.target, args, None)
+    }
+
+    /// Send a delivery, returning a [`Promise`] for its result along with an object referring to
+    /// the result, which can be delivered to before the promise resolves.
+    ///
+    /// Deliveries to the returned object are sent to the remote immediately and handled by it once
+    /// the result is available, rather than waiting for the result to make a round trip. If the
+    /// result isn't an object, they're broken. The remote is told it can forget the result once
+    /// every clone of the returned object has been dropped.
+    pub fn deliver_pipelined(
+        &self,
+        args: Sequence<'_>,
+    ) -> BoxFuture<'_, Result<(Promise, RemoteObject), SendError>> {
+        // allocated before anything is sent, so that it's released even if this future is dropped
+        let question = Arc::new(Question {
+            session: self.session.clone(),
+            position: self.session.new_question(),
+        });
+        let sent = self
+            .session
+            .deliver_promise(self.target, args, Some(question.position));
+        async move {
+            let promise = sent.await?;
+            let answer = Self {
+                target: DescAnswer::from(question.position).into(),
+                session: self.session.clone(),
+                question: Some(question),
+            };
+            Ok((promise, answer))
+        }
+        .boxed()
     }
 
     //pub async fn call_only<'arg>(
@@ -281,3 +515,16 @@ impl RemoteObject {
     //     unsafe { self.session.clone().into_remote_object_unchecked(position) }
     // }
 }
+
+/// An answer position allocated by this side of a session, which the remote is told to forget once
+/// every [`RemoteObject`] referring to it has been dropped.
+struct Question {
+    session: Arc<dyn CapTpDeliver + Send + Sync + 'static>,
+    position: u64,
+}
+
+impl Drop for Question {
+    fn drop(&mut self) {
+        self.session.clone().gc_answer(self.position);
+    }
+}
@@ -1,10 +1,10 @@
 use std::sync::Arc;
-use std::{borrow::Cow, future::Future};
+use std::{borrow::Cow, future::Future, time::Duration};
 
 use syrup::{call_sequence, sequence, Decode};
 
-use super::{DeliverError, RemoteObject};
-use crate::captp::msg::{DescHandoffReceive, DescImport};
+use super::{DeliverError, Promise, RemoteObject};
+use crate::captp::msg::{DescHandoffReceive, DescImport, DescImportObject};
 use crate::captp::CapTpDeliver;
 use crate::captp::{msg::DescExport, SendError};
 
@@ -37,22 +37,52 @@ impl RemoteBootstrap {
 }
 
 impl RemoteBootstrap {
-    #[tracing::instrument(skip(self), fields(swiss_number = crate::hash(&swiss_number)))]
+    /// Fetch an object, waiting for at most the session's default timeout.
     pub async fn fetch(&self, swiss_number: &[u8]) -> Result<RemoteObject, FetchError> {
+        self.fetch_timeout(swiss_number, self.base.session.default_timeout())
+            .await
+    }
+
+    /// Fetch an object, waiting for at most `timeout`.
+    #[tracing::instrument(skip(self), fields(swiss_number = crate::hash(&swiss_number)))]
+    pub async fn fetch_timeout(
+        &self,
+        swiss_number: &[u8],
+        timeout: Option<Duration>,
+    ) -> Result<RemoteObject, FetchError> {
         tracing::trace!("fetching object");
         let mut args = self
             .base
-            .deliver_and(call_sequence!["fetch", syrup::Bytes(swiss_number.into())])
+            .deliver_and_timeout(
+                call_sequence!["fetch", syrup::Bytes(swiss_number.into())],
+                timeout,
+            )
             .await?;
-        let session = self.base.session.clone();
+        let position = args
+            .stream
+            .require(Cow::Borrowed("desc:export"))
+            .and_then(DescExport::decode)?;
 
-        Ok(RemoteObject {
-            position: args
-                .stream
-                .require(Cow::Borrowed("desc:export"))
-                .and_then(DescExport::decode)?,
-            session,
-        })
+        Ok(self
+            .base
+            .session
+            .clone()
+            .import(DescImportObject::from(position).into()))
+    }
+
+    /// Fetch an object without waiting for the answer, returning a promise for the answer along
+    /// with the fetched object, which can be delivered to straight away.
+    ///
+    /// See [`RemoteObject::deliver_pipelined`].
+    #[tracing::instrument(skip(self), fields(swiss_number = crate::hash(&swiss_number)))]
+    pub async fn fetch_pipelined(
+        &self,
+        swiss_number: &[u8],
+    ) -> Result<(Promise, RemoteObject), SendError> {
+        tracing::trace!("fetching object");
+        self.base
+            .deliver_pipelined(call_sequence!["fetch", syrup::Bytes(swiss_number.into())])
+            .await
     }
 
     #[tracing::instrument(skip(self), fields(swiss_number = crate::hash(&swiss_number)))]
@@ -0,0 +1,100 @@
+use std::sync::Arc;
+
+use super::{Object, Promise, RemoteObject};
+use crate::captp::{
+    msg::{DeliverTarget, DescAnswer, DescCapability},
+    CapTpDeliver, RemoteKey,
+};
+
+/// A capability which can be passed as an argument to a delivery.
+///
+/// Capabilities are encoded relative to the session they're sent over, with
+/// [`Capability::encode_for`] or [`RemoteObject::encode_cap`].
+#[derive(Clone)]
+pub enum Capability {
+    /// A local object, exported to the receiving session when encoded.
+    Local(Arc<dyn Object + Send + Sync>),
+    /// An object exported by a remote session.
+    ///
+    /// These can only be passed back to the session which exported them, as passing them to
+    /// another session requires a third-party handoff with `desc:handoff-give`, which isn't
+    /// implemented yet.
+    Remote(RemoteObject),
+    /// A local promise, exported to the receiving session when encoded.
+    Promise(Promise),
+}
+
+impl std::fmt::Debug for Capability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Local(_) => f.debug_tuple("Local").finish_non_exhaustive(),
+            Self::Remote(obj) => f.debug_tuple("Remote").field(obj).finish(),
+            Self::Promise(promise) => f.debug_tuple("Promise").field(promise).finish(),
+        }
+    }
+}
+
+impl<Obj: Object + Send + Sync + 'static> From<Arc<Obj>> for Capability {
+    fn from(obj: Arc<Obj>) -> Self {
+        Self::Local(obj)
+    }
+}
+
+impl From<Arc<dyn Object + Send + Sync>> for Capability {
+    fn from(obj: Arc<dyn Object + Send + Sync>) -> Self {
+        Self::Local(obj)
+    }
+}
+
+impl From<RemoteObject> for Capability {
+    fn from(obj: RemoteObject) -> Self {
+        Self::Remote(obj)
+    }
+}
+
+impl From<Promise> for Capability {
+    fn from(promise: Promise) -> Self {
+        Self::Promise(promise)
+    }
+}
+
+/// Returned by [`Capability::encode_for`].
+#[derive(Debug, thiserror::Error)]
+pub enum CapError {
+    #[error(
+        "cannot pass an object exported by {} to {}: handoffs are not yet supported",
+        crate::hash(exporter),
+        crate::hash(receiver)
+    )]
+    Handoff {
+        exporter: RemoteKey,
+        receiver: RemoteKey,
+    },
+    #[error("cannot pass the pipelined answer {0:?}; pass its result once it resolves")]
+    Answer(DescAnswer),
+}
+
+impl Capability {
+    /// Get the descriptor referring to this capability from the perspective of `session`'s remote,
+    /// exporting it if necessary.
+    ///
+    /// Remote objects can only be passed back to the session which exported them, and fail with
+    /// [`CapError::Handoff`] otherwise. Pipelined answers can't be passed until they resolve.
+    pub fn encode_for<Session>(&self, session: &Session) -> Result<DescCapability, CapError>
+    where
+        Session: CapTpDeliver + ?Sized,
+    {
+        match self {
+            Self::Local(obj) => Ok(session.exports().export_object(obj.clone()).into()),
+            Self::Promise(promise) => Ok(session.exports().export_promise(promise.clone()).into()),
+            Self::Remote(obj) if obj.remote_vkey() == session.remote_vkey() => match obj.target {
+                DeliverTarget::Export(position) => Ok(position.into()),
+                DeliverTarget::Answer(answer) => Err(CapError::Answer(answer)),
+            },
+            Self::Remote(obj) => Err(CapError::Handoff {
+                exporter: obj.remote_vkey(),
+                receiver: session.remote_vkey(),
+            }),
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use std::borrow::Cow;
+
+use syrup::{Decode, Encode};
+
+/// Whether a method is called through `op:deliver` or `op:deliver-only`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MethodKind {
+    Deliver,
+    DeliverOnly,
+}
+
+impl MethodKind {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Deliver => "deliver",
+            Self::DeliverOnly => "deliver-only",
+        }
+    }
+}
+
+/// Static description of a method handled by an [`Object`](super::Object).
+///
+/// Generated for each `#[deliver]` and `#[deliver_only]` function by
+/// [`impl_object`](crate::impl_object).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MethodInfo {
+    pub symbol: &'static str,
+    pub kind: MethodKind,
+    /// The Rust types of the arguments expected in the delivery, in order.
+    pub args: &'static [&'static str],
+    pub doc: &'static str,
+}
+
+/// Encodable form of [`MethodInfo`], returned by the built-in `__describe` method.
+#[derive(Clone, Encode, Decode)]
+#[syrup(label = "rexa:method")]
+pub struct MethodDescription<'input> {
+    #[syrup(as = syrup::Symbol)]
+    pub symbol: Cow<'input, str>,
+    #[syrup(as = syrup::Symbol)]
+    pub kind: Cow<'input, str>,
+    pub args: Vec<Cow<'input, str>>,
+    pub doc: Cow<'input, str>,
+}
+
+impl<'i> std::fmt::Debug for MethodDescription<'i> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.to_tokens().fmt(f)
+    }
+}
+
+impl From<&MethodInfo> for MethodDescription<'static> {
+    fn from(info: &MethodInfo) -> Self {
+        Self {
+            symbol: Cow::Borrowed(info.symbol),
+            kind: Cow::Borrowed(info.kind.as_str()),
+            args: info.args.iter().copied().map(Cow::Borrowed).collect(),
+            doc: Cow::Borrowed(info.doc),
+        }
+    }
+}
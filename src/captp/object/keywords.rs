@@ -0,0 +1,92 @@
+use std::{borrow::Cow, collections::HashMap};
+
+use syrup::{Decode, Symbol, TokenTree};
+
+/// Keyword arguments, decoded from a syrup dictionary with symbol keys.
+///
+/// Used for `#[arg(dict)]` inputs in [`impl_object`](crate::impl_object) functions, which are
+/// converted to the input type with [`FromKeywords`].
+#[derive(Debug, Clone, Default)]
+pub struct Keywords {
+    pub entries: HashMap<Symbol<'static>, TokenTree<'static>>,
+}
+
+impl From<HashMap<Symbol<'static>, TokenTree<'static>>> for Keywords {
+    fn from(entries: HashMap<Symbol<'static>, TokenTree<'static>>) -> Self {
+        Self { entries }
+    }
+}
+
+impl Keywords {
+    /// Remove and decode the value for `key`, if it was given.
+    pub fn take<T: Decode<'static>>(
+        &mut self,
+        key: &'static str,
+    ) -> Result<Option<T>, KeywordError> {
+        let Some(value) = self.entries.remove(&Symbol(Cow::Borrowed(key))) else {
+            return Ok(None);
+        };
+        match T::decode(value.clone()) {
+            Ok(res) => Ok(Some(res)),
+            Err(_) => Err(KeywordError::Unexpected {
+                key: Cow::Borrowed(key),
+                expected: std::any::type_name::<T>(),
+                received: value,
+            }),
+        }
+    }
+
+    /// Remove and decode the value for `key`, failing if it wasn't given.
+    pub fn require<T: Decode<'static>>(&mut self, key: &'static str) -> Result<T, KeywordError> {
+        self.take(key)?.ok_or_else(|| KeywordError::Missing {
+            key: Cow::Borrowed(key),
+            expected: std::any::type_name::<T>(),
+        })
+    }
+
+    /// The keys which haven't yet been taken.
+    pub fn remaining(&self) -> impl Iterator<Item = &Symbol<'static>> {
+        self.entries.keys()
+    }
+
+    /// Convert these keywords with [`FromKeywords`], failing if any are left unrecognized.
+    pub fn into_keywords<T: FromKeywords>(mut self) -> Result<T, KeywordError> {
+        let res = T::from_keywords(&mut self)?;
+        match self.remaining().next() {
+            Some(key) => Err(KeywordError::Unrecognized(key.0.clone())),
+            None => Ok(res),
+        }
+    }
+}
+
+/// Returned by [`Keywords`] functions and [`FromKeywords::from_keywords`].
+#[derive(Debug, thiserror::Error)]
+pub enum KeywordError {
+    #[error("missing keyword {key}, expected {expected}")]
+    Missing {
+        key: Cow<'static, str>,
+        expected: &'static str,
+    },
+    #[error("unexpected value for keyword {key}, expected {expected}, received {received:?}")]
+    Unexpected {
+        key: Cow<'static, str>,
+        expected: &'static str,
+        received: TokenTree<'static>,
+    },
+    #[error("unrecognized keyword {0}")]
+    Unrecognized(Cow<'static, str>),
+}
+
+/// A type which can be constructed from [`Keywords`].
+///
+/// Implementations [take](Keywords::take) the keywords they recognize; any left over are rejected
+/// as [unrecognized](KeywordError::Unrecognized).
+pub trait FromKeywords: Sized {
+    fn from_keywords(keywords: &mut Keywords) -> Result<Self, KeywordError>;
+}
+
+impl FromKeywords for Keywords {
+    fn from_keywords(keywords: &mut Keywords) -> Result<Self, KeywordError> {
+        Ok(std::mem::take(keywords))
+    }
+}
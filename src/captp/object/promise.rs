@@ -0,0 +1,263 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::{
+    future::{BoxFuture, Shared},
+    FutureExt,
+};
+use syrup::{de::Sequence, Encode, TokenTree};
+
+use super::{Answer, DeliverError, PromiseResult};
+use crate::{
+    async_compat::oneshot,
+    captp::{msg::DescImport, GenericResolver, SendError},
+};
+
+/// The result of a [`Promise`].
+///
+/// Errors are wrapped in an [`Arc`] so that every clone of a promise can observe them.
+pub type PromiseOutput = Result<Sequence<'static>, Arc<DeliverError<'static>>>;
+
+/// A cloneable handle to the eventual result of a delivery.
+///
+/// Promises aren't tied to the session they came from, so promises from different sessions can be
+/// chained with [`Promise::then`] or combined with [`Promise::all`] and [`Promise::race`].
+#[derive(Clone)]
+pub struct Promise {
+    inner: Shared<BoxFuture<'static, PromiseOutput>>,
+    /// Present if this promise was created by [`PromiseResolver::new`].
+    listeners: Option<Arc<Listeners>>,
+}
+
+impl std::fmt::Debug for Promise {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Promise")
+            .field("resolved", &self.peek().is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Future for Promise {
+    type Output = PromiseOutput;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.inner.poll_unpin(cx)
+    }
+}
+
+impl From<Answer<'static>> for Promise {
+    fn from(answer: Answer<'static>) -> Self {
+        Self::new(async move {
+            match answer.await {
+                Ok(Ok(args)) => Ok(args),
+                Ok(Err(reason)) => Err(DeliverError::Broken(reason)),
+                Err(error) => Err(DeliverError::Recv(error)),
+            }
+        })
+    }
+}
+
+impl Promise {
+    /// Create a promise resolved by `fut`.
+    pub fn new<E>(fut: impl Future<Output = Result<Sequence<'static>, E>> + Send + 'static) -> Self
+    where
+        E: Into<Arc<DeliverError<'static>>>,
+    {
+        Self {
+            inner: fut.map(|res| res.map_err(Into::into)).boxed().shared(),
+            listeners: None,
+        }
+    }
+
+    /// Create a promise which has already been fulfilled with `args`.
+    pub fn fulfilled(args: Sequence<'static>) -> Self {
+        Self::new(futures::future::ok::<_, Arc<DeliverError<'static>>>(args))
+    }
+
+    /// Create a promise which has already been broken with `reason`.
+    pub fn broken(reason: TokenTree<'static>) -> Self {
+        Self::new(futures::future::err(DeliverError::Broken(reason)))
+    }
+
+    /// The result of this promise, if it has been resolved and polled.
+    pub fn peek(&self) -> Option<&PromiseOutput> {
+        self.inner.peek()
+    }
+
+    /// Register a remote listener, returning it if it should instead be notified by awaiting this
+    /// promise.
+    ///
+    /// Listeners to promises created by [`PromiseResolver::new`] are notified when the promise is
+    /// resolved; listeners to other promises, or to resolved ones, are returned.
+    pub(crate) fn listen(&self, listener: GenericResolver) -> Option<GenericResolver> {
+        match &self.listeners {
+            Some(listeners) => listeners.listen(listener),
+            None => Some(listener),
+        }
+    }
+
+    /// Create a promise which, once this one is fulfilled, is resolved by the result of `f`.
+    ///
+    /// If this promise is broken, the returned promise is broken with the same error.
+    pub fn then<F, Fut, E>(self, f: F) -> Self
+    where
+        F: FnOnce(Sequence<'static>) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<Sequence<'static>, E>> + Send + 'static,
+        E: Into<Arc<DeliverError<'static>>>,
+    {
+        Self::new(async move {
+            match self.await {
+                Ok(args) => f(args).await.map_err(Into::into),
+                Err(error) => Err(error),
+            }
+        })
+    }
+
+    /// Create a promise which, if this one is broken, is resolved by the result of `f`.
+    ///
+    /// If this promise is fulfilled, the returned promise is fulfilled with the same value.
+    pub fn catch<F, Fut, E>(self, f: F) -> Self
+    where
+        F: FnOnce(Arc<DeliverError<'static>>) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<Sequence<'static>, E>> + Send + 'static,
+        E: Into<Arc<DeliverError<'static>>>,
+    {
+        Self::new(async move {
+            match self.await {
+                Ok(args) => Ok(args),
+                Err(error) => f(error).await.map_err(Into::into),
+            }
+        })
+    }
+
+    /// Wait for every promise to be fulfilled, or for any of them to be broken.
+    pub fn all(
+        promises: impl IntoIterator<Item = Promise>,
+    ) -> impl Future<Output = Result<Vec<Sequence<'static>>, Arc<DeliverError<'static>>>> {
+        futures::future::try_join_all(promises)
+    }
+
+    /// Create a promise resolved by whichever of `promises` is resolved first.
+    ///
+    /// If `promises` is empty, the returned promise is never resolved.
+    pub fn race(promises: impl IntoIterator<Item = Promise>) -> Self {
+        let promises = promises.into_iter().collect::<Vec<_>>();
+        if promises.is_empty() {
+            return Self::new(futures::future::pending::<PromiseOutput>());
+        }
+        Self::new(futures::future::select_all(promises).map(|(res, _, _)| res))
+    }
+}
+
+/// Notify a remote listener of a promise's result.
+///
+/// Errors other than [`DeliverError::Broken`] are sent as their messages.
+pub(crate) async fn notify(listener: GenericResolver, res: PromiseOutput) -> Result<(), SendError> {
+    match res {
+        Ok(args) => listener.fulfill(args, None, DescImport::default()).await,
+        Err(error) => match &*error {
+            DeliverError::Broken(reason) => listener.break_promise(reason.clone()).await,
+            error => {
+                let message = error.to_string();
+                listener.break_promise(message.to_tokens()).await
+            }
+        },
+    }
+}
+
+enum ListenState {
+    Pending(Vec<GenericResolver>),
+    Resolved,
+}
+
+/// Remote listeners to a [`Promise`] created by [`PromiseResolver::new`].
+struct Listeners {
+    state: parking_lot::Mutex<ListenState>,
+}
+
+impl Listeners {
+    fn listen(&self, listener: GenericResolver) -> Option<GenericResolver> {
+        match &mut *self.state.lock() {
+            ListenState::Pending(listeners) => {
+                listeners.push(listener);
+                None
+            }
+            ListenState::Resolved => Some(listener),
+        }
+    }
+
+    /// Mark the promise as resolved, returning the listeners which need to be notified.
+    fn resolve(&self) -> Vec<GenericResolver> {
+        match std::mem::replace(&mut *self.state.lock(), ListenState::Resolved) {
+            ListenState::Pending(listeners) => listeners,
+            ListenState::Resolved => unreachable!("promise resolved twice"),
+        }
+    }
+}
+
+/// The resolving half of a local [`Promise`], which may be exported to remote sessions with
+/// [`ExportManager::export_promise`](crate::captp::ExportManager::export_promise).
+#[must_use]
+pub struct PromiseResolver {
+    sender: oneshot::Sender<PromiseOutput>,
+    listeners: Arc<Listeners>,
+}
+
+impl std::fmt::Debug for PromiseResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PromiseResolver").finish_non_exhaustive()
+    }
+}
+
+impl PromiseResolver {
+    pub fn new() -> (Promise, Self) {
+        let (sender, receiver) = oneshot::channel();
+        let listeners = Arc::new(Listeners {
+            state: ListenState::Pending(Vec::new()).into(),
+        });
+        let mut promise = Promise::new(receiver.map(|res| match res {
+            Ok(res) => res,
+            Err(error) => Err(Arc::new(DeliverError::Recv(error))),
+        }));
+        promise.listeners = Some(listeners.clone());
+        (promise, Self { sender, listeners })
+    }
+
+    pub async fn fulfill(self, args: Sequence<'static>) {
+        self.resolve(Ok(args)).await
+    }
+
+    pub async fn break_promise(self, reason: TokenTree<'static>) {
+        self.resolve(Err(reason)).await
+    }
+
+    /// Resolve with the result of another promise, once it's available.
+    pub async fn resolve_with(self, promise: Promise) {
+        self.resolve_output(promise.await).await
+    }
+
+    /// Resolve the local promise and notify every remote listener.
+    ///
+    /// Failures to notify listeners are logged rather than returned, as they only affect the
+    /// sessions of those listeners.
+    pub async fn resolve(self, res: PromiseResult<'static>) {
+        self.resolve_output(res.map_err(|reason| Arc::new(DeliverError::Broken(reason))))
+            .await
+    }
+
+    async fn resolve_output(self, res: PromiseOutput) {
+        let listeners = self.listeners.resolve();
+        if self.sender.send(res.clone()).is_err() {
+            tracing::trace!("local promise dropped before resolution");
+        }
+        for listener in listeners {
+            if let Err(error) = notify(listener, res.clone()).await {
+                tracing::warn!(%error, "failed to notify promise listener");
+            }
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use ed25519_dalek::{SigningKey, VerifyingKey};
 use syrup::Encode;
@@ -7,10 +7,7 @@ use super::{
     msg::{DescExport, OpAbort},
     object::{RemoteBootstrap, RemoteObject},
 };
-use crate::{
-    async_compat::{AsyncRead, AsyncWrite},
-    captp::{msg::DescImportObject, CapTpReadExt},
-};
+use crate::captp::{msg::DescImportObject, CapTpMessageRead, CapTpMessageWrite};
 
 mod builder;
 pub use builder::*;
@@ -24,6 +21,10 @@ pub use manager::*;
 mod error;
 pub use error::*;
 
+mod message_queue;
+
+mod dispatch;
+
 mod keymap;
 pub(crate) use keymap::*;
 
@@ -32,6 +33,7 @@ pub use registry::*;
 
 mod internal;
 pub(crate) use internal::*;
+pub use internal::ImportKind;
 
 mod resolver;
 pub use resolver::*;
@@ -90,8 +92,8 @@ impl<Reader, Writer> From<&'_ Arc<CapTpSessionInternal<Reader, Writer>>>
 impl<Reader, Writer> CapTpSession<Reader, Writer> {
     pub fn as_dyn(&self) -> Arc<dyn AbstractCapTpSession + Send + Sync + 'static>
     where
-        Reader: CapTpReadExt + Send + 'static,
-        Writer: AsyncWrite + Send + Unpin + 'static,
+        Reader: CapTpMessageRead + Send + 'static,
+        Writer: CapTpMessageWrite + Send + 'static,
     {
         self.base.clone()
     }
@@ -104,27 +106,50 @@ impl<Reader, Writer> CapTpSession<Reader, Writer> {
         &self.base.remote_vkey
     }
 
+    /// Information about the remote end of the connection provided by the netlayer, if it's a `T`.
+    ///
+    /// For example, the credentials of the process on the other end of a unix socket.
+    pub fn peer_info<T: std::any::Any>(&self) -> Option<&T> {
+        self.base.peer_info.as_deref()?.downcast_ref()
+    }
+
     pub fn export_object(&self, obj: impl IntoExport) -> DescImportObject {
         self.base.exports.export_object(obj)
     }
 
+    /// Whether the import at `position` is an object or a promise, if the remote has sent it.
+    pub fn import_kind(&self, position: u64) -> Option<ImportKind> {
+        self.base.imports.get(&position).map(|kind| *kind)
+    }
+
     pub fn is_aborted(&self) -> bool {
         self.base.is_aborted()
     }
 
+    /// The timeout used by [`RemoteObject::deliver_and`] and [`RemoteBootstrap::fetch`] for
+    /// objects from this session.
+    pub fn default_timeout(&self) -> Option<Duration> {
+        *self.base.default_timeout.read().unwrap()
+    }
+
+    pub fn set_default_timeout(&self, timeout: Option<Duration>) {
+        *self.base.default_timeout.write().unwrap() = timeout;
+    }
+
     pub async fn abort<'reason>(&self, reason: impl Into<OpAbort<'reason>>) -> Result<(), SendError>
     where
-        Writer: AsyncWrite + Unpin,
+        Writer: CapTpMessageWrite + Send + 'static,
     {
-        let res = self.base.send_msg(&reason.into().to_tokens()).await;
-        self.base.local_abort();
+        let reason = reason.into();
+        let res = self.base.send_msg(&reason.to_tokens()).await;
+        self.base.local_abort(&reason.reason);
         res
     }
 
     pub fn into_remote_object(self, position: DescExport) -> Option<RemoteObject>
     where
         Reader: Send + 'static,
-        Writer: AsyncWrite + Send + Unpin + 'static,
+        Writer: CapTpMessageWrite + Send + 'static,
     {
         self.base.into_remote_object(position)
     }
@@ -132,15 +157,15 @@ impl<Reader, Writer> CapTpSession<Reader, Writer> {
     pub fn get_remote_bootstrap(self) -> RemoteBootstrap
     where
         Reader: Send + 'static,
-        Writer: AsyncWrite + Send + Unpin + 'static,
+        Writer: CapTpMessageWrite + Send + 'static,
     {
         RemoteBootstrap::new(self.base.clone())
     }
 
     pub fn event_stream(&self) -> impl futures::stream::Stream<Item = Result<Event, RecvError>> + '_
     where
-        Reader: CapTpReadExt + Send + 'static,
-        Writer: AsyncWrite + Send + Unpin + 'static,
+        Reader: CapTpMessageRead + Send + 'static,
+        Writer: CapTpMessageWrite + Send + 'static,
     {
         futures::stream::unfold(self, |session| async move {
             Some((session.recv_event().await, session))
@@ -151,16 +176,16 @@ impl<Reader, Writer> CapTpSession<Reader, Writer> {
         self,
     ) -> impl futures::stream::Stream<Item = Result<Event, RecvError>> + Unpin
     where
-        Reader: CapTpReadExt + Send + 'static,
-        Writer: AsyncWrite + Unpin + Send + 'static,
+        Reader: CapTpMessageRead + Send + 'static,
+        Writer: CapTpMessageWrite + Send + 'static,
     {
         use futures::StreamExt;
         async fn recv<Reader, Writer>(
             session: CapTpSession<Reader, Writer>,
         ) -> Option<(Result<Event, RecvError>, CapTpSession<Reader, Writer>)>
         where
-            Reader: CapTpReadExt + Send + 'static,
-            Writer: AsyncWrite + Send + Unpin + 'static,
+            Reader: CapTpMessageRead + Send + 'static,
+            Writer: CapTpMessageWrite + Send + 'static,
         {
             Some((session.recv_event().await, session))
         }
@@ -170,8 +195,8 @@ impl<Reader, Writer> CapTpSession<Reader, Writer> {
     // #[tracing::instrument()]
     pub async fn recv_event(&self) -> Result<Event, RecvError>
     where
-        Reader: CapTpReadExt + Send + 'static,
-        Writer: AsyncWrite + Send + Unpin + 'static,
+        Reader: CapTpMessageRead + Send + 'static,
+        Writer: CapTpMessageWrite + Send + 'static,
     {
         self.base.clone().recv_event().await
     }
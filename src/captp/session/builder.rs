@@ -1,7 +1,6 @@
-use std::future::Future;
+use std::{any::Any, future::Future};
 
 use ed25519_dalek::{SignatureError, Signer, SigningKey, VerifyingKey};
-use rand::rngs::OsRng;
 use syrup::{
     de::{DecodeError, LexError, LexErrorKind},
     Decode, Encode, TokenStream, TokenTree,
@@ -9,9 +8,9 @@ use syrup::{
 
 use super::CapTpSession;
 use crate::{
-    async_compat::{AsyncRead, AsyncWrite, AsyncWriteExt},
     captp::{
-        msg::OpStartSession, session::CapTpSessionManager, CapTpRead, CapTpReadExt, ReadSyrupError,
+        msg::OpStartSession, session::CapTpSessionManager, CapTpMessageRead, CapTpMessageWrite,
+        ReadSyrupError,
     },
     locator::NodeLocator,
     CAPTP_VERSION,
@@ -41,16 +40,17 @@ impl<'i> From<ReadSyrupError> for SessionInitError {
 }
 
 pub struct CapTpSessionBuilder<'manager, Reader, Writer> {
-    manager: &'manager mut CapTpSessionManager<Reader, Writer>,
+    manager: &'manager CapTpSessionManager<Reader, Writer>,
     reader: Reader,
     writer: Writer,
     signing_key: SigningKey,
+    peer_info: Option<Box<dyn Any + Send + Sync>>,
     // registry: Option<Arc<super::SwissRegistry<Socket>>>,
 }
 
 impl<'m, Reader, Writer> CapTpSessionBuilder<'m, Reader, Writer> {
     pub fn new(
-        manager: &'m mut CapTpSessionManager<Reader, Writer>,
+        manager: &'m CapTpSessionManager<Reader, Writer>,
         reader: Reader,
         writer: Writer,
     ) -> Self {
@@ -58,11 +58,19 @@ impl<'m, Reader, Writer> CapTpSessionBuilder<'m, Reader, Writer> {
             manager,
             reader,
             writer,
-            signing_key: SigningKey::generate(&mut OsRng),
+            signing_key: manager.signing_key().clone(),
+            peer_info: None,
             // registry: None,
         }
     }
 
+    /// Attach information about the remote end of the connection, to be retrieved with
+    /// [`CapTpSession::peer_info`].
+    pub fn with_peer_info(mut self, peer_info: Option<Box<dyn Any + Send + Sync>>) -> Self {
+        self.peer_info = peer_info;
+        self
+    }
+
     // pub fn with_registry(mut self, registry: Option<Arc<super::SwissRegistry<Socket>>>) -> Self {
     //     self.registry = registry;
     //     self
@@ -73,28 +81,30 @@ impl<'m, Reader, Writer> CapTpSessionBuilder<'m, Reader, Writer> {
         local_locator: NodeLocator<'locator>,
     ) -> impl Future<Output = Result<CapTpSession<Reader, Writer>, SessionInitError>> + 'm
     where
-        Reader: CapTpReadExt + Send,
-        Writer: AsyncWrite + Unpin,
+        Reader: CapTpMessageRead + Send,
+        Writer: CapTpMessageWrite + Send + 'static,
     {
-        let start_msg = self
-            .generate_start_msg(local_locator)
-            .to_tokens()
-            .encode()
-            .into_owned();
+        let start_msg =
+            Writer::prepare_message(&self.generate_start_msg(local_locator).to_tokens());
 
         async move {
             let (remote_vkey, remote_loc) = self.recv_start_session().await?;
 
-            self.writer.write_all(&start_msg).await?;
-            self.writer.flush().await?;
-
-            Ok(self.manager.finalize_session(
-                self.reader,
-                self.writer,
-                self.signing_key,
-                remote_vkey,
-                remote_loc,
-            ))
+            self.writer.write_message(start_msg).await?;
+            self.writer.flush_messages().await?;
+
+            let session = self
+                .manager
+                .finalize_session(
+                    self.reader,
+                    self.writer,
+                    self.peer_info,
+                    remote_vkey,
+                    remote_loc,
+                    false,
+                )
+                .await;
+            Ok(session)
         }
     }
 
@@ -103,33 +113,35 @@ impl<'m, Reader, Writer> CapTpSessionBuilder<'m, Reader, Writer> {
         local_locator: NodeLocator<'locator>,
     ) -> impl Future<Output = Result<CapTpSession<Reader, Writer>, SessionInitError>> + 'm
     where
-        Reader: CapTpReadExt + Send,
-        Writer: AsyncWrite + Unpin,
+        Reader: CapTpMessageRead + Send,
+        Writer: CapTpMessageWrite + Send + 'static,
     {
         let local_designator = local_locator.designator.clone().into_owned();
         tracing::debug!(local = %local_designator, "connecting with OpStartSession");
 
-        let start_msg = self
-            .generate_start_msg(local_locator)
-            .to_tokens()
-            .encode()
-            .into_owned();
+        let start_msg =
+            Writer::prepare_message(&self.generate_start_msg(local_locator).to_tokens());
 
         async move {
-            self.writer.write_all(&start_msg).await?;
-            self.writer.flush().await?;
+            self.writer.write_message(start_msg).await?;
+            self.writer.flush_messages().await?;
 
             tracing::debug!(local = %local_designator, "sent OpStartSession, receiving response");
 
             let (remote_vkey, remote_loc) = self.recv_start_session().await?;
 
-            Ok(self.manager.finalize_session(
-                self.reader,
-                self.writer,
-                self.signing_key,
-                remote_vkey,
-                remote_loc,
-            ))
+            let session = self
+                .manager
+                .finalize_session(
+                    self.reader,
+                    self.writer,
+                    self.peer_info,
+                    remote_vkey,
+                    remote_loc,
+                    true,
+                )
+                .await;
+            Ok(session)
         }
     }
 
@@ -151,11 +163,11 @@ impl<'m, Reader, Writer> CapTpSessionBuilder<'m, Reader, Writer> {
         &mut self,
     ) -> Result<(VerifyingKey, NodeLocator<'static>), SessionInitError>
     where
-        Reader: CapTpReadExt + Send,
+        Reader: CapTpMessageRead + Send,
     {
         let response = self
             .reader
-            .consume_syrup()
+            .read_message()
             .await?
             .decode::<OpStartSession<'static>>()?;
 
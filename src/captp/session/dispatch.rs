@@ -0,0 +1,180 @@
+use std::{
+    collections::{hash_map::Entry, HashMap, VecDeque},
+    convert::Infallible,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Waker},
+};
+
+use futures::{
+    future::BoxFuture,
+    stream::{FuturesUnordered, StreamExt},
+    task::ArcWake,
+    FutureExt,
+};
+use parking_lot::Mutex;
+
+use crate::captp::msg::DeliverTarget;
+
+type Queues = Mutex<HashMap<DeliverTarget, VecDeque<BoxFuture<'static, ()>>>>;
+
+/// Handlers for incoming messages, run by whichever calls to `recv_event` are in progress.
+///
+/// Work queued for the same target runs one item at a time, in the order in which it was queued,
+/// while work for different targets runs concurrently. Nothing is run while the reader is held, so
+/// handlers may wait on later messages from the remote.
+#[derive(Default)]
+pub(crate) struct Dispatcher {
+    /// Work waiting on earlier work for the same target. A target has an entry for as long as its
+    /// runner is running.
+    queues: Arc<Queues>,
+    /// Runners started since `running` was last polled.
+    started: Mutex<Vec<BoxFuture<'static, ()>>>,
+    /// Only locked while polling, so that work can start or clear work without deadlocking.
+    running: Mutex<FuturesUnordered<BoxFuture<'static, ()>>>,
+    /// Set when `running` may need to be polled again by whichever task holds it.
+    contended: AtomicBool,
+    cleared: AtomicBool,
+    pollers: Arc<Pollers>,
+}
+
+impl Dispatcher {
+    /// Queue `work` to be run once all work previously queued for `target` has finished.
+    pub(crate) fn dispatch(&self, target: DeliverTarget, work: BoxFuture<'static, ()>) {
+        if self.cleared.load(Ordering::SeqCst) {
+            return;
+        }
+        match self.queues.lock().entry(target) {
+            Entry::Occupied(mut queue) => {
+                queue.get_mut().push_back(work);
+                return;
+            }
+            Entry::Vacant(queue) => {
+                queue.insert(VecDeque::new());
+            }
+        }
+        self.started
+            .lock()
+            .push(run_queue(self.queues.clone(), target, work));
+        self.pollers.wake_all();
+    }
+
+    /// Drop all queued and running work, and any work dispatched afterwards.
+    pub(crate) fn clear(&self) {
+        self.cleared.store(true, Ordering::SeqCst);
+        self.queues.lock().clear();
+        self.started.lock().clear();
+        // otherwise whichever task is polling the work clears it
+        if let Some(mut running) = self.running.try_lock() {
+            running.clear();
+        }
+    }
+
+    /// Run dispatched work for as long as the returned future is polled.
+    pub(crate) fn run(&self) -> Run<'_> {
+        Run {
+            dispatcher: self,
+            id: self.pollers.next_id.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+}
+
+/// Run `first`, then the rest of the work queued for `target`.
+fn run_queue(
+    queues: Arc<Queues>,
+    target: DeliverTarget,
+    first: BoxFuture<'static, ()>,
+) -> BoxFuture<'static, ()> {
+    async move {
+        let mut next = first;
+        loop {
+            next.await;
+            let mut queues = queues.lock();
+            match queues.get_mut(&target).and_then(VecDeque::pop_front) {
+                Some(work) => next = work,
+                None => {
+                    queues.remove(&target);
+                    break;
+                }
+            }
+        }
+    }
+    .boxed()
+}
+
+/// The tasks polling a [`Dispatcher`], any of which may be woken to run its work.
+#[derive(Default)]
+struct Pollers {
+    wakers: Mutex<HashMap<usize, Waker>>,
+    next_id: AtomicUsize,
+}
+
+impl Pollers {
+    fn wake_all(&self) {
+        // a waker may lead straight back here, so wake them without holding the lock
+        let wakers = self.wakers.lock().values().cloned().collect::<Vec<_>>();
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+impl ArcWake for Pollers {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.wake_all();
+    }
+}
+
+/// Returned by [`Dispatcher::run`]. Never completes.
+pub(crate) struct Run<'dispatcher> {
+    dispatcher: &'dispatcher Dispatcher,
+    id: usize,
+}
+
+impl Future for Run<'_> {
+    type Output = Infallible;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let dispatcher = self.dispatcher;
+        dispatcher
+            .pollers
+            .wakers
+            .lock()
+            .insert(self.id, cx.waker().clone());
+        // work wakes every poller, so polling it with one waker is enough
+        let waker = futures::task::waker(dispatcher.pollers.clone());
+        loop {
+            // flagged before trying the lock, so that if another task holds it, that task polls
+            // again after releasing it
+            dispatcher.contended.store(true, Ordering::SeqCst);
+            let Some(mut running) = dispatcher.running.try_lock() else {
+                return Poll::Pending;
+            };
+            dispatcher.contended.store(false, Ordering::SeqCst);
+            running.extend(dispatcher.started.lock().drain(..));
+            while !dispatcher.cleared.load(Ordering::SeqCst)
+                && matches!(
+                    running.poll_next_unpin(&mut Context::from_waker(&waker)),
+                    Poll::Ready(Some(()))
+                )
+            {}
+            if dispatcher.cleared.load(Ordering::SeqCst) {
+                running.clear();
+            }
+            drop(running);
+            if !dispatcher.contended.swap(false, Ordering::SeqCst) {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+impl Drop for Run<'_> {
+    fn drop(&mut self) {
+        self.dispatcher.pollers.wakers.lock().remove(&self.id);
+    }
+}
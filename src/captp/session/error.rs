@@ -17,8 +17,8 @@ pub enum RecvError {
     SessionAborted(String),
     #[error("attempted recv on locally aborted session")]
     SessionAbortedLocally,
-    #[error("unknown delivery target: {0}, args: {1:?}")]
-    UnknownTarget(u64, Sequence<'static>),
+    #[error("delivery to unknown answer: {0}, args: {1:?}")]
+    UnknownAnswer(u64, Sequence<'static>),
 }
 
 impl From<ReadSyrupError> for RecvError {
@@ -38,4 +38,10 @@ pub enum SendError {
     SessionAborted(String),
     #[error("attempted send on locally aborted session")]
     SessionAbortedLocally,
+    #[error("attempted send to unknown import: {0}")]
+    UnknownImport(u64),
+    #[error("attempted send to unknown answer: {0}")]
+    UnknownAnswer(u64),
+    #[error("message {0} was lost before it could be written")]
+    MessageLost(u64),
 }
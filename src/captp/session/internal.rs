@@ -1,20 +1,35 @@
-use super::{KeyMap, RecvError, SendError};
+use super::{dispatch::Dispatcher, message_queue::MessageQueue, KeyMap, RecvError, SendError};
 use crate::{
-    async_compat::{AsyncRead, AsyncWrite, AsyncWriteExt},
+    async_compat::oneshot,
     captp::{
-        msg::{DescExport, DescImport, DescImportObject, DescImportPromise, Operation},
-        object::Object,
-        CapTpReadExt, IntoExport, RemoteKey,
+        msg::{
+            DeliverTarget, DescAnswer, DescExport, DescImport, DescImportObject, DescImportPromise,
+            OpDeliverOnly, Operation,
+        },
+        object::{Object, Promise, PromiseResolver},
+        CapTpDeliver, CapTpMessageRead, CapTpMessageWrite, GenericResolver, IntoExport, RemoteKey,
     },
     locator::NodeLocator,
 };
-use dashmap::{DashMap, DashSet};
+use dashmap::{mapref::entry::Entry, DashMap, DashSet};
 use ed25519_dalek::{SigningKey, VerifyingKey};
-use futures::lock::Mutex;
-use std::sync::{atomic::AtomicBool, Arc, RwLock};
+use futures::{
+    future::{select, BoxFuture, Either, Shared},
+    lock::Mutex,
+    FutureExt,
+};
+use std::{
+    any::Any,
+    collections::{HashSet, VecDeque},
+    sync::{
+        atomic::{AtomicBool, AtomicU64},
+        Arc, RwLock,
+    },
+    time::Duration,
+};
 use syrup::{
     de::{Literal, LiteralValue},
-    Decode, Encode, Sequence, TokenTree,
+    literal, Decode, Encode, Sequence, TokenTree,
 };
 use tracing::Instrument;
 
@@ -22,8 +37,20 @@ pub struct ExportManager {
     pub(super) remote_vkey: RemoteKey,
     /// Objects exported to the remote
     pub(super) exports: KeyMap<Arc<dyn Object + Send + Sync>>,
-    /// Answers exported to the remote
-    pub(super) answers: DashMap<u64, ()>,
+    /// Promises exported to the remote, sharing positions with `exports`
+    pub(super) promises: DashMap<u64, Promise>,
+    /// How many times each export has been sent to the remote, less the `wire_delta` of each
+    /// `op:gc-export` it has sent back
+    wire_counts: DashMap<u64, u64>,
+    /// Positions of exported objects by address, so that exporting an object again reuses its
+    /// position
+    positions: DashMap<usize, u64>,
+    /// Positions of answer resolvers which were removed before being resolved
+    pub(super) abandoned: Abandoned,
+    /// Answers to deliveries from the remote, which it may pipeline further deliveries on
+    answers: DashMap<u64, Shared<BoxFuture<'static, AnswerTarget>>>,
+    /// Resolve the unresolved `answers`
+    answer_resolvers: DashMap<u64, oneshot::Sender<AnswerTarget>>,
 }
 
 impl ExportManager {
@@ -32,38 +59,284 @@ impl ExportManager {
             remote_vkey,
             // Bootstrap object handled internally.
             exports: KeyMap::with_initial(1),
+            promises: DashMap::new(),
+            wire_counts: DashMap::new(),
+            positions: DashMap::new(),
+            abandoned: Abandoned::default(),
             answers: DashMap::new(),
+            answer_resolvers: DashMap::new(),
         }
     }
 
+    /// Export an object, counting it as sent to the remote once more.
+    ///
+    /// An object which is still exported keeps its position, and [`Object::exported`] is only
+    /// called when it's given a new one.
     pub fn export_object(&self, obj: impl IntoExport) -> DescImportObject {
         let obj = obj.into_export();
+        // held until the export is complete, so the same object exported concurrently gets one
+        // position
+        let mut entry = match self.positions.entry(object_key(&obj)) {
+            Entry::Occupied(entry) => {
+                // missing if the export has been collected, in which case it gets a new position
+                if let Some(mut count) = self.wire_counts.get_mut(entry.get()) {
+                    *count += 1;
+                    return DescImportObject {
+                        position: *entry.get(),
+                    };
+                }
+                entry.into_ref()
+            }
+            Entry::Vacant(entry) => entry.insert(0),
+        };
         let reserve = self.exports.reserve();
-        obj.exported(&self.remote_vkey, reserve.key().into());
-        DescImportObject {
-            position: reserve.finalize(obj),
+        self.wire_counts.insert(reserve.key(), 1);
+        *entry = reserve.key();
+        let position = reserve.finalize(obj.clone());
+        drop(entry);
+        obj.exported(&self.remote_vkey, position.into());
+        DescImportObject { position }
+    }
+
+    /// Export a promise, so that the remote can listen for its resolution and deliver to it.
+    pub fn export_promise(&self, promise: Promise) -> DescImportPromise {
+        let reserve = self.exports.reserve();
+        self.wire_counts.insert(reserve.key(), 1);
+        DescImportPromise {
+            position: reserve.finalize_in(&self.promises, promise),
+        }
+    }
+
+    /// Export a new local promise, returning its resolver.
+    pub fn export_answer(&self) -> (DescImportPromise, PromiseResolver) {
+        let (promise, resolver) = PromiseResolver::new();
+        (self.export_promise(promise), resolver)
+    }
+
+    /// Register a listener to the promise at `position`, returning it along with the promise if it
+    /// should instead be notified once the promise resolves.
+    ///
+    /// Returns the listener as an error if there's no promise at `position`.
+    fn listen(
+        &self,
+        position: u64,
+        listener: GenericResolver,
+    ) -> Result<Option<(GenericResolver, Promise)>, GenericResolver> {
+        let Some(promise) = self.promises.get(&position).map(|promise| promise.clone()) else {
+            return Err(listener);
+        };
+        Ok(promise.listen(listener).map(|listener| (listener, promise)))
+    }
+
+    /// Keep the answer to a delivery from the remote at `position`, so that it can pipeline
+    /// deliveries on it.
+    fn expect_answer(&self, position: u64) {
+        let (sender, receiver) = oneshot::channel();
+        let answer = receiver
+            .map(|res| {
+                res.unwrap_or_else(|_| {
+                    AnswerTarget::Broken(literal![String; b"answer was dropped unresolved"])
+                })
+            })
+            .boxed()
+            .shared();
+        self.answers.insert(position, answer);
+        self.answer_resolvers.insert(position, sender);
+    }
+
+    /// Resolve the answer at `position`, if the remote is still expecting it.
+    pub(super) fn resolve_answer(&self, position: u64, target: AnswerTarget) {
+        if let Some((_, sender)) = self.answer_resolvers.remove(&position) {
+            if sender.send(target).is_err() {
+                tracing::trace!(position, "answer collected before being resolved");
+            }
+        }
+    }
+
+    fn answer(&self, position: u64) -> Option<Shared<BoxFuture<'static, AnswerTarget>>> {
+        self.answers.get(&position).map(|answer| answer.clone())
+    }
+
+    /// Forget the answer at `position`, breaking deliveries still waiting on it.
+    fn gc_answer(&self, position: u64) {
+        self.answers.remove(&position);
+        self.answer_resolvers.remove(&position);
+    }
+
+    /// Release `wire_delta` of the references to an export held by the remote, removing it once
+    /// none are left and calling [`Object::gc`] if it was an object.
+    fn gc_export(&self, position: u64, wire_delta: u64) {
+        if let Some(mut count) = self.wire_counts.get_mut(&position) {
+            *count = count.saturating_sub(wire_delta);
+        }
+        // also fails if the export was sent again since, or has already been removed
+        if self
+            .wire_counts
+            .remove_if(&position, |_, count| *count == 0)
+            .is_none()
+        {
+            return;
+        }
+        if self.promises.remove(&position).is_some() {
+            return;
+        }
+        if let Some(obj) = self.remove_export(position) {
+            obj.gc(&self.remote_vkey, position.into());
+        }
+    }
+
+    /// Unexport the object at `position`, returning it if it was exported.
+    fn remove_export(&self, position: u64) -> Option<Arc<dyn Object + Send + Sync>> {
+        self.wire_counts.remove(&position);
+        let obj = self.exports.remove(position)?;
+        self.positions
+            .remove_if(&object_key(&obj), |_, exported| *exported == position);
+        Some(obj)
+    }
+
+    /// Call [`Object::session_aborted`] for every export.
+    fn session_aborted(&self, reason: &str) {
+        for obj in self.exports.values() {
+            obj.session_aborted(&self.remote_vkey, reason);
+        }
+    }
+}
+
+/// Identifies an exported object by its address.
+fn object_key(obj: &Arc<dyn Object + Send + Sync>) -> usize {
+    Arc::as_ptr(obj).cast::<()>() as usize
+}
+
+/// Unexports the resolver for an answer when dropped.
+///
+/// If the answer wasn't received, its position is remembered as abandoned so that the remote
+/// resolving it late isn't treated as an error.
+pub(crate) struct AnswerGuard<'exports> {
+    exports: &'exports ExportManager,
+    position: u64,
+    answered: bool,
+}
+
+impl<'e> AnswerGuard<'e> {
+    pub(crate) fn new(exports: &'e ExportManager, position: u64) -> Self {
+        Self {
+            exports,
+            position,
+            answered: false,
         }
     }
 
-    pub fn export_answer(&self) -> DescImportPromise {
-        todo!()
+    pub(crate) fn answered(&mut self) {
+        self.answered = true;
+    }
+}
+
+impl Drop for AnswerGuard<'_> {
+    fn drop(&mut self) {
+        self.exports.remove_export(self.position);
+        if !self.answered {
+            tracing::debug!(position = self.position, "abandoning answer");
+            self.exports.abandoned.insert(self.position);
+        }
+    }
+}
+
+/// The most recently abandoned answer positions.
+///
+/// Only the last [`Abandoned::CAPACITY`] are remembered, so a remote which resolves an answer
+/// long after it was abandoned is treated as delivering to an unknown target.
+#[derive(Default)]
+pub(crate) struct Abandoned {
+    positions: parking_lot::Mutex<(VecDeque<u64>, HashSet<u64>)>,
+}
+
+impl Abandoned {
+    const CAPACITY: usize = 1024;
+
+    fn insert(&self, position: u64) {
+        let (order, positions) = &mut *self.positions.lock();
+        // positions removed since they were inserted still take up space in `order`
+        if order.len() >= Self::CAPACITY {
+            if let Some(oldest) = order.pop_front() {
+                positions.remove(&oldest);
+            }
+        }
+        order.push_back(position);
+        positions.insert(position);
+    }
+
+    /// Forget `position`, returning whether it had been abandoned.
+    fn remove(&self, position: u64) -> bool {
+        self.positions.lock().1.remove(&position)
+    }
+}
+
+/// What the answer to a delivery from the remote resolved to.
+#[derive(Clone)]
+pub(crate) enum AnswerTarget {
+    /// An object exported to the remote.
+    Local(u64),
+    /// An object exported by the remote.
+    Remote(u64),
+    /// The answer was broken, or isn't an object, with the reason given to deliveries on it.
+    Broken(TokenTree<'static>),
+}
+
+impl AnswerTarget {
+    /// What an answer fulfilled with `value` as its first argument resolved to.
+    pub(crate) fn of(value: Option<&TokenTree<'_>>) -> Self {
+        let Some(value) = value else {
+            return Self::Broken(literal![String; b"answer was fulfilled without a value"]);
+        };
+        if let Ok(DescImportObject { position }) = DescImportObject::decode(value.clone()) {
+            Self::Local(position)
+        } else if let Ok(DescExport { position }) = DescExport::decode(value.clone()) {
+            Self::Remote(position)
+        } else {
+            Self::Broken(literal![String; b"answer is not an object"])
+        }
+    }
+}
+
+/// Whether an import refers to an object or a promise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImportKind {
+    Object,
+    Promise,
+}
+
+impl From<&DescImport> for ImportKind {
+    fn from(desc: &DescImport) -> Self {
+        match desc {
+            DescImport::Object(_) => Self::Object,
+            DescImport::Promise(_) => Self::Promise,
+        }
     }
 }
 
 pub(crate) struct CapTpSessionInternal<Reader, Writer> {
     reader: Mutex<Reader>,
-    writer: Mutex<Writer>,
+    outbound: MessageQueue<Writer>,
+    /// Runs deliveries to each export in the order in which they were received
+    dispatcher: Dispatcher,
     pub(super) signing_key: SigningKey,
+    /// Provided by the netlayer; see [`CapTpSession::peer_info`](super::CapTpSession::peer_info)
+    pub(super) peer_info: Option<Box<dyn Any + Send + Sync>>,
 
     pub(super) remote_vkey: RemoteKey,
     pub(super) remote_locator: NodeLocator<'static>,
 
-    /// Objects imported from the remote
-    pub(super) imports: DashSet<u64>,
+    /// Objects and promises imported from the remote
+    pub(super) imports: DashMap<u64, ImportKind>,
+    /// Answer positions allocated for deliveries to the remote which haven't been collected
+    pub(super) questions: DashSet<u64>,
+    pub(super) next_question: AtomicU64,
     pub(super) exports: ExportManager,
 
     pub(super) aborted_by_remote: RwLock<Option<String>>,
     pub(super) aborted_locally: AtomicBool,
+
+    pub(super) default_timeout: RwLock<Option<Duration>>,
 }
 
 #[cfg(feature = "extra-diagnostics")]
@@ -86,51 +359,67 @@ impl<Reader, Writer> std::fmt::Debug for CapTpSessionInternal<Reader, Writer> {
 impl<Reader, Writer> CapTpSessionInternal<Reader, Writer> {
     pub(super) fn new(
         reader: Mutex<Reader>,
-        writer: Mutex<Writer>,
+        writer: Writer,
         signing_key: SigningKey,
+        peer_info: Option<Box<dyn Any + Send + Sync>>,
         remote_vkey: RemoteKey,
         remote_locator: NodeLocator<'static>,
     ) -> Self {
         Self {
             reader,
-            writer,
+            outbound: MessageQueue::new(writer),
+            dispatcher: Dispatcher::default(),
             signing_key,
+            peer_info,
 
             remote_vkey,
             remote_locator,
 
-            imports: DashSet::new(),
+            imports: DashMap::new(),
+            questions: DashSet::new(),
+            next_question: AtomicU64::new(0),
             exports: ExportManager::new(remote_vkey),
             aborted_by_remote: RwLock::default(),
             aborted_locally: false.into(),
+
+            default_timeout: RwLock::default(),
         }
     }
 
     //#[tracing::instrument(skip(msg))]
-    pub(super) fn send_msg<'fut, 'msg: 'fut>(
-        &'fut self,
-        msg: &'fut TokenTree<'msg>,
-    ) -> impl std::future::Future<Output = Result<(), SendError>> + 'fut
+    pub(super) async fn send_msg(&self, msg: &TokenTree<'_>) -> Result<(), SendError>
     where
-        Writer: AsyncWrite + Unpin,
+        Writer: CapTpMessageWrite + Send + 'static,
     {
-        async move {
-            if self
-                .aborted_locally
-                .load(std::sync::atomic::Ordering::Relaxed)
-            {
-                return Err(SendError::SessionAbortedLocally);
-            }
-            if let Some(reason) = self.aborted_by_remote.read().unwrap().as_ref() {
-                return Err(SendError::SessionAborted(reason.clone()));
-            }
-            self.writer
-                .lock()
-                .await
-                .write_all(&msg.encode())
-                .await
-                .map_err(SendError::from)
+        let ticket = self.queue_msg(msg)?;
+        self.flush(ticket).await
+    }
+
+    /// Queue a message to be written by [`Self::flush`], returning its ticket.
+    ///
+    /// Messages are written in the order in which this is called; see [`MessageQueue`].
+    pub(super) fn queue_msg(&self, msg: &TokenTree<'_>) -> Result<u64, SendError>
+    where
+        Writer: CapTpMessageWrite + Send + 'static,
+    {
+        if self
+            .aborted_locally
+            .load(std::sync::atomic::Ordering::Relaxed)
+        {
+            return Err(SendError::SessionAbortedLocally);
+        }
+        if let Some(reason) = self.aborted_by_remote.read().unwrap().as_ref() {
+            return Err(SendError::SessionAborted(reason.clone()));
         }
+        Ok(self.outbound.push(msg))
+    }
+
+    /// Write queued messages until the message with `ticket` has been written.
+    pub(super) async fn flush(&self, ticket: u64) -> Result<(), SendError>
+    where
+        Writer: CapTpMessageWrite + Send + 'static,
+    {
+        self.outbound.flush(ticket).await
     }
 
     //#[tracing::instrument]
@@ -146,9 +435,9 @@ impl<Reader, Writer> CapTpSessionInternal<Reader, Writer> {
     //        .map_err(From::from)
     //}
 
-    pub(super) async fn recv_msg<Msg>(&self) -> Result<Msg, RecvError>
+    pub(super) async fn recv_msg<Msg>(&self, reader: &mut Reader) -> Result<Msg, RecvError>
     where
-        Reader: CapTpReadExt + Send,
+        Reader: CapTpMessageRead + Send,
         Msg: Decode<'static>,
     {
         if self
@@ -160,10 +449,8 @@ impl<Reader, Writer> CapTpSessionInternal<Reader, Writer> {
         if let Some(reason) = self.aborted_by_remote.read().unwrap().as_ref() {
             return Err(RecvError::SessionAborted(reason.clone()));
         }
-        self.reader
-            .lock()
-            .await
-            .consume_syrup()
+        reader
+            .read_message()
             .await?
             .decode::<Msg>()
             .map_err(From::from)
@@ -172,12 +459,45 @@ impl<Reader, Writer> CapTpSessionInternal<Reader, Writer> {
     // pub(super) fn export(&self, val: Arc<dyn crate::captp::object::Object + Send + Sync>) -> u64 {
     // }
 
-    pub(super) fn local_abort(&self) {
+    /// Record an import descriptor received from the remote, so that it can be delivered to.
+    pub(super) fn record_import(&self, desc: &DescImport) {
+        self.imports.insert(desc.position(), desc.into());
+    }
+
+    /// Fail if `position` isn't the bootstrap object or a recorded import.
+    pub(super) fn check_import(&self, position: u64) -> Result<(), SendError> {
+        if position == 0 || self.imports.contains_key(&position) {
+            Ok(())
+        } else {
+            Err(SendError::UnknownImport(position))
+        }
+    }
+
+    /// Fail if `target` isn't a recorded import or an uncollected answer.
+    pub(super) fn check_target(&self, target: DeliverTarget) -> Result<(), SendError> {
+        match target {
+            DeliverTarget::Export(DescExport { position }) => self.check_import(position),
+            DeliverTarget::Answer(DescAnswer { position })
+                if self.questions.contains(&position) =>
+            {
+                Ok(())
+            }
+            DeliverTarget::Answer(DescAnswer { position }) => {
+                Err(SendError::UnknownAnswer(position))
+            }
+        }
+    }
+
+    pub(super) fn local_abort(&self, reason: &str) {
         self.aborted_locally
             .store(true, std::sync::atomic::Ordering::Relaxed);
+        self.dispatcher.clear();
+        self.exports.session_aborted(reason);
     }
 
     pub(super) fn set_remote_abort(&self, reason: String) {
+        self.dispatcher.clear();
+        self.exports.session_aborted(&reason);
         *self.aborted_by_remote.write().unwrap() = Some(reason);
     }
 
@@ -190,140 +510,325 @@ impl<Reader, Writer> CapTpSessionInternal<Reader, Writer> {
     // TODO :: propagate delivery errors
     pub(super) async fn recv_event(self: Arc<Self>) -> Result<super::Event, RecvError>
     where
-        Reader: CapTpReadExt + Send + 'static,
-        Writer: AsyncWrite + Send + Unpin + 'static,
+        Reader: CapTpMessageRead + Send + 'static,
+        Writer: CapTpMessageWrite + Send + 'static,
     {
-        fn bootstrap_deliver_only<'args>(mut args: Sequence<'args>) -> super::Event {
-            match args.stream.pop() {
-                Some(TokenTree::Literal(Literal {
-                    repr: LiteralValue::Symbol(ident),
-                    ..
-                })) => match &*ident {
-                    b"deposit-gift" => todo!("bootstrap: deposit-gift"),
-                    id => todo!(
-                        "unrecognized bootstrap function: {}",
-                        String::from_utf8_lossy(id)
-                    ),
-                },
-                _ => todo!(),
+        let mut run = std::pin::pin!(self.dispatcher.run());
+        loop {
+            let handled = std::pin::pin!(async {
+                tracing::trace!("awaiting message");
+                let mut reader = self.reader.lock().await;
+                let msg = self
+                    .recv_msg::<crate::captp::msg::Operation<'static>>(&mut reader)
+                    .await?;
+                // handled before the reader is released, so that concurrent calls to
+                // `recv_event` queue deliveries to each target in the order in which they were
+                // received
+                self.clone().handle_msg(msg)
+            });
+            // run handlers while waiting for the next message, since they may be waiting on it
+            match select(handled, run.as_mut()).await {
+                Either::Left((Ok(Some(event)), _)) => break Ok(event),
+                Either::Left((Ok(None), _)) => {}
+                Either::Left((Err(error), _)) => break Err(error),
+                Either::Right((never, _)) => match never {},
             }
         }
-        fn bootstrap_deliver<'args, Reader, Writer>(
-            session: Arc<CapTpSessionInternal<Reader, Writer>>,
-            mut args: Sequence<'args>,
-            answer_pos: Option<u64>,
-            resolve_me_desc: crate::captp::msg::DescImport,
-        ) -> super::Event
-        where
-            Writer: AsyncWrite + Send + Unpin + 'static,
-            Reader: Send + 'static,
-        {
-            match args.stream.pop() {
-                Some(TokenTree::Literal(Literal {
-                    repr: LiteralValue::Symbol(ident),
-                    ..
-                })) => match &*ident {
-                    b"fetch" => {
-                        let swiss = match args.stream.pop() {
-                            Some(TokenTree::Literal(Literal {
-                                repr: LiteralValue::Symbol(swiss),
-                                ..
-                            })) => swiss,
-                            Some(s) => todo!("malformed swiss num: {s:?}"),
-                            None => todo!("missing swiss num"),
-                        };
-                        super::Event::Bootstrap(crate::captp::BootstrapEvent::Fetch {
-                            resolver: crate::captp::GenericResolver::new(
-                                session,
-                                answer_pos,
-                                resolve_me_desc,
-                            )
-                            .into(),
-                            swiss: swiss.into_owned(),
-                        })
-                    }
-                    b"withdraw-gift" => todo!("bootstrap: withdraw-gift"),
-                    id => todo!(
-                        "unrecognized bootstrap function: {}",
-                        String::from_utf8_lossy(id)
-                    ),
-                },
-                _ => todo!(),
+    }
+
+    /// Handle a message from the remote, returning an event if it should be handled by the caller
+    /// of `recv_event`.
+    ///
+    /// Deliveries and notifications are queued on [`Dispatcher`] rather than awaited here.
+    fn handle_msg(
+        self: Arc<Self>,
+        msg: Operation<'static>,
+    ) -> Result<Option<super::Event>, RecvError>
+    where
+        Reader: Send + 'static,
+        Writer: CapTpMessageWrite + Send + 'static,
+    {
+        tracing::debug!(?msg, "received message");
+        // record the descriptors we're expected to resolve; imports within arguments are
+        // recorded as they're decoded
+        match &msg {
+            Operation::Deliver(del) => {
+                self.record_import(&del.resolve_me_desc);
+                // kept before the delivery is handled, so that deliveries pipelined on the answer
+                // can wait for it
+                if let Some(answer_pos) = del.answer_pos {
+                    self.exports.expect_answer(answer_pos);
+                }
             }
+            Operation::Listen(listen) => self.record_import(&listen.listen_desc),
+            _ => {}
         }
-        loop {
-            tracing::trace!("awaiting message");
-            let msg = self
-                .recv_msg::<crate::captp::msg::Operation<'static>>()
-                .await?;
-            tracing::debug!(?msg, "received message");
-            match msg {
-                Operation::DeliverOnly(del) => match del.to_desc.position {
-                    0 => break Ok(bootstrap_deliver_only(del.args)),
-                    pos => {
-                        // let del = Delivery::DeliverOnly {
-                        //     to_desc: del.to_desc,
-                        //     args: del.args,
-                        // };
-                        // break Ok(Event::Delivery(del));
-                        match self.exports.exports.get(&pos) {
-                            Some(obj) => {
-                                let span = tracing::info_span!("deliver_only");
-                                let _guard = span.enter();
-                                if let Err(error) = obj.deliver_only(self.clone(), del.args) {
-                                    tracing::error!(pos, %error, "deliver_only");
+        match msg {
+            Operation::DeliverOnly(del) => match del.to_desc {
+                DeliverTarget::Export(DescExport { position: 0 }) => {
+                    return Ok(Some(bootstrap_deliver_only(del.args)))
+                }
+                DeliverTarget::Export(DescExport { position: pos }) => {
+                    let obj = self.exports.exports.get(&pos).map(|obj| obj.clone());
+                    match obj {
+                        Some(obj) => {
+                            let session = self.clone();
+                            self.dispatcher.dispatch(
+                                del.to_desc,
+                                async move {
+                                    let span = tracing::info_span!("deliver_only");
+                                    if let Err(error) =
+                                        span.in_scope(|| obj.deliver_only(session, del.args))
+                                    {
+                                        tracing::error!(pos, %error, "deliver_only");
+                                    }
                                 }
-                            }
-                            None => break Err(RecvError::UnknownTarget(pos, del.args)),
+                                .boxed(),
+                            );
                         }
+                        None if self.exports.abandoned.remove(pos) => {
+                            tracing::debug!(pos, "ignoring delivery to abandoned answer");
+                        }
+                        None => self.clone().deliver_to_promise(pos, del.args, None),
                     }
-                },
-                Operation::Deliver(del) => match del.to_desc.position {
-                    0 => {
-                        break Ok(bootstrap_deliver(
-                            self.clone(),
-                            del.args,
-                            del.answer_pos,
-                            del.resolve_me_desc,
-                        ))
-                    }
-                    pos => {
-                        // let del = Delivery::Deliver {
-                        //     to_desc: del.to_desc,
-                        //     args: del.args,
-                        //     resolver: GenericResolver {
-                        //         session: self.clone(),
-                        //         answer_pos: del.answer_pos,
-                        //         resolve_me_desc: del.resolve_me_desc,
-                        //     },
-                        // };
-                        // break Ok(Event::Delivery(del));
-                        match self.exports.exports.get(&pos) {
-                            Some(obj) => {
-                                if let Err(error) = obj
-                                    .deliver(
-                                        self.clone(),
-                                        del.args,
-                                        crate::captp::GenericResolver::new(
-                                            self.clone(),
-                                            del.answer_pos,
-                                            del.resolve_me_desc,
-                                        ),
-                                    )
-                                    .instrument(tracing::info_span!("deliver").or_current())
-                                    .await
-                                {
-                                    tracing::error!(pos, %error, "deliver");
+                }
+                DeliverTarget::Answer(DescAnswer { position }) => {
+                    let Some(answer) = self.exports.answer(position) else {
+                        return Err(RecvError::UnknownAnswer(position, del.args));
+                    };
+                    let session = self.clone();
+                    self.dispatcher.dispatch(
+                        del.to_desc,
+                        async move {
+                            let target = answer.await;
+                            session.deliver_to_answer(target, del.args, None).await;
+                        }
+                        .boxed(),
+                    );
+                }
+            },
+            Operation::Deliver(del) => match del.to_desc {
+                DeliverTarget::Export(DescExport { position: 0 }) => {
+                    return Ok(Some(bootstrap_deliver(
+                        self.clone(),
+                        del.args,
+                        del.answer_pos,
+                        del.resolve_me_desc,
+                    )))
+                }
+                DeliverTarget::Export(DescExport { position: pos }) => {
+                    let obj = self.exports.exports.get(&pos).map(|obj| obj.clone());
+                    match obj {
+                        Some(obj) => {
+                            let resolver = crate::captp::GenericResolver::new(
+                                self.clone(),
+                                del.answer_pos,
+                                del.resolve_me_desc,
+                            );
+                            let session = self.clone();
+                            self.dispatcher.dispatch(
+                                del.to_desc,
+                                async move {
+                                    if let Err(error) = obj
+                                        .deliver(session, del.args, resolver)
+                                        .instrument(tracing::info_span!("deliver").or_current())
+                                        .await
+                                    {
+                                        tracing::error!(pos, %error, "deliver");
+                                    }
                                 }
+                                .boxed(),
+                            );
+                        }
+                        None if self.exports.abandoned.remove(pos) => {
+                            tracing::debug!(pos, "ignoring delivery to abandoned answer");
+                        }
+                        None => {
+                            let resolver = GenericResolver::new(
+                                self.clone(),
+                                del.answer_pos,
+                                del.resolve_me_desc,
+                            );
+                            self.clone()
+                                .deliver_to_promise(pos, del.args, Some(resolver));
+                        }
+                    }
+                }
+                DeliverTarget::Answer(DescAnswer { position }) => {
+                    let Some(answer) = self.exports.answer(position) else {
+                        return Err(RecvError::UnknownAnswer(position, del.args));
+                    };
+                    let resolver =
+                        GenericResolver::new(self.clone(), del.answer_pos, del.resolve_me_desc);
+                    let session = self.clone();
+                    self.dispatcher.dispatch(
+                        del.to_desc,
+                        async move {
+                            let target = answer.await;
+                            session
+                                .deliver_to_answer(target, del.args, Some(resolver))
+                                .await;
+                        }
+                        .boxed(),
+                    );
+                }
+            },
+            Operation::GcExport(crate::captp::msg::OpGcExport {
+                export_position,
+                wire_delta,
+            }) => {
+                tracing::trace!(export_position, wire_delta, "gc export");
+                self.exports.gc_export(export_position, wire_delta);
+            }
+            Operation::GcAnswer(crate::captp::msg::OpGcAnswer { answer_position }) => {
+                tracing::trace!(answer_position, "gc answer");
+                self.exports.gc_answer(answer_position);
+            }
+            Operation::Listen(crate::captp::msg::OpListen {
+                to_desc,
+                listen_desc,
+                ..
+            }) => {
+                let pos = to_desc.position;
+                let listener = GenericResolver::new(self.clone(), None, listen_desc);
+                match self.exports.listen(pos, listener) {
+                    Ok(Some((listener, promise))) => self.dispatcher.dispatch(
+                        to_desc.into(),
+                        async move {
+                            let res = promise.await;
+                            if let Err(error) = crate::captp::object::notify(listener, res).await {
+                                tracing::error!(pos, %error, "listen");
                             }
-                            None => break Err(RecvError::UnknownTarget(pos, del.args)),
                         }
+                        .boxed(),
+                    ),
+                    Ok(None) => {}
+                    // the remote may be listening to a promise it was never sent, or which has
+                    // been collected, which only concerns the listener
+                    Err(listener) => self.dispatcher.dispatch(
+                        to_desc.into(),
+                        async move {
+                            let reason = literal![String; b"unknown promise"];
+                            if let Err(error) = listener.break_promise(reason).await {
+                                tracing::error!(pos, %error, "listen");
+                            }
+                        }
+                        .boxed(),
+                    ),
+                }
+            }
+            Operation::Abort(crate::captp::msg::OpAbort { reason }) => {
+                self.set_remote_abort(reason.clone().into_owned());
+                return Ok(Some(super::Event::Abort(reason.into_owned())));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Queue a delivery to the exported promise at `position`, to be forwarded to whatever it's
+    /// fulfilled with. Deliveries to anything else are logged and dropped, breaking `resolver`.
+    fn deliver_to_promise(
+        self: Arc<Self>,
+        position: u64,
+        args: Sequence<'static>,
+        resolver: Option<GenericResolver>,
+    ) where
+        Reader: Send + 'static,
+        Writer: CapTpMessageWrite + Send + 'static,
+    {
+        let to_desc = DescExport::from(position).into();
+        let promise = self
+            .exports
+            .promises
+            .get(&position)
+            .map(|promise| promise.clone());
+        let Some(promise) = promise else {
+            tracing::warn!(position, ?args, "delivery to unknown target");
+            if let Some(resolver) = resolver {
+                self.dispatcher.dispatch(
+                    to_desc,
+                    async move {
+                        let reason = literal![String; b"unknown target"];
+                        if let Err(error) = resolver.break_promise(reason).await {
+                            tracing::error!(position, %error, "breaking delivery");
+                        }
+                    }
+                    .boxed(),
+                );
+            }
+            return;
+        };
+        let session = self.clone();
+        self.dispatcher.dispatch(
+            to_desc,
+            async move {
+                match promise.await {
+                    Ok(mut values) => {
+                        let target = AnswerTarget::of(values.stream.pop().as_ref());
+                        session.deliver_to_answer(target, args, resolver).await;
+                    }
+                    Err(error) => {
+                        let Some(resolver) = resolver else { return };
+                        let res = crate::captp::object::notify(resolver, Err(error)).await;
+                        if let Err(error) = res {
+                            tracing::error!(position, %error, "breaking delivery");
+                        }
+                    }
+                }
+            }
+            .boxed(),
+        );
+    }
+
+    /// Deliver to whatever a pipelined answer resolved to, breaking `resolver` if it isn't an
+    /// object.
+    async fn deliver_to_answer(
+        self: Arc<Self>,
+        target: AnswerTarget,
+        args: Sequence<'static>,
+        resolver: Option<GenericResolver>,
+    ) where
+        Reader: Send + 'static,
+        Writer: CapTpMessageWrite + Send + 'static,
+    {
+        let reason = match target {
+            AnswerTarget::Local(pos) => match self.exports.exports.get(&pos).map(|obj| obj.clone())
+            {
+                Some(obj) => {
+                    let res = match resolver {
+                        Some(resolver) => {
+                            obj.deliver(self.clone(), args, resolver)
+                                .instrument(tracing::info_span!("deliver").or_current())
+                                .await
+                        }
+                        None => obj.deliver_only(self.clone(), args),
+                    };
+                    if let Err(error) = res {
+                        tracing::error!(pos, %error, "pipelined delivery");
                     }
-                },
-                Operation::Abort(crate::captp::msg::OpAbort { reason }) => {
-                    self.set_remote_abort(reason.clone().into_owned());
-                    break Ok(super::Event::Abort(reason.into_owned()));
+                    return;
                 }
+                None => literal![String; b"answer is no longer exported"],
+            },
+            // the answer is the remote's own object, so it can handle the delivery itself
+            AnswerTarget::Remote(pos) => {
+                let res = match resolver {
+                    Some(resolver) => resolver.forward(pos.into(), args).await,
+                    None => {
+                        self.deliver_only(&OpDeliverOnly::new(DescExport::from(pos).into(), args))
+                            .await
+                    }
+                };
+                if let Err(error) = res {
+                    tracing::error!(pos, %error, "forwarding pipelined delivery");
+                }
+                return;
+            }
+            AnswerTarget::Broken(reason) => reason,
+        };
+        if let Some(resolver) = resolver {
+            if let Err(error) = resolver.break_promise(reason).await {
+                tracing::error!(%error, "breaking pipelined delivery");
             }
         }
     }
@@ -334,3 +839,63 @@ impl<Reader, Writer> CapTpSessionInternal<Reader, Writer> {
     //     ObjectInbox::new(pos, receiver)
     // }
 }
+
+fn bootstrap_deliver_only(mut args: Sequence<'_>) -> super::Event {
+    match args.stream.pop() {
+        Some(TokenTree::Literal(Literal {
+            repr: LiteralValue::Symbol(ident),
+            ..
+        })) => match &*ident {
+            b"deposit-gift" => todo!("bootstrap: deposit-gift"),
+            id => todo!(
+                "unrecognized bootstrap function: {}",
+                String::from_utf8_lossy(id)
+            ),
+        },
+        _ => todo!(),
+    }
+}
+
+fn bootstrap_deliver<Reader, Writer>(
+    session: Arc<CapTpSessionInternal<Reader, Writer>>,
+    mut args: Sequence<'_>,
+    answer_pos: Option<u64>,
+    resolve_me_desc: crate::captp::msg::DescImport,
+) -> super::Event
+where
+    Writer: CapTpMessageWrite + Send + 'static,
+    Reader: Send + 'static,
+{
+    match args.stream.pop() {
+        Some(TokenTree::Literal(Literal {
+            repr: LiteralValue::Symbol(ident),
+            ..
+        })) => match &*ident {
+            b"fetch" => {
+                let swiss = match args.stream.pop() {
+                    Some(TokenTree::Literal(Literal {
+                        repr: LiteralValue::Symbol(swiss),
+                        ..
+                    })) => swiss,
+                    Some(s) => todo!("malformed swiss num: {s:?}"),
+                    None => todo!("missing swiss num"),
+                };
+                super::Event::Bootstrap(crate::captp::BootstrapEvent::Fetch {
+                    resolver: crate::captp::GenericResolver::new(
+                        session,
+                        answer_pos,
+                        resolve_me_desc,
+                    )
+                    .into(),
+                    swiss: swiss.into_owned(),
+                })
+            }
+            b"withdraw-gift" => todo!("bootstrap: withdraw-gift"),
+            id => todo!(
+                "unrecognized bootstrap function: {}",
+                String::from_utf8_lossy(id)
+            ),
+        },
+        _ => todo!(),
+    }
+}
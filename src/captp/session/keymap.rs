@@ -17,6 +17,13 @@ impl<'m, V> Reservation<'m, V> {
         self.map.insert(self.key, value);
         self.key
     }
+
+    /// Finalize the reservation with a value kept in `map` instead, which shares its keys with
+    /// this reservation's map.
+    pub(crate) fn finalize_in<W>(self, map: &DashMap<u64, W>, value: W) -> u64 {
+        map.insert(self.key, value);
+        self.key
+    }
 }
 
 #[derive(Debug, Default)]
@@ -52,6 +59,14 @@ impl<V> KeyMap<V> {
         self.map.remove(&key).map(|(_, v)| v)
     }
 
+    /// Clone every value in the map, so that they can be used without holding any locks.
+    pub(crate) fn values(&self) -> Vec<V>
+    where
+        V: Clone,
+    {
+        self.map.iter().map(|entry| entry.value().clone()).collect()
+    }
+
     #[tracing::instrument(level = tracing::Level::TRACE, skip(self))]
     pub(crate) fn get<'s>(&'s self, key: &u64) -> Option<dashmap::mapref::one::Ref<'s, u64, V>> {
         self.map.get(key)
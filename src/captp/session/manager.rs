@@ -1,63 +1,230 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{any::Any, collections::HashMap, sync::Arc};
 
 use ed25519_dalek::{SigningKey, VerifyingKey};
+use parking_lot::RwLock;
+use rand::rngs::OsRng;
 
 use super::{CapTpSession, CapTpSessionBuilder, CapTpSessionInternal};
-use crate::locator::NodeLocator;
+use crate::{captp::CapTpMessageWrite, locator::NodeLocator};
 
-#[derive(Clone, Default)]
+struct ManagedSession<Reader, Writer> {
+    session: CapTpSession<Reader, Writer>,
+    /// Whether this node started the session
+    outgoing: bool,
+}
+
+/// A session which lost out to another with the same node, and the reason it's aborted with.
+type Discarded<Reader, Writer> = (CapTpSession<Reader, Writer>, &'static str);
+
+struct Sessions<Reader, Writer> {
+    by_key: HashMap<VerifyingKey, ManagedSession<Reader, Writer>>,
+    /// Keys of the nodes reached by dialing each address, as given by [`address`].
+    by_address: HashMap<String, VerifyingKey>,
+}
+
+/// Identify a locator regardless of the order of its hints.
+fn address(locator: &NodeLocator<'_>) -> String {
+    let mut hints = locator
+        .hints
+        .iter()
+        .map(|(key, value)| (&*key.0, &**value))
+        .collect::<Vec<_>>();
+    hints.sort_unstable();
+    format!("{:?}", (&locator.designator, &locator.transport, hints))
+}
+
+/// Tracks the sessions of one node, indexed by the remote node's key.
+///
+/// Every session started through a manager uses the same signing key, so that remote nodes can
+/// recognize this node however they reach it, and vice versa.
 pub struct CapTpSessionManager<Reader, Writer> {
-    sessions: HashMap<String, CapTpSession<Reader, Writer>>,
-    outgoing: HashMap<String, (SigningKey, VerifyingKey)>,
+    signing_key: SigningKey,
+    sessions: RwLock<Sessions<Reader, Writer>>,
 }
 
 impl<Reader, Writer> std::fmt::Debug for CapTpSessionManager<Reader, Writer> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("CapTpSessionManager")
-            .field("sessions", &self.sessions)
-            .field("outgoing", &self.outgoing)
-            .finish()
+            .field("local_vkey", &crate::hash(&self.signing_key.verifying_key()))
+            .field("addresses", &self.sessions.read().by_address.keys())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Reader, Writer> Default for CapTpSessionManager<Reader, Writer> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl<Reader, Writer> CapTpSessionManager<Reader, Writer> {
     pub fn new() -> Self {
+        Self::with_signing_key(SigningKey::generate(&mut OsRng))
+    }
+
+    pub fn with_signing_key(signing_key: SigningKey) -> Self {
         Self {
-            sessions: HashMap::new(),
-            outgoing: HashMap::new(),
+            signing_key,
+            sessions: RwLock::new(Sessions {
+                by_key: HashMap::new(),
+                by_address: HashMap::new(),
+            }),
         }
     }
 
-    pub fn get(&self, designator: impl AsRef<str>) -> Option<&CapTpSession<Reader, Writer>> {
-        self.sessions.get(designator.as_ref())
+    pub fn signing_key(&self) -> &SigningKey {
+        &self.signing_key
+    }
+
+    /// Get the live session with the node reached by dialing `locator`, as recorded by
+    /// [`Self::alias`].
+    pub fn get(&self, locator: &NodeLocator<'_>) -> Option<CapTpSession<Reader, Writer>> {
+        let sessions = self.sessions.read();
+        let key = sessions.by_address.get(&address(locator))?;
+        sessions
+            .by_key
+            .get(key)
+            .filter(|s| !s.session.is_aborted())
+            .map(|s| s.session.clone())
+    }
+
+    /// Get the live session with the node whose key is `remote_vkey`.
+    pub fn get_by_key(&self, remote_vkey: &VerifyingKey) -> Option<CapTpSession<Reader, Writer>> {
+        self.sessions
+            .read()
+            .by_key
+            .get(remote_vkey)
+            .filter(|s| !s.session.is_aborted())
+            .map(|s| s.session.clone())
+    }
+
+    /// Record that dialing `locator` reached the node whose key is `remote_vkey`.
+    ///
+    /// Netlayers should only alias locators they dialed themselves: the locator a remote node sends
+    /// when starting a session is only its own claim.
+    pub fn alias(&self, locator: &NodeLocator<'_>, remote_vkey: VerifyingKey) {
+        self.sessions
+            .write()
+            .by_address
+            .insert(address(locator), remote_vkey);
+    }
+
+    /// Remove every session from this manager.
+    pub fn drain(&self) -> Vec<CapTpSession<Reader, Writer>> {
+        let mut sessions = self.sessions.write();
+        sessions.by_address.clear();
+        sessions.by_key.drain().map(|(_, s)| s.session).collect()
     }
 
     pub fn init_session(
-        &mut self,
+        &self,
         reader: Reader,
         writer: Writer,
     ) -> CapTpSessionBuilder<'_, Reader, Writer> {
         CapTpSessionBuilder::new(self, reader, writer)
     }
 
-    pub(super) fn finalize_session(
-        &mut self,
+    /// Store a new session, replacing any existing session with the same node unless the existing
+    /// one should be kept instead.
+    ///
+    /// If both sessions were started by the same node, the remote has reconnected, so the new
+    /// session replaces the existing one. Otherwise, the nodes tried to connect to each other at
+    /// the same time (crossed hellos), and the session started by the node with the greater key is
+    /// kept. The remote node reaches the same decision on its end. The discarded session is
+    /// aborted.
+    pub(super) async fn finalize_session(
+        &self,
         reader: Reader,
         writer: Writer,
-        signing_key: SigningKey,
+        peer_info: Option<Box<dyn Any + Send + Sync>>,
         remote_vkey: VerifyingKey,
         remote_loc: NodeLocator<'static>,
-    ) -> CapTpSession<Reader, Writer> {
-        let designator = remote_loc.designator.clone().into_owned();
+        outgoing: bool,
+    ) -> CapTpSession<Reader, Writer>
+    where
+        Writer: CapTpMessageWrite + Send + 'static,
+    {
         let internal = Arc::new(CapTpSessionInternal::new(
             reader.into(),
-            writer.into(),
-            signing_key,
+            writer,
+            self.signing_key.clone(),
+            peer_info,
             remote_vkey,
             remote_loc,
         ));
         let res = CapTpSession { base: internal };
-        self.sessions.insert(designator, res.clone());
-        res
+
+        let (kept, discarded) = self.keep_session(res, remote_vkey, outgoing);
+        if let Some((discarded, reason)) = discarded {
+            // the connection may already be gone, in which case there's no one to tell
+            if let Err(error) = discarded.abort(reason).await {
+                tracing::debug!(
+                    remote = ?crate::hash(&remote_vkey),
+                    %error,
+                    "failed to abort discarded session"
+                );
+            }
+        }
+        kept
+    }
+
+    /// Decide whether to keep `res` or an existing session with the same node, as described by
+    /// [`Self::finalize_session`], returning the kept session and the session to abort, with why.
+    fn keep_session(
+        &self,
+        res: CapTpSession<Reader, Writer>,
+        remote_vkey: VerifyingKey,
+        outgoing: bool,
+    ) -> (
+        CapTpSession<Reader, Writer>,
+        Option<Discarded<Reader, Writer>>,
+    ) {
+        let mut sessions = self.sessions.write();
+        let discarded = match sessions.by_key.get(&remote_vkey) {
+            Some(existing) if !existing.session.is_aborted() => {
+                let local_vkey = self.signing_key.verifying_key();
+                let starter = |outgoing: bool| {
+                    if outgoing {
+                        local_vkey.to_bytes()
+                    } else {
+                        remote_vkey.to_bytes()
+                    }
+                };
+                if existing.outgoing == outgoing {
+                    tracing::debug!(
+                        remote = ?crate::hash(&remote_vkey),
+                        outgoing,
+                        "replacing existing session after reconnect"
+                    );
+                    Some((existing.session.clone(), "replaced by a new session"))
+                } else if starter(existing.outgoing) > starter(outgoing) {
+                    tracing::debug!(
+                        remote = ?crate::hash(&remote_vkey),
+                        outgoing,
+                        "keeping existing session after crossed hellos"
+                    );
+                    return (
+                        existing.session.clone(),
+                        Some((res, "crossed hellos mitigated")),
+                    );
+                } else {
+                    tracing::debug!(
+                        remote = ?crate::hash(&remote_vkey),
+                        outgoing,
+                        "replacing existing session after crossed hellos"
+                    );
+                    Some((existing.session.clone(), "crossed hellos mitigated"))
+                }
+            }
+            _ => None,
+        };
+        sessions.by_key.insert(
+            remote_vkey,
+            ManagedSession {
+                session: res.clone(),
+                outgoing,
+            },
+        );
+        (res, discarded)
     }
 }
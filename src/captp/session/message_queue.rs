@@ -1,3 +1,142 @@
-// pub struct CapTpMessageQueue<Socket> {}
-//
-// impl<Socket> CapTpMessageQueue<Socket> {}
+use std::collections::VecDeque;
+
+use futures::{future::BoxFuture, lock::Mutex};
+use syrup::TokenTree;
+
+use super::SendError;
+use crate::captp::CapTpMessageWrite;
+
+/// Outgoing messages, written in the order in which they were queued.
+///
+/// Messages are queued synchronously when a send is started, so sends started in program order are
+/// written in that order regardless of the order in which their futures are polled. Whichever send
+/// holds the writer writes every message queued before its own.
+///
+/// Each write owns the writer until it completes, so a flush which is dropped mid-write leaves the
+/// write to be finished by the next flush rather than abandoning a partially written message.
+pub(crate) struct MessageQueue<Writer> {
+    state: parking_lot::Mutex<QueueState<Writer>>,
+    writer: Mutex<WriterState<Writer>>,
+}
+
+/// Writes a prepared message, returning the writer once it's done.
+type WriteFuture<Writer> = BoxFuture<'static, (Writer, std::io::Result<()>)>;
+type QueuedWrite<Writer> = Box<dyn FnOnce(Writer) -> WriteFuture<Writer> + Send>;
+
+struct QueueState<Writer> {
+    queued: VecDeque<(u64, QueuedWrite<Writer>)>,
+    next_ticket: u64,
+    /// Every message with a lower ticket has been written.
+    written: u64,
+    /// Set once a write fails, after which no further messages are written.
+    failure: Option<(std::io::ErrorKind, String)>,
+}
+
+impl<Writer> Default for QueueState<Writer> {
+    fn default() -> Self {
+        Self {
+            queued: VecDeque::new(),
+            next_ticket: 0,
+            written: 0,
+            failure: None,
+        }
+    }
+}
+
+enum WriterState<Writer> {
+    Idle(Writer),
+    /// Writing the message with the given ticket.
+    Writing(u64, WriteFuture<Writer>),
+    /// Only while a write is being started, or if starting one panicked.
+    Poisoned,
+}
+
+impl<Writer> MessageQueue<Writer> {
+    pub(crate) fn new(writer: Writer) -> Self {
+        Self {
+            state: parking_lot::Mutex::default(),
+            writer: Mutex::new(WriterState::Idle(writer)),
+        }
+    }
+
+    /// Queue a message, returning its ticket.
+    ///
+    /// The message is [prepared](CapTpMessageWrite::prepare_message) immediately, so that it isn't
+    /// borrowed while it waits to be written.
+    pub(crate) fn push(&self, msg: &TokenTree<'_>) -> u64
+    where
+        Writer: CapTpMessageWrite + Send + 'static,
+    {
+        let msg = Writer::prepare_message(msg);
+        let write: QueuedWrite<Writer> = Box::new(move |mut writer: Writer| {
+            Box::pin(async move {
+                let res = writer.write_message(msg).await;
+                (writer, res)
+            })
+        });
+        let mut state = self.state.lock();
+        let ticket = state.next_ticket;
+        state.next_ticket += 1;
+        state.queued.push_back((ticket, write));
+        ticket
+    }
+
+    fn fail(&self, error: std::io::Error) -> SendError {
+        self.state
+            .lock()
+            .failure
+            .get_or_insert_with(|| (error.kind(), error.to_string()));
+        error.into()
+    }
+
+    /// Write queued messages until the message with `ticket` has been written.
+    ///
+    /// If this future is dropped before then, the message remains queued, or in the middle of being
+    /// written, and is finished by the next flush.
+    pub(crate) async fn flush(&self, ticket: u64) -> Result<(), SendError>
+    where
+        Writer: CapTpMessageWrite + Send + 'static,
+    {
+        let mut writer = self.writer.lock().await;
+        loop {
+            // finish the write in progress, which may have been started by a dropped flush
+            if let WriterState::Writing(writing, write) = &mut *writer {
+                let writing = *writing;
+                let (idle, res) = write.await;
+                *writer = WriterState::Idle(idle);
+                match res {
+                    Ok(()) => self.state.lock().written = writing + 1,
+                    Err(error) => return Err(self.fail(error)),
+                }
+            }
+
+            let (next, write) = {
+                let mut state = self.state.lock();
+                if let Some((kind, error)) = &state.failure {
+                    return Err(std::io::Error::new(*kind, error.clone()).into());
+                }
+                if state.written > ticket {
+                    break;
+                }
+                match state.queued.pop_front() {
+                    Some(next) => next,
+                    None => return Err(SendError::MessageLost(ticket)),
+                }
+            };
+            let idle = match std::mem::replace(&mut *writer, WriterState::Poisoned) {
+                WriterState::Idle(idle) => idle,
+                WriterState::Writing(..) | WriterState::Poisoned => {
+                    return Err(SendError::MessageLost(ticket))
+                }
+            };
+            *writer = WriterState::Writing(next, write(idle));
+        }
+        let WriterState::Idle(idle) = &mut *writer else {
+            return Err(SendError::MessageLost(ticket));
+        };
+        // writers which buffer or frame their output need to be flushed for it to be sent
+        idle.flush_messages()
+            .await
+            .map_err(|error| self.fail(error))
+    }
+}
@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use syrup::{literal, sequence, symbol, Encode, Sequence, TokenTree};
 
-use super::CapTpDeliver;
+use super::{AnswerTarget, CapTpDeliver};
 use crate::captp::{
     msg::{DescExport, DescImport, DescImportObject, OpDeliver, OpDeliverOnly},
     object::DeliverError,
@@ -61,6 +61,19 @@ impl GenericResolver {
         }
     }
 
+    /// Record what the answer resolved to, if the remote may pipeline deliveries on it.
+    fn resolve_answer(&self, target: impl FnOnce() -> AnswerTarget) {
+        if let Some(answer_pos) = self.answer_pos {
+            self.session.exports().resolve_answer(answer_pos, target());
+        }
+    }
+
+    /// Record what the answer resolved to from the value it's fulfilled with, the first of `args`.
+    fn resolve_answer_with(&self, args: &Sequence<'_>) {
+        // take the value from a copy, so that `args` is sent exactly as given
+        self.resolve_answer(|| AnswerTarget::of(args.clone().stream.pop().as_ref()));
+    }
+
     pub async fn fulfill<'args>(
         mut self,
         mut args: Sequence<'args>,
@@ -72,11 +85,12 @@ impl GenericResolver {
             self.resolved = true;
         }
 
+        self.resolve_answer_with(&args);
         args.stream.insert(0, literal![Symbol; b"fulfill"]);
 
         self.session
             .deliver(&OpDeliver::new(
-                self.position(),
+                self.position().into(),
                 args,
                 answer_pos,
                 resolve_me_desc,
@@ -93,9 +107,12 @@ impl GenericResolver {
             self.resolved = true;
         }
 
+        self.resolve_answer_with(&args);
         args.stream.insert(0, literal![Symbol; b"fulfill"]);
 
-        self.session.deliver_and(self.position(), args).await
+        self.session
+            .deliver_and(self.position().into(), args, self.session.default_timeout())
+            .await
     }
 
     pub async fn break_promise<'error>(
@@ -106,13 +123,39 @@ impl GenericResolver {
         {
             self.resolved = true;
         }
+        self.resolve_answer(|| AnswerTarget::Broken(error.clone().into_owned()));
         self.session
             .deliver_only(&OpDeliverOnly::new(
-                self.position(),
+                self.position().into(),
                 sequence![symbol!["break"], error],
             ))
             .await
     }
+
+    /// Have the remote resolve this itself, by delivering `args` to its own object at `to_desc`.
+    ///
+    /// Deliveries pipelined on this answer are broken, since its result is never seen here.
+    pub(super) async fn forward(
+        mut self,
+        to_desc: DescExport,
+        args: Sequence<'_>,
+    ) -> Result<(), SendError> {
+        #[cfg(feature = "extra-diagnostics")]
+        {
+            self.resolved = true;
+        }
+        self.resolve_answer(|| {
+            AnswerTarget::Broken(literal![String; b"answer was forwarded to its sender"])
+        });
+        self.session
+            .deliver(&OpDeliver::new(
+                to_desc.into(),
+                args,
+                None,
+                self.resolve_me_desc,
+            ))
+            .await
+    }
 }
 
 #[must_use]
@@ -143,6 +186,9 @@ impl FetchResolver {
         answer_pos: Option<u64>,
         resolve_me_desc: DescImport,
     ) -> Result<(), SendError> {
+        // the fetched object is exported by this side, whatever descriptor it's sent as
+        self.base
+            .resolve_answer(|| AnswerTarget::Local(position.position));
         self.base
             .fulfill(sequence![position], answer_pos, resolve_me_desc)
             .await
@@ -152,6 +198,8 @@ impl FetchResolver {
         self,
         position: DescExport,
     ) -> Result<Sequence<'static>, DeliverError<'static>> {
+        self.base
+            .resolve_answer(|| AnswerTarget::Local(position.position));
         self.base.fulfill_and(sequence![position]).await
     }
 
@@ -1,20 +1,18 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use ed25519_dalek::{SigningKey, VerifyingKey};
-use futures::future::BoxFuture;
+use futures::future::{select, BoxFuture, Either};
 use futures::FutureExt;
+use futures_timer::Delay;
 use syrup::{de::Sequence, Encode};
 
-use super::{CapTpSessionInternal, Event, RecvError, RemoteKey, SendError};
-use crate::captp::object::{DeliverError, RemoteBootstrap, RemoteObject, Resolver};
+use super::{AnswerGuard, CapTpSessionInternal, Event, ImportKind, RecvError, RemoteKey, SendError};
+use crate::captp::object::{DeliverError, Promise, RemoteBootstrap, RemoteObject, Resolver};
 use crate::captp::{msg::DescImport, ExportManager};
 use crate::captp::{
-    msg::{DescExport, OpAbort, OpDeliver, OpDeliverOnly},
-    CapTpReadExt,
-};
-use crate::{
-    async_compat::{AsyncRead, AsyncWrite},
-    captp::object::Object,
+    msg::{DeliverTarget, DescExport, OpAbort, OpDeliver, OpDeliverOnly, OpGcAnswer},
+    object::Object,
+    CapTpMessageRead, CapTpMessageWrite,
 };
 
 pub trait IntoExport {
@@ -35,26 +33,62 @@ impl IntoExport for Arc<dyn Object + Send + Sync + 'static> {
     }
 }
 
+/// Sends deliveries over a session.
+///
+/// # Ordering
+///
+/// Messages are queued when these functions are called, rather than when their futures are first
+/// polled, and are written in the order in which they were queued. So, deliveries made in program
+/// order through one session (and so through any one [`RemoteObject`]) arrive in that order, even
+/// when their futures are awaited concurrently or out of order. On the receiving side, deliveries
+/// to each export or answer are handled in the order in which they arrived.
 pub trait CapTpDeliver {
     fn exports(&self) -> &ExportManager;
     fn deliver_only<'f>(
         &'f self,
-        delivery: &'f OpDeliverOnly<'f>,
+        delivery: &OpDeliverOnly<'_>,
     ) -> futures::future::BoxFuture<'f, Result<(), SendError>>;
     fn deliver<'f>(
         &'f self,
-        delivery: &'f OpDeliver<'f>,
+        delivery: &OpDeliver<'_>,
     ) -> futures::future::BoxFuture<'f, Result<(), SendError>>;
+    /// Send a delivery and await its answer, failing with [`DeliverError::Timeout`] if it takes
+    /// longer than `timeout`.
+    ///
+    /// The answer's resolver is unexported once the answer arrives, times out, or the returned
+    /// future is dropped.
     fn deliver_and<'f>(
         &'f self,
-        to_desc: DescExport,
-        args: Sequence<'f>,
+        to_desc: DeliverTarget,
+        args: Sequence<'_>,
+        timeout: Option<Duration>,
     ) -> futures::future::BoxFuture<'f, Result<Sequence<'static>, DeliverError<'static>>>;
+    /// Send a delivery, returning a promise for its answer.
+    ///
+    /// If `answer_pos` is given, the remote also keeps the answer at that position, from
+    /// [`CapTpDeliver::new_question`], so that it can be delivered to before it resolves.
+    fn deliver_promise<'f>(
+        &'f self,
+        to_desc: DeliverTarget,
+        args: Sequence<'_>,
+        answer_pos: Option<u64>,
+    ) -> futures::future::BoxFuture<'f, Result<Promise, SendError>>;
+    /// Allocate an answer position, which must be released with [`CapTpDeliver::gc_answer`].
+    fn new_question(&self) -> u64;
+    /// Release an answer position, telling the remote that it can forget the answer.
+    fn gc_answer(self: Arc<Self>, position: u64);
     fn into_remote_object(self: Arc<Self>, position: DescExport) -> Option<RemoteObject>;
+    /// # Safety
+    /// - An object must already be exported at `position`.
+    #[deprecated(note = "imports are recorded as they're received; use `into_remote_object`")]
     #[allow(unsafe_code)]
     unsafe fn into_remote_object_unchecked(self: Arc<Self>, position: DescExport) -> RemoteObject;
+    /// Get the object referred to by an import descriptor received from the remote.
+    fn import(self: Arc<Self>, desc: DescImport) -> RemoteObject;
 
     fn remote_vkey(&self) -> RemoteKey;
+    /// The timeout used for calls which don't specify their own.
+    fn default_timeout(&self) -> Option<Duration>;
 }
 
 /// Allows dynamic dispatch for `CapTpSession`s.
@@ -68,8 +102,11 @@ pub trait AbstractCapTpSession {
     fn into_remote_object(self: Arc<Self>, position: DescExport) -> Option<RemoteObject>;
     /// # Safety
     /// - An object must already be exported at `position`.
+    #[deprecated(note = "imports are recorded as they're received; use `into_remote_object`")]
     #[allow(unsafe_code)]
     unsafe fn into_remote_object_unchecked(self: Arc<Self>, position: DescExport) -> RemoteObject;
+    /// Get the object referred to by an import descriptor received from the remote.
+    fn import(self: Arc<Self>, desc: DescImport) -> RemoteObject;
     fn is_aborted(&self) -> bool;
     fn abort<'result>(
         &'result self,
@@ -82,7 +119,7 @@ pub trait AbstractCapTpSession {
 impl<Reader, Writer> CapTpDeliver for CapTpSessionInternal<Reader, Writer>
 where
     Reader: Send + 'static,
-    Writer: AsyncWrite + Send + Unpin + 'static,
+    Writer: CapTpMessageWrite + Send + 'static,
 {
     fn exports(&self) -> &ExportManager {
         &self.exports
@@ -90,35 +127,111 @@ where
 
     fn deliver_only<'f>(
         &'f self,
-        delivery: &'f OpDeliverOnly<'f>,
+        delivery: &OpDeliverOnly<'_>,
     ) -> futures::future::BoxFuture<'f, Result<(), SendError>> {
-        async move { self.send_msg(&delivery.to_tokens()).await }.boxed()
+        let queued = self
+            .check_target(delivery.to_desc)
+            .and_then(|_| self.queue_msg(&delivery.to_tokens()));
+        async move { self.flush(queued?).await }.boxed()
     }
 
     fn deliver<'f>(
         &'f self,
-        delivery: &'f OpDeliver<'f>,
+        delivery: &OpDeliver<'_>,
     ) -> futures::future::BoxFuture<'f, Result<(), SendError>> {
-        async move { self.send_msg(&delivery.to_tokens()).await }.boxed()
+        let queued = self
+            .check_target(delivery.to_desc)
+            .and_then(|_| self.queue_msg(&delivery.to_tokens()));
+        async move { self.flush(queued?).await }.boxed()
     }
 
     fn deliver_and<'f>(
         &'f self,
-        to_desc: DescExport,
-        args: Sequence<'f>,
+        to_desc: DeliverTarget,
+        args: Sequence<'_>,
+        timeout: Option<Duration>,
     ) -> futures::future::BoxFuture<'f, Result<Sequence<'static>, DeliverError<'static>>> {
         let (resolver, answer) = Resolver::new();
         let pos = self.exports.export_object(resolver);
+        let mut guard = AnswerGuard::new(&self.exports, pos.position);
+        let sent = self.deliver(&OpDeliver::new(to_desc, args, None, pos.into()));
         async move {
-            self.deliver(&OpDeliver::new(to_desc, args, None, pos.into()))
-                .await?;
-            answer.await?.map_err(DeliverError::Broken)
+            // the timeout only applies to the answer, so that the delivery isn't cut off mid-write
+            sent.await?;
+            let answer = std::pin::pin!(answer);
+            let res = match timeout {
+                Some(after) => match select(answer, Delay::new(after)).await {
+                    Either::Left((res, _)) => res,
+                    Either::Right(_) => return Err(DeliverError::Timeout { to_desc, after }),
+                },
+                None => answer.await,
+            }?;
+            guard.answered();
+            res.map_err(DeliverError::Broken)
         }
         .boxed()
     }
 
+    fn deliver_promise<'f>(
+        &'f self,
+        to_desc: DeliverTarget,
+        args: Sequence<'_>,
+        answer_pos: Option<u64>,
+    ) -> futures::future::BoxFuture<'f, Result<Promise, SendError>> {
+        let (resolver, answer) = Resolver::new();
+        let pos = self.exports.export_object(resolver);
+        let sent = self.deliver(&OpDeliver::new(to_desc, args, answer_pos, pos.into()));
+        async move {
+            sent.await?;
+            Ok(answer.into())
+        }
+        .boxed()
+    }
+
+    fn new_question(&self) -> u64 {
+        let position = self
+            .next_question
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        self.questions.insert(position);
+        position
+    }
+
+    fn gc_answer(self: Arc<Self>, position: u64) {
+        if self.questions.remove(&position).is_none() {
+            return;
+        }
+        let ticket = match self.queue_msg(
+            &OpGcAnswer {
+                answer_position: position,
+            }
+            .to_tokens(),
+        ) {
+            Ok(ticket) => ticket,
+            Err(error) => {
+                tracing::debug!(position, %error, "failed to send gc-answer");
+                return;
+            }
+        };
+        let flush = async move {
+            if let Err(error) = self.flush(ticket).await {
+                tracing::debug!(position, %error, "failed to send gc-answer");
+            }
+        };
+
+        // this is called on drop, so the flush can only be awaited by a task of its own
+        #[cfg(feature = "tokio")]
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(flush);
+            return;
+        }
+        // otherwise the queue keeps a write which can't finish immediately for the next flush
+        if flush.now_or_never().is_none() {
+            tracing::trace!(position, "gc-answer left queued for the next flush");
+        }
+    }
+
     fn into_remote_object(self: Arc<Self>, position: DescExport) -> Option<RemoteObject> {
-        if position.position != 0 && !self.imports.contains(&position.position) {
+        if self.check_import(position.position).is_err() {
             None
         } else {
             Some(RemoteObject::new(self.clone(), position))
@@ -127,18 +240,31 @@ where
 
     #[allow(unsafe_code)]
     unsafe fn into_remote_object_unchecked(self: Arc<Self>, position: DescExport) -> RemoteObject {
+        // record the position so that deliveries to it aren't rejected
+        self.imports
+            .entry(position.position)
+            .or_insert(ImportKind::Object);
         RemoteObject::new(self.clone(), position)
     }
 
+    fn import(self: Arc<Self>, desc: DescImport) -> RemoteObject {
+        self.record_import(&desc);
+        RemoteObject::new(self, desc.position().into())
+    }
+
     fn remote_vkey(&self) -> RemoteKey {
         self.remote_vkey
     }
+
+    fn default_timeout(&self) -> Option<Duration> {
+        *self.default_timeout.read().unwrap()
+    }
 }
 
 impl<Reader, Writer> AbstractCapTpSession for CapTpSessionInternal<Reader, Writer>
 where
-    Reader: CapTpReadExt + Send + 'static,
-    Writer: AsyncWrite + Send + Unpin + 'static,
+    Reader: CapTpMessageRead + Send + 'static,
+    Writer: CapTpMessageWrite + Send + 'static,
 {
     fn signing_key(&self) -> &SigningKey {
         &self.signing_key
@@ -157,11 +283,15 @@ where
         <Self as CapTpDeliver>::into_remote_object(self, position)
     }
 
-    #[allow(unsafe_code)]
+    #[allow(unsafe_code, deprecated)]
     unsafe fn into_remote_object_unchecked(self: Arc<Self>, position: DescExport) -> RemoteObject {
         unsafe { <Self as CapTpDeliver>::into_remote_object_unchecked(self, position) }
     }
 
+    fn import(self: Arc<Self>, desc: DescImport) -> RemoteObject {
+        <Self as CapTpDeliver>::import(self, desc)
+    }
+
     fn is_aborted(&self) -> bool {
         self.is_aborted()
     }
@@ -169,7 +299,7 @@ where
     fn abort<'f>(&'f self, reason: &'f OpAbort<'f>) -> BoxFuture<'f, Result<(), SendError>> {
         async move {
             let res = self.send_msg(&reason.to_tokens()).await;
-            self.local_abort();
+            self.local_abort(&reason.reason);
             res
         }
         .boxed()
@@ -0,0 +1,42 @@
+use rexa::{
+    captp::{msg::DescImport, BootstrapEvent, CapTpSession, Event, IntoExport},
+    netlayer::Netlayer,
+};
+use rexa_netlayer_mock::{MockNetlayer, MockNetwork};
+
+use super::netlayers::BoxError;
+
+pub(crate) type Session =
+    CapTpSession<<MockNetlayer as Netlayer>::Reader, <MockNetlayer as Netlayer>::Writer>;
+
+/// Connect two mock nodes on a network of their own.
+#[allow(dead_code)]
+pub(crate) async fn connected_pair() -> Result<(Session, Session), BoxError> {
+    let network = MockNetwork::new();
+    let node_a = network.bind("a")?;
+    let node_b = network.bind("b")?;
+    let locator_b = node_b.locators().pop().unwrap();
+
+    let (session_ab, session_ba) = tokio::join!(node_a.connect(&locator_b), node_b.accept());
+    Ok((session_ab?, session_ba?))
+}
+
+/// Handle events on `session` until it's aborted, answering every fetch with the result of
+/// `export`.
+#[allow(dead_code)]
+pub(crate) async fn serve<Obj: IntoExport>(
+    session: Session,
+    export: impl Fn() -> Obj,
+) -> Result<(), BoxError> {
+    loop {
+        match session.recv_event().await? {
+            Event::Bootstrap(BootstrapEvent::Fetch { resolver, .. }) => {
+                let pos = session.export_object(export());
+                resolver
+                    .fulfill(pos.position.into(), None, DescImport::default())
+                    .await?;
+            }
+            Event::Abort(_) => break Ok(()),
+        }
+    }
+}
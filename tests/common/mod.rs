@@ -1,3 +1,4 @@
+pub(crate) mod mock;
 pub(crate) mod netlayers;
 
 #[allow(dead_code)]
@@ -9,6 +10,7 @@ pub(crate) enum LogFormat {
     Json,
 }
 
+#[allow(dead_code)]
 pub(crate) fn initialize_tracing(
     log_format: LogFormat,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
@@ -2,6 +2,7 @@ use rexa::netlayer::Netlayer;
 
 pub(crate) type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
 
+#[allow(dead_code)]
 pub(crate) trait NlFuture<Nl>: std::future::Future<Output = Result<Nl, BoxError>> {}
 impl<Nl, F: std::future::Future<Output = Result<Nl, BoxError>>> NlFuture<Nl> for F {}
 
@@ -41,7 +42,7 @@ pub(crate) async fn make_tcp_netlayer(
 }
 
 #[allow(dead_code)]
-#[cfg(target_family = "unix")]
+#[cfg(all(target_family = "unix", feature = "test-deps"))]
 pub(crate) async fn mktemp(
     dir: bool,
     prefix: impl AsRef<str>,
@@ -88,19 +89,18 @@ pub(crate) async fn make_unix_netlayer(
     test_name: &'static str,
     index: usize,
 ) -> Result<rexa::netlayer::datastream::UnixNetlayer, BoxError> {
-    let addr = if cfg!(target_os = "linux") {
-        // use std::os::linux::net::SocketAddrExt;
-        // FIX :: tokio unix socketaddr does not support as_abstract_namespace
-        // std::os::unix::net::SocketAddr::from_abstract_name(format!("rexa-{test_name}-{index}"))?
-        //format!("rexa-{test_name}-{index}.XXXXXXXXX")
-        std::os::unix::net::SocketAddr::from_pathname(
-            mktemp(false, format!("rexa-{test_name}.socket.{index}")).await?,
-        )?
-    } else {
-        std::os::unix::net::SocketAddr::from_pathname(
-            mktemp(false, format!("rexa-{test_name}.socket.{index}")).await?,
-        )?
+    #[cfg(target_os = "linux")]
+    let addr = {
+        use std::os::linux::net::SocketAddrExt;
+        std::os::unix::net::SocketAddr::from_abstract_name(format!(
+            "rexa-{test_name}-{index}-{}",
+            std::process::id()
+        ))?
     };
+    #[cfg(not(target_os = "linux"))]
+    let addr = std::os::unix::net::SocketAddr::from_pathname(
+        mktemp(false, format!("rexa-{test_name}.socket.{index}")).await?,
+    )?;
     rexa::netlayer::datastream::UnixNetlayer::bind(&addr)
         .await
         .map_err(From::from)
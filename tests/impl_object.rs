@@ -0,0 +1,213 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use common::{
+    mock::{connected_pair, serve},
+    netlayers::BoxError,
+};
+use rexa::captp::object::{
+    DeliverError, ErrorRecord, FromKeywords, KeywordError, Keywords, ObjectError, RemoteObject,
+};
+use syrup::{de::Sequence, symbol, Decode, Symbol};
+
+mod common;
+
+#[test]
+fn impl_object() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/impl_object/pass/*.rs");
+    t.compile_fail("tests/ui/impl_object/fail/*.rs");
+}
+
+struct Options {
+    step: u64,
+    limit: Option<u64>,
+}
+
+impl FromKeywords for Options {
+    fn from_keywords(keywords: &mut Keywords) -> Result<Self, KeywordError> {
+        Ok(Self {
+            step: keywords.require("step")?,
+            limit: keywords.take("limit")?,
+        })
+    }
+}
+
+#[derive(Default)]
+struct Counter {
+    count: AtomicU64,
+    step: AtomicU64,
+    limit: AtomicU64,
+}
+
+#[rexa::impl_object]
+impl Counter {
+    #[deliver()]
+    async fn add(&self, amount: u64) -> Result<u64, ObjectError> {
+        Ok(self.count.fetch_add(amount, Ordering::Relaxed) + amount)
+    }
+
+    #[deliver(symbol = "add-all")]
+    async fn add_all(&self, #[arg(rest)] amounts: Vec<u64>) -> Result<u64, ObjectError> {
+        self.add(amounts.into_iter().sum()).await
+    }
+
+    #[deliver()]
+    async fn step(&self, times: Option<u64>) -> Result<u64, ObjectError> {
+        self.add(self.step.load(Ordering::Relaxed) * times.unwrap_or(1))
+            .await
+    }
+
+    #[deliver()]
+    async fn configure(&self, #[arg(dict)] options: Options) -> Result<u64, ObjectError> {
+        self.step.store(options.step, Ordering::Relaxed);
+        self.limit
+            .store(options.limit.unwrap_or(u64::MAX), Ordering::Relaxed);
+        Ok(self.limit.load(Ordering::Relaxed))
+    }
+}
+
+/// Connect two mock nodes, answering every fetch on the second with a new [`Counter`], and fetch
+/// one from the first.
+async fn counter() -> Result<RemoteObject, BoxError> {
+    let (session_ab, session_ba) = connected_pair().await?;
+    let events_ab = session_ab.clone();
+    tokio::spawn(async move { while events_ab.recv_event().await.is_ok() {} });
+    tokio::spawn(serve(session_ba, || Arc::new(Counter::default())));
+
+    Ok(session_ab.get_remote_bootstrap().fetch(b"counter").await?)
+}
+
+async fn call(counter: &RemoteObject, args: Sequence<'_>) -> Result<u64, BoxError> {
+    let mut res = counter.deliver_and(args).await?;
+    Ok(u64::decode(res.stream.pop().ok_or("empty answer")?)?)
+}
+
+/// Call `counter`, expecting the promise to be broken with an error record.
+async fn call_err(
+    counter: &RemoteObject,
+    args: Sequence<'_>,
+) -> Result<ErrorRecord<'static>, BoxError> {
+    match counter.deliver_and(args).await {
+        Err(DeliverError::Broken(reason)) => Ok(ErrorRecord::decode(reason)?),
+        res => Err(format!("expected broken promise, found {res:?}").into()),
+    }
+}
+
+fn keywords<const N: usize>(entries: [(&'static str, u64); N]) -> HashMap<Symbol<'static>, u64> {
+    entries
+        .into_iter()
+        .map(|(key, value)| (Symbol(Cow::Borrowed(key)), value))
+        .collect()
+}
+
+#[tokio::test]
+async fn dispatch() -> Result<(), BoxError> {
+    let counter = counter().await?;
+    assert_eq!(
+        call(&counter, syrup::sequence![symbol!["add"], 2u64]).await?,
+        2
+    );
+    assert_eq!(
+        call(
+            &counter,
+            syrup::sequence![symbol!["add-all"], 1u64, 2u64, 3u64]
+        )
+        .await?,
+        8
+    );
+    assert_eq!(
+        call(&counter, syrup::sequence![symbol!["add-all"]]).await?,
+        8
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn optional_and_keywords() -> Result<(), BoxError> {
+    let counter = counter().await?;
+    assert_eq!(
+        call(
+            &counter,
+            syrup::sequence![symbol!["configure"], keywords([("step", 5)])]
+        )
+        .await?,
+        u64::MAX
+    );
+    assert_eq!(
+        call(
+            &counter,
+            syrup::sequence![symbol!["configure"], keywords([("step", 2), ("limit", 10)])]
+        )
+        .await?,
+        10
+    );
+    assert_eq!(call(&counter, syrup::sequence![symbol!["step"]]).await?, 2);
+    assert_eq!(
+        call(&counter, syrup::sequence![symbol!["step"], 3u64]).await?,
+        8
+    );
+    Ok(())
+}
+
+/// Deliveries which don't match a method break the promise, describing what was wrong.
+#[tokio::test]
+async fn invalid_deliveries() -> Result<(), BoxError> {
+    let counter = counter().await?;
+
+    let record = call_err(&counter, syrup::sequence![symbol!["frobnicate"]]).await?;
+    assert_eq!(record.method.as_deref(), Some("frobnicate"));
+
+    let record = call_err(&counter, syrup::sequence![symbol!["add"]]).await?;
+    assert_eq!(
+        (record.method.as_deref(), record.position),
+        (Some("add"), Some(1))
+    );
+
+    let record = call_err(&counter, syrup::sequence![symbol!["add"], "two"]).await?;
+    assert_eq!(
+        (record.method.as_deref(), record.position),
+        (Some("add"), Some(1))
+    );
+
+    let record = call_err(&counter, syrup::sequence![symbol!["add"], 1u64, 2u64]).await?;
+    assert_eq!(
+        (record.method.as_deref(), record.position),
+        (Some("add"), Some(2))
+    );
+
+    let record = call_err(
+        &counter,
+        syrup::sequence![symbol!["configure"], keywords([("limit", 10)])],
+    )
+    .await?;
+    assert_eq!(record.position, Some(1));
+    assert!(
+        record.message.contains("missing keyword step"),
+        "{record:?}"
+    );
+
+    let record = call_err(
+        &counter,
+        syrup::sequence![symbol!["configure"], keywords([("step", 1), ("stride", 2)])],
+    )
+    .await?;
+    assert_eq!(record.position, Some(1));
+    assert!(
+        record.message.contains("unrecognized keyword stride"),
+        "{record:?}"
+    );
+
+    // nothing was added by the failed deliveries
+    assert_eq!(
+        call(&counter, syrup::sequence![symbol!["add"], 0u64]).await?,
+        0
+    );
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use futures::executor::block_on;
+use rexa::captp::object::{DeliverError, Promise, PromiseOutput, PromiseResolver};
+use syrup::{literal, sequence};
+
+#[test]
+fn then_and_catch() {
+    let res = block_on(Promise::fulfilled(sequence![literal![String; b"a"]]).then(
+        |mut args| async move {
+            args.stream.push(literal![String; b"b"]);
+            Ok::<_, DeliverError<'static>>(args)
+        },
+    ))
+    .unwrap();
+    assert_eq!(res.stream.len(), 2);
+
+    let res = block_on(
+        Promise::broken(literal![String; b"oops"])
+            .catch(|_| futures::future::ok::<_, DeliverError<'static>>(sequence![])),
+    );
+    assert!(res.unwrap().stream.is_empty());
+}
+
+#[test]
+fn all_and_race() {
+    let ok = Promise::fulfilled(sequence![]);
+    let broken = Promise::broken(literal![String; b"oops"]);
+    let pending = Promise::new(futures::future::pending::<PromiseOutput>());
+
+    assert_eq!(
+        block_on(Promise::all([ok.clone(), ok.clone()]))
+            .unwrap()
+            .len(),
+        2
+    );
+    assert!(block_on(Promise::all([ok.clone(), broken.clone()])).is_err());
+    assert!(block_on(Promise::race([pending.clone(), ok])).is_ok());
+    assert!(block_on(Promise::race([pending, broken])).is_err());
+}
+
+#[test]
+fn resolver() {
+    let (promise, resolver) = PromiseResolver::new();
+    let chained = promise
+        .clone()
+        .then(|args| futures::future::ok::<_, DeliverError<'static>>(args));
+    assert!(promise.peek().is_none());
+
+    block_on(resolver.fulfill(sequence![literal![String; b"a"]]));
+    assert_eq!(block_on(chained).unwrap().stream.len(), 1);
+    assert!(block_on(promise).is_ok());
+
+    let (promise, resolver) = PromiseResolver::new();
+    block_on(resolver.break_promise(literal![String; b"oops"]));
+    assert!(matches!(
+        block_on(promise).unwrap_err().as_ref(),
+        DeliverError::Broken(_)
+    ));
+}
@@ -0,0 +1,6 @@
+#[test]
+fn remote_object() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/remote_object/pass/*.rs");
+    t.compile_fail("tests/ui/remote_object/fail/*.rs");
+}
@@ -113,10 +113,31 @@ where
         let node_a = make_nl("crossed_hellos", 0).await?;
         let node_b = make_nl("crossed_hellos", 1).await?;
         let locator_a = node_a.locator::<String, String>();
-        let locator_b = node_a.locator::<String, String>();
+        let locator_b = node_b.locator::<String, String>();
+
+        let (ab, ba, a_acc, b_acc) = tokio::join!(
+            node_a.connect(&locator_b),
+            node_b.connect(&locator_a),
+            node_a.accept(),
+            node_b.accept()
+        );
+        for res in [ab, ba, a_acc, b_acc] {
+            res?;
+        }
 
-        tokio::spawn(async move { node_a.connect(&locator_b).await });
-        tokio::spawn(async move { node_b.connect(&locator_a).await });
+        // both nodes should have kept the same one of the two sessions
+        let session_ab = node_a.connect(&locator_b).await?;
+        let session_ba = node_b.connect(&locator_a).await?;
+        assert!(!session_ab.is_aborted());
+        assert!(!session_ba.is_aborted());
+        assert_eq!(
+            session_ab.signing_key().verifying_key(),
+            *session_ba.remote_vkey()
+        );
+        assert_eq!(
+            session_ba.signing_key().verifying_key(),
+            *session_ab.remote_vkey()
+        );
 
         Ok(())
     }) {
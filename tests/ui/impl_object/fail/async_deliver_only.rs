@@ -0,0 +1,13 @@
+use rexa::captp::object::ObjectError;
+
+struct Pinger;
+
+#[rexa::impl_object]
+impl Pinger {
+    #[deliver_only()]
+    async fn ping(&self) -> Result<(), ObjectError> {
+        Ok(())
+    }
+}
+
+fn main() {}
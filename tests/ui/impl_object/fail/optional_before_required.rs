@@ -0,0 +1,13 @@
+use rexa::captp::object::ObjectError;
+
+struct Greeter;
+
+#[rexa::impl_object]
+impl Greeter {
+    #[deliver()]
+    async fn greet(&self, title: Option<String>, name: String) -> Result<String, ObjectError> {
+        Ok(format!("hello, {}{name}", title.unwrap_or_default()))
+    }
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use rexa::captp::object::ObjectError;
+
+struct Summer;
+
+#[rexa::impl_object]
+impl Summer {
+    #[deliver()]
+    async fn sum(&self, #[arg(rest)] values: Option<u64>) -> Result<u64, ObjectError> {
+        Ok(values.unwrap_or_default())
+    }
+}
+
+fn main() {}
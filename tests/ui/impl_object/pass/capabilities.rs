@@ -0,0 +1,25 @@
+use rexa::captp::object::{ObjectError, RemoteObject};
+
+struct Registry {
+    objects: std::sync::Mutex<Vec<RemoteObject>>,
+}
+
+#[rexa::impl_object]
+impl Registry {
+    #[deliver_only()]
+    fn register(&self, obj: RemoteObject, backup: Option<RemoteObject>) -> Result<(), ObjectError> {
+        let mut objects = self.objects.lock().unwrap();
+        objects.push(obj);
+        objects.extend(backup);
+        Ok(())
+    }
+
+    #[deliver()]
+    fn register_all(&self, #[arg(rest)] objs: Vec<RemoteObject>) -> u64 {
+        let mut objects = self.objects.lock().unwrap();
+        objects.extend(objs);
+        objects.len() as u64
+    }
+}
+
+fn main() {}
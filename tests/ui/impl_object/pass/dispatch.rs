@@ -0,0 +1,42 @@
+use rexa::captp::object::{Keywords, ObjectError};
+use syrup::TokenTree;
+
+struct Counter {
+    count: std::sync::atomic::AtomicU64,
+}
+
+#[rexa::impl_object(describe)]
+impl Counter {
+    /// Add to the count, returning the new count.
+    #[deliver()]
+    async fn add(&self, amount: u64) -> Result<u64, ObjectError> {
+        Ok(self
+            .count
+            .fetch_add(amount, std::sync::atomic::Ordering::Relaxed)
+            + amount)
+    }
+
+    #[deliver(symbol = "add-all")]
+    async fn add_all(&self, #[arg(rest)] amounts: Vec<u64>) -> Result<u64, ObjectError> {
+        self.add(amounts.into_iter().sum()).await
+    }
+
+    #[deliver_only()]
+    fn reset(&self, to: Option<u64>) -> Result<(), ObjectError> {
+        self.count
+            .store(to.unwrap_or(0), std::sync::atomic::Ordering::Relaxed);
+        Ok(())
+    }
+
+    #[deliver_only()]
+    fn configure(&self, #[arg(dict)] _options: Keywords) -> Result<(), ObjectError> {
+        Ok(())
+    }
+
+    #[deliver_only(fallback)]
+    fn ignore(&self, #[arg(syrup = arg)] _first: TokenTree<'static>) -> Result<(), ObjectError> {
+        Ok(())
+    }
+}
+
+fn main() {}
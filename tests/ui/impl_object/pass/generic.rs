@@ -0,0 +1,42 @@
+use std::sync::{Arc, Mutex};
+
+use rexa::captp::{msg::DescExport, object::ObjectError, RemoteKey};
+
+struct Store<T> {
+    items: Mutex<Vec<T>>,
+}
+
+#[rexa::impl_object]
+impl<T> Store<T>
+where
+    T: for<'i> syrup::Decode<'i> + Send + 'static,
+{
+    #[deliver_only()]
+    fn push(&self, item: T) -> Result<(), ObjectError> {
+        self.items.lock().unwrap().push(item);
+        Ok(())
+    }
+
+    #[deliver_only()]
+    fn share(self: &Arc<Self>) -> Result<(), ObjectError> {
+        let _this: Arc<Self> = self.clone();
+        Ok(())
+    }
+
+    #[exported]
+    fn on_export(&self, position: DescExport) {
+        let _ = position;
+    }
+
+    #[gc]
+    fn on_gc(self: Arc<Self>, #[arg(remote_key)] _key: &RemoteKey) {
+        self.items.lock().unwrap().clear();
+    }
+
+    #[session_aborted]
+    fn on_abort(&self, reason: &str) {
+        let _ = reason;
+    }
+}
+
+fn main() {}
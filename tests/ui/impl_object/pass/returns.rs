@@ -0,0 +1,41 @@
+use std::collections::HashMap;
+
+use rexa::captp::object::ObjectError;
+
+struct Directory {
+    entries: HashMap<String, u64>,
+}
+
+#[rexa::impl_object]
+impl Directory {
+    #[deliver()]
+    fn len(&self) -> u64 {
+        self.entries.len() as u64
+    }
+
+    #[deliver()]
+    async fn get(&self, name: String) -> Option<u64> {
+        self.entries.get(&name).copied()
+    }
+
+    #[deliver()]
+    fn require(&self, name: String) -> Result<u64, String> {
+        self.entries
+            .get(&name)
+            .copied()
+            .ok_or_else(|| format!("no such entry: {name}"))
+    }
+
+    #[deliver()]
+    async fn check(&self, name: String) -> Result<(), ObjectError> {
+        match self.entries.contains_key(&name) {
+            true => Ok(()),
+            false => Err(ObjectError::unknown_method(name)),
+        }
+    }
+
+    #[deliver()]
+    fn ping(&self) {}
+}
+
+fn main() {}
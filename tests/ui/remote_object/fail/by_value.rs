@@ -0,0 +1,9 @@
+use rexa::captp::object::CallError;
+
+#[rexa::remote_object]
+pub trait Greeter {
+    #[deliver()]
+    async fn greet(self) -> Result<String, CallError>;
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+#[rexa::remote_object]
+pub trait Counter {
+    #[deliver()]
+    async fn count(&self) -> u64;
+}
+
+fn main() {}
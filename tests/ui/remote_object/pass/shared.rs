@@ -0,0 +1,67 @@
+use rexa::captp::object::{CallError, RemoteObject};
+use syrup::Encode;
+
+#[derive(Encode)]
+#[syrup(label = "registry:error")]
+pub struct RegistryError {
+    message: String,
+}
+
+impl From<CallError> for RegistryError {
+    fn from(error: CallError) -> Self {
+        Self {
+            message: error.to_string(),
+        }
+    }
+}
+
+#[rexa::remote_object]
+pub trait Registry {
+    #[deliver()]
+    async fn len(&self) -> Result<u64, RegistryError>;
+
+    #[deliver(symbol = "register-all")]
+    async fn register_all(&self, objs: Vec<u64>, backup: RemoteObject)
+        -> Result<(), RegistryError>;
+
+    #[deliver_only()]
+    async fn clear(&self) -> Result<(), CallError>;
+}
+
+struct Local;
+
+#[rexa::impl_object]
+impl Registry for Local {
+    async fn len(&self) -> Result<u64, RegistryError> {
+        Ok(0)
+    }
+
+    #[deliver(symbol = "register-all")]
+    async fn register_all(
+        &self,
+        _objs: Vec<u64>,
+        _backup: RemoteObject,
+    ) -> Result<(), RegistryError> {
+        Ok(())
+    }
+}
+
+/// Proxies can decode answers which are capabilities.
+#[rexa::remote_object]
+pub trait Mirror {
+    #[deliver()]
+    async fn backup(&self) -> Result<RemoteObject, CallError>;
+}
+
+/// Code generic over the trait accepts both local objects and proxies.
+async fn len_of(registry: &impl Registry) -> Result<u64, RegistryError> {
+    registry.len().await
+}
+
+#[allow(dead_code)]
+async fn use_both(local: Local, remote: RemoteRegistry) -> Result<u64, RegistryError> {
+    remote.clear().await?;
+    Ok(len_of(&local).await? + len_of(&remote).await?)
+}
+
+fn main() {}